@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+/// Asks the frontmost browser for its active tab URL. Returns `None` when the
+/// frontmost app isn't an enabled browser, permissions are missing, the
+/// platform is unsupported, or the lookup doesn't finish within `timeout`.
+pub async fn active_tab_url(enabled_browsers: &[String], timeout: Duration) -> Option<String> {
+    match tokio::time::timeout(timeout, query_active_tab(enabled_browsers)).await {
+        Ok(url) => url.filter(|url| url.starts_with("http://") || url.starts_with("https://")),
+        Err(_) => None,
+    }
+}
+
+/// Name of the frontmost app (macOS app name or Windows process name), or
+/// `None` when it can't be determined within `timeout`.
+pub async fn frontmost_app(timeout: Duration) -> Option<String> {
+    tokio::time::timeout(timeout, query_frontmost_app()).await.ok().flatten()
+}
+
+/// Domain used to tag clips captured from a page, without a leading `www.`.
+pub fn domain_tag(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    Some(host.trim_start_matches("www.").to_string())
+}
+
+#[cfg(target_os = "macos")]
+async fn query_active_tab(enabled_browsers: &[String]) -> Option<String> {
+    let frontmost = query_frontmost_app().await?;
+
+    if !enabled_browsers.iter().any(|browser| browser == &frontmost) {
+        return None;
+    }
+
+    let script = match frontmost.as_str() {
+        "Safari" | "Safari Technology Preview" => {
+            format!("tell application \"{}\" to get URL of front document", frontmost)
+        }
+        "Google Chrome" | "Microsoft Edge" | "Brave Browser" | "Arc" | "Chromium" => {
+            format!("tell application \"{}\" to get URL of active tab of front window", frontmost)
+        }
+        // Firefox has no scripting interface for its tabs
+        _ => return None,
+    };
+
+    run_osascript(&script).await
+}
+
+#[cfg(target_os = "macos")]
+async fn query_frontmost_app() -> Option<String> {
+    run_osascript(
+        "tell application \"System Events\" to get name of first application process whose frontmost is true",
+    )
+    .await
+}
+
+#[cfg(target_os = "macos")]
+async fn run_osascript(script: &str) -> Option<String> {
+    let output = tokio::process::Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .ok()?;
+
+    // Missing automation permission shows up as a non-zero exit; skip silently
+    if !output.status.success() {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(target_os = "windows")]
+async fn query_active_tab(enabled_browsers: &[String]) -> Option<String> {
+    // UI Automation via PowerShell: find the foreground window's process and,
+    // for Chromium-based browsers, read the address bar edit control.
+    const SCRIPT: &str = r#"
+Add-Type -AssemblyName UIAutomationClient
+Add-Type @"
+using System;
+using System.Runtime.InteropServices;
+public class Fg { [DllImport("user32.dll")] public static extern IntPtr GetForegroundWindow();
+  [DllImport("user32.dll")] public static extern uint GetWindowThreadProcessId(IntPtr h, out uint p); }
+"@
+$h = [Fg]::GetForegroundWindow()
+$procId = 0
+[Fg]::GetWindowThreadProcessId($h, [ref]$procId) | Out-Null
+$name = (Get-Process -Id $procId).ProcessName
+Write-Output $name
+$root = [System.Windows.Automation.AutomationElement]::FromHandle($h)
+$cond = New-Object System.Windows.Automation.PropertyCondition([System.Windows.Automation.AutomationElement]::ControlTypeProperty, [System.Windows.Automation.ControlType]::Edit)
+$edit = $root.FindFirst([System.Windows.Automation.TreeScope]::Descendants, $cond)
+if ($edit) { Write-Output $edit.GetCurrentPropertyValue([System.Windows.Automation.ValuePattern]::ValueProperty) }
+"#;
+
+    let output = tokio::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines().map(str::trim);
+    let process = lines.next()?;
+    if !enabled_browsers.iter().any(|browser| browser.eq_ignore_ascii_case(process)) {
+        return None;
+    }
+
+    // Chromium hides the scheme in the address bar
+    let address = lines.next().filter(|line| !line.is_empty())?;
+    if address.starts_with("http://") || address.starts_with("https://") {
+        Some(address.to_string())
+    } else {
+        Some(format!("https://{}", address))
+    }
+}
+
+#[cfg(target_os = "windows")]
+async fn query_frontmost_app() -> Option<String> {
+    const SCRIPT: &str = r#"
+Add-Type @"
+using System;
+using System.Runtime.InteropServices;
+public class Fg { [DllImport("user32.dll")] public static extern IntPtr GetForegroundWindow();
+  [DllImport("user32.dll")] public static extern uint GetWindowThreadProcessId(IntPtr h, out uint p); }
+"@
+$procId = 0
+[Fg]::GetWindowThreadProcessId([Fg]::GetForegroundWindow(), [ref]$procId) | Out-Null
+Write-Output (Get-Process -Id $procId).ProcessName
+"#;
+
+    let output = tokio::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+async fn query_frontmost_app() -> Option<String> {
+    None
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+async fn query_active_tab(_enabled_browsers: &[String]) -> Option<String> {
+    None
+}
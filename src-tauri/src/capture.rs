@@ -0,0 +1,450 @@
+use chrono::Utc;
+use serde::Serialize;
+use uuid::Uuid;
+use crate::browser;
+use crate::clipboard::{LastCapture, Selection, SelfWriteState};
+use crate::config::{AppConfig, SummaryStrategy};
+use crate::database::{self, ClipItem, ClipMetadata, ClipPreview, ContentType};
+use crate::urls;
+
+// Captures shorter than this (in bytes) are ignored
+const MIN_CAPTURE_BYTES: usize = 4;
+
+// Length, in characters, of summaries written without the model
+const SUMMARY_CHARS: usize = 50;
+
+/// `source` of clips copied to the clipboard
+pub const CLIPBOARD_SOURCE: &str = "clipboard";
+/// `source` of clips picked up from the Linux primary selection
+pub const PRIMARY_SELECTION_SOURCE: &str = "primary-selection";
+
+/// What the monitor would store for some text, from `preview_capture`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CapturePreview {
+    pub would_capture: bool,
+    /// Why the text would be ignored, when it would be
+    pub skip_reason: Option<String>,
+    pub summary: String,
+    pub tags: Vec<String>,
+    pub content_type: ContentType,
+    pub urls: Vec<String>,
+    pub large: bool,
+}
+
+/// Why the monitor would ignore `content`, or `None` if it would keep it.
+pub fn skip_reason(content: &str) -> Option<&'static str> {
+    if content.trim().is_empty() {
+        Some("Content is blank")
+    } else if content.len() < MIN_CAPTURE_BYTES {
+        Some("Content is too short")
+    } else {
+        None
+    }
+}
+
+/// Decides which clipboard reads a monitor captures, independent of where
+/// the text comes from and where it's stored: change detection, the size
+/// filters, our own writes and text the other monitor already captured.
+pub struct CapturePipeline {
+    primary: bool,
+    self_write: SelfWriteState,
+    last_capture: LastCapture,
+    last_content: String,
+    last_read: String,
+}
+
+impl CapturePipeline {
+    pub fn new(selection: Selection, self_write: SelfWriteState, last_capture: LastCapture) -> Self {
+        Self {
+            primary: selection == Selection::Primary,
+            self_write,
+            last_capture,
+            last_content: String::new(),
+            last_read: String::new(),
+        }
+    }
+
+    /// Treats `content` as handled without capturing it, e.g. while paused.
+    pub fn mark_seen(&mut self, content: String) {
+        self.last_content = content;
+    }
+
+    /// Treats `content` as captured already, e.g. by the previous session.
+    pub fn mark_captured(&mut self, content: String) {
+        self.last_capture.replace(&content);
+        self.last_content = content;
+    }
+
+    /// Whether this read of `content` should be captured. The primary
+    /// selection changes while the user drags, so it must read the same
+    /// twice in a row first; `min_bytes` applies on top of `skip_reason`.
+    pub fn admit(&mut self, content: &str, min_bytes: usize) -> bool {
+        if self.primary && content != self.last_read {
+            self.last_read = content.to_string();
+            return false;
+        }
+        if content == self.last_content || skip_reason(content).is_some() || content.len() < min_bytes {
+            return false;
+        }
+        self.last_content = content.to_string();
+
+        // Skip text we just wrote ourselves (copy_clip etc.). We only ever
+        // write the clipboard, so the record is left for its monitor.
+        if !self.primary && self.self_write.is_self_write(content) {
+            return false;
+        }
+        self.last_capture.replace(content)
+    }
+}
+
+/// Builds the clip the monitor stores for `content`: summary, tags, type
+/// and URLs. `lossy` marks text decoded with replacement characters;
+/// `source_url` is the browser page it came from, if known.
+pub fn build_clip(
+    content: &str,
+    lossy: bool,
+    source: &str,
+    source_url: Option<String>,
+    config: &AppConfig,
+) -> ClipItem {
+    let summary = quick_summary(content, config.summary_strategy);
+
+    let urls = urls::extract_urls(content);
+
+    let mut tags = database::detect_tags(content, &urls);
+    if lossy {
+        tags.push("binary".to_string());
+    }
+
+    let content_type = ContentType::detect(content, &tags);
+
+    // Advisory only: the clip is still saved, the UI decides
+    if config.is_large_clip(content.len()) {
+        tags.push("large".to_string());
+    }
+
+    if let Some(domain) = source_url.as_deref().and_then(browser::domain_tag) {
+        tags.push(domain);
+    }
+
+    let captured_at = Utc::now();
+    ClipItem {
+        id: Uuid::new_v4().to_string(),
+        content: content.to_string(),
+        summary,
+        tags,
+        created_at: captured_at,
+        last_modified: captured_at,
+        source: Some(source.to_string()),
+        // Computed by `Database::insert_clip` with the configured model
+        embedding: None,
+        urls,
+        embed_truncated: false,
+        content_type,
+        source_url,
+        rewrite_history: Vec::new(),
+        masked: false,
+        keywords: Vec::new(),
+        metadata: ClipMetadata::default(),
+        seq: None,
+        code_language: None,
+    }
+}
+
+/// What to send with `large-clip-captured` for `clip`, or `None` if it is
+/// under the warning size.
+pub fn large_clip_notice(clip: &ClipItem, config: &AppConfig) -> Option<ClipPreview> {
+    config
+        .is_large_clip(clip.content.len())
+        .then(|| ClipPreview::new(clip, config.preview_chars))
+}
+
+/// The summary written at capture time. `llm-when-long` starts out like
+/// `truncate`; the model's summary replaces it later (see
+/// `needs_model_summary`).
+pub fn quick_summary(content: &str, strategy: SummaryStrategy) -> String {
+    match strategy {
+        SummaryStrategy::FirstLine => {
+            let line = content.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+            shorten(line)
+        }
+        SummaryStrategy::Truncate | SummaryStrategy::LlmWhenLong => {
+            if content.chars().count() > SUMMARY_CHARS {
+                shorten(content)
+            } else {
+                content.lines().next().unwrap_or(content).to_string()
+            }
+        }
+    }
+}
+
+/// Whether `content` should be summarized by the model under `config`.
+pub fn needs_model_summary(content: &str, config: &AppConfig) -> bool {
+    config.summary_strategy == SummaryStrategy::LlmWhenLong && content.chars().count() > config.long_clip_chars
+}
+
+fn shorten(text: &str) -> String {
+    if text.chars().count() > SUMMARY_CHARS {
+        format!("{}...", text.chars().take(SUMMARY_CHARS - 3).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+/// Runs `content` through the same steps as a real capture without
+/// storing anything. The source app and browser page aren't looked up.
+pub fn preview(content: &str, config: &AppConfig) -> CapturePreview {
+    let clip = build_clip(content, false, CLIPBOARD_SOURCE, None, config);
+    let skip_reason = skip_reason(content);
+    CapturePreview {
+        would_capture: skip_reason.is_none(),
+        skip_reason: skip_reason.map(str::to_string),
+        summary: clip.summary,
+        tags: clip.tags,
+        content_type: clip.content_type,
+        urls: clip.urls,
+        large: config.is_large_clip(content.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::tests::FakeClipboard;
+    use crate::clipboard::{ClipboardSource, MonitorPause};
+    use crate::config::NonUtf8Clipboard;
+
+    fn pipeline(selection: Selection) -> CapturePipeline {
+        CapturePipeline::new(selection, SelfWriteState::default(), LastCapture::default())
+    }
+
+    // One monitor poll: what it would capture from the clipboard's next read
+    fn poll(clipboard: &mut FakeClipboard, pipeline: &mut CapturePipeline, min_bytes: usize) -> Option<String> {
+        let read = clipboard.read(NonUtf8Clipboard::Lossy).unwrap()?;
+        pipeline.admit(&read.text, min_bytes).then_some(read.text)
+    }
+
+    #[test]
+    fn unchanged_clipboard_is_captured_once() {
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["hello world", "hello world", "second copy"]);
+        let mut pipeline = pipeline(Selection::Clipboard);
+
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0).as_deref(), Some("hello world"));
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0), None);
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0).as_deref(), Some("second copy"));
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0), None);
+    }
+
+    #[test]
+    fn blank_and_short_text_is_skipped() {
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["ok", "   \n\t  ", "long enough"]);
+        let mut pipeline = pipeline(Selection::Clipboard);
+
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0), None);
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0), None);
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0).as_deref(), Some("long enough"));
+    }
+
+    #[test]
+    fn own_writes_are_not_captured() {
+        let self_write = SelfWriteState::default();
+        let mut pipeline = CapturePipeline::new(Selection::Clipboard, self_write.clone(), LastCapture::default());
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["copied by the user"]);
+        assert!(poll(&mut clipboard, &mut pipeline, 0).is_some());
+
+        // `copy_clip` records the text before it reaches the clipboard
+        self_write.record("pasted from history");
+        clipboard.copy("pasted from history");
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0), None);
+    }
+
+    #[test]
+    fn recopying_our_own_write_is_captured() {
+        let self_write = SelfWriteState::default();
+        let mut pipeline = CapturePipeline::new(Selection::Clipboard, self_write.clone(), LastCapture::default());
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &[]);
+
+        self_write.record("pasted from history");
+        clipboard.copy("pasted from history");
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0), None);
+
+        clipboard.copy("something in between");
+        clipboard.copy("pasted from history");
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0).as_deref(), Some("something in between"));
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0).as_deref(), Some("pasted from history"));
+    }
+
+    #[test]
+    fn own_writes_do_not_suppress_the_primary_selection() {
+        let self_write = SelfWriteState::default();
+        let mut primary = CapturePipeline::new(Selection::Primary, self_write.clone(), LastCapture::default());
+        let mut selection = FakeClipboard::new(Selection::Primary, &["selected after pasting"]);
+
+        self_write.record("selected after pasting");
+        assert_eq!(poll(&mut selection, &mut primary, 0), None);
+        assert!(poll(&mut selection, &mut primary, 0).is_some());
+        // The record is left for the clipboard monitor
+        assert!(self_write.is_self_write("selected after pasting"));
+    }
+
+    #[test]
+    fn copies_made_while_paused_are_not_captured_on_resume() {
+        let pause = MonitorPause::default();
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["copied while paused"]);
+        let mut pipeline = pipeline(Selection::Clipboard);
+
+        // The monitor's paused branch
+        pause.set_paused(true);
+        let read = clipboard.read(NonUtf8Clipboard::Lossy).unwrap().unwrap();
+        assert!(pause.is_paused());
+        pipeline.mark_seen(read.text);
+
+        pause.set_paused(false);
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0), None);
+        clipboard.copy("copied after resuming");
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0).as_deref(), Some("copied after resuming"));
+    }
+
+    #[test]
+    fn nothing_is_captured_without_autostart_until_resumed() {
+        let pause = MonitorPause::default();
+        pause.apply_autostart(&AppConfig::default());
+        assert!(!pause.is_paused());
+
+        pause.apply_autostart(&AppConfig { autostart_monitoring: false, ..AppConfig::default() });
+        assert!(pause.is_paused());
+
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["on the clipboard at launch"]);
+        let mut pipeline = pipeline(Selection::Clipboard);
+        // The monitor's loop, including its paused branch
+        let monitor_poll = |clipboard: &mut FakeClipboard, pipeline: &mut CapturePipeline| {
+            let read = clipboard.read(NonUtf8Clipboard::Lossy).unwrap()?;
+            if pause.is_paused() {
+                pipeline.mark_seen(read.text);
+                return None;
+            }
+            pipeline.admit(&read.text, 0).then_some(read.text)
+        };
+
+        assert_eq!(monitor_poll(&mut clipboard, &mut pipeline), None);
+        clipboard.copy("copied before resuming");
+        assert_eq!(monitor_poll(&mut clipboard, &mut pipeline), None);
+
+        pause.set_paused(false);
+        assert_eq!(monitor_poll(&mut clipboard, &mut pipeline), None);
+        clipboard.copy("copied after resuming");
+        assert_eq!(monitor_poll(&mut clipboard, &mut pipeline).as_deref(), Some("copied after resuming"));
+    }
+
+    #[test]
+    fn primary_selection_waits_for_the_selection_to_settle() {
+        let mut clipboard = FakeClipboard::new(Selection::Primary, &["sel", "selected te", "selected text"]);
+        let mut pipeline = pipeline(Selection::Primary);
+
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0), None);
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0), None);
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0), None);
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0).as_deref(), Some("selected text"));
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 0), None);
+    }
+
+    #[test]
+    fn primary_selection_applies_its_minimum_length() {
+        let mut clipboard = FakeClipboard::new(Selection::Primary, &["a few words"]);
+        let mut pipeline = pipeline(Selection::Primary);
+
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 20), None);
+        assert_eq!(poll(&mut clipboard, &mut pipeline, 20), None);
+    }
+
+    #[test]
+    fn text_is_stored_once_across_both_selections() {
+        let last_capture = LastCapture::default();
+        let self_write = SelfWriteState::default();
+        let mut primary = CapturePipeline::new(Selection::Primary, self_write.clone(), last_capture.clone());
+        let mut clipboard = CapturePipeline::new(Selection::Clipboard, self_write, last_capture);
+        let mut selection = FakeClipboard::new(Selection::Primary, &["selected then copied"]);
+        let mut copies = FakeClipboard::new(Selection::Clipboard, &["selected then copied"]);
+
+        assert_eq!(poll(&mut selection, &mut primary, 0), None);
+        assert!(poll(&mut selection, &mut primary, 0).is_some());
+        assert_eq!(poll(&mut copies, &mut clipboard, 0), None);
+    }
+
+    #[test]
+    fn undecodable_text_is_kept_or_skipped_per_policy() {
+        let config = AppConfig::default();
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["binary\0payload"]);
+
+        let read = clipboard.read(NonUtf8Clipboard::Lossy).unwrap().unwrap();
+        let clip = build_clip(&read.text, read.lossy, CLIPBOARD_SOURCE, None, &config);
+        assert_eq!(clip.content, "binary\u{FFFD}payload");
+        assert!(clip.tags.iter().any(|tag| tag == "binary"));
+
+        assert!(clipboard.read(NonUtf8Clipboard::Skip).unwrap().is_none());
+    }
+
+    #[test]
+    fn build_clip_tags_what_it_detects() {
+        let config = AppConfig::default();
+        let content = "let docs = https://example.com/guide";
+        let clip = build_clip(content, true, CLIPBOARD_SOURCE, Some("https://www.rust-lang.org/learn".to_string()), &config);
+
+        assert_eq!(clip.urls, ["https://example.com/guide"]);
+        for tag in ["url", "code", "binary", "rust-lang.org"] {
+            assert!(clip.tags.iter().any(|t| t == tag), "missing {} in {:?}", tag, clip.tags);
+        }
+        assert_eq!(clip.content_type, ContentType::Code);
+        assert_eq!(clip.source.as_deref(), Some(CLIPBOARD_SOURCE));
+        assert!(clip.embedding.is_none());
+    }
+
+    #[test]
+    fn large_clip_notice_fires_only_over_the_threshold() {
+        let config = AppConfig { large_clip_warning_bytes: 1024, ..AppConfig::default() };
+
+        let big = build_clip(&"x".repeat(2048), false, CLIPBOARD_SOURCE, None, &config);
+        let notice = large_clip_notice(&big, &config).expect("an over-threshold clip is announced");
+        assert_eq!(notice.size_bytes, 2048);
+        assert!(big.tags.iter().any(|tag| tag == "large"));
+
+        let small = build_clip("a short note", false, CLIPBOARD_SOURCE, None, &config);
+        assert!(large_clip_notice(&small, &config).is_none());
+        assert!(!small.tags.iter().any(|tag| tag == "large"));
+    }
+
+    #[tokio::test]
+    async fn preview_matches_what_a_capture_stores() {
+        let (db, _dir) = crate::database::tests::test_db().await;
+        let config = db.config().clone();
+        let prose = "a long paragraph of prose ".repeat(10);
+
+        for content in [
+            "let total = items.len();",
+            "https://example.com/pricing",
+            "Meeting notes\nfirst line is the summary",
+            prose.as_str(),
+        ] {
+            let previewed = preview(content, &config);
+            assert!(previewed.would_capture, "{:?}", content);
+            assert!(pipeline(Selection::Clipboard).admit(content, 0));
+
+            let clip = build_clip(content, false, CLIPBOARD_SOURCE, None, &config);
+            db.insert_clip(&clip).await.unwrap();
+            let stored = db.get_clip(&clip.id).await.unwrap().unwrap();
+
+            assert_eq!(previewed.summary, stored.summary, "{:?}", content);
+            assert_eq!(previewed.tags, stored.tags, "{:?}", content);
+            assert_eq!(previewed.content_type, stored.content_type, "{:?}", content);
+            assert_eq!(previewed.urls, stored.urls, "{:?}", content);
+        }
+
+        for content in ["ok", "  \n  "] {
+            let previewed = preview(content, &config);
+            assert!(!previewed.would_capture);
+            assert!(previewed.skip_reason.is_some());
+            assert!(!pipeline(Selection::Clipboard).admit(content, 0));
+        }
+        assert_eq!(db.get_recent_clips(10).await.unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,37 @@
+use std::sync::OnceLock;
+use regex::{Captures, Regex};
+
+/// Renumbers the `[n]` markers in a model answer that cites `source_count`
+/// numbered sources. Valid markers become `[1]`, `[2]`, ... in order of
+/// first use; markers outside `1..=source_count` (or not numbers at all)
+/// are removed. Returns the rewritten answer and the zero-based indices of
+/// the cited sources, in their new order.
+pub fn renumber_citations(answer: &str, source_count: usize) -> (String, Vec<usize>) {
+    let mut cited: Vec<usize> = Vec::new();
+
+    let rewritten = citation_marker().replace_all(answer, |caps: &Captures| {
+        let leading = &caps[0][..caps[0].len() - caps[1].len() - 2];
+        match caps[1].parse::<usize>() {
+            Ok(number) if (1..=source_count).contains(&number) => {
+                let index = number - 1;
+                let position = match cited.iter().position(|&seen| seen == index) {
+                    Some(position) => position,
+                    None => {
+                        cited.push(index);
+                        cited.len() - 1
+                    }
+                };
+                format!("{}[{}]", leading, position + 1)
+            }
+            _ => String::new(),
+        }
+    });
+
+    (rewritten.into_owned(), cited)
+}
+
+// `[n]` with the whitespace before it, compiled once
+fn citation_marker() -> &'static Regex {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    MARKER.get_or_init(|| Regex::new(r"\s?\[(\d+)\]").unwrap())
+}
@@ -0,0 +1,300 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use arboard::Clipboard;
+use serde::Serialize;
+use crate::config::{AppConfig, NonUtf8Clipboard};
+
+// How long our own clipboard write is ignored by the monitor. Long enough to
+// cover a few poll cycles, short enough that a genuine re-copy still registers.
+const SELF_WRITE_EXPIRY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy)]
+pub struct LastWrite {
+    pub hash: u64,
+    pub expires_at: Instant,
+}
+
+/// Shared record of the last clipboard write made by ClipSage itself.
+#[derive(Debug, Clone, Default)]
+pub struct SelfWriteState(Arc<Mutex<Option<LastWrite>>>);
+
+impl SelfWriteState {
+    /// Writes `text` to the system clipboard, recording it first so the
+    /// monitor can never observe the write before it knows to ignore it.
+    pub fn write_text(&self, text: &str) -> Result<()> {
+        self.record(text);
+
+        let mut clipboard = Clipboard::new().map_err(|e| anyhow!("Failed to open clipboard: {}", e))?;
+        clipboard
+            .set_text(text.to_string())
+            .map_err(|e| anyhow!("Failed to write clipboard: {}", e))
+    }
+
+    pub fn record(&self, text: &str) {
+        let mut last_write = self.0.lock().unwrap();
+        *last_write = Some(LastWrite {
+            hash: content_hash(text),
+            expires_at: Instant::now() + SELF_WRITE_EXPIRY,
+        });
+    }
+
+    /// Returns true if `text` is our own recent write. A match consumes the
+    /// record so later identical copies are captured normally.
+    pub fn is_self_write(&self, text: &str) -> bool {
+        let mut last_write = self.0.lock().unwrap();
+        match *last_write {
+            Some(write) if write.expires_at > Instant::now() && write.hash == content_hash(text) => {
+                *last_write = None;
+                true
+            }
+            Some(write) if write.expires_at <= Instant::now() => {
+                *last_write = None;
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Which selection a monitor watches. `Primary` is the X11/Wayland
+/// middle-click selection and reads as empty on other platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    Clipboard,
+    Primary,
+}
+
+/// The clip last captured by either monitor, so text that is selected and
+/// then explicitly copied (or the reverse) is only stored once.
+#[derive(Debug, Clone, Default)]
+pub struct LastCapture(Arc<Mutex<Option<u64>>>);
+
+impl LastCapture {
+    /// Records `text` as captured. Returns false if it was already the last
+    /// capture, in which case it shouldn't be stored again.
+    pub fn replace(&self, text: &str) -> bool {
+        let hash = content_hash(text);
+        let mut last = self.0.lock().unwrap();
+        if *last == Some(hash) {
+            return false;
+        }
+        *last = Some(hash);
+        true
+    }
+}
+
+/// Whether the monitors are capturing. Shared between clones.
+#[derive(Debug, Clone, Default)]
+pub struct MonitorPause(Arc<AtomicBool>);
+
+impl MonitorPause {
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.0.store(paused, Ordering::Relaxed);
+    }
+
+    /// Starts out paused when `autostart_monitoring` is off, so nothing is
+    /// captured until `resume_monitoring`.
+    pub fn apply_autostart(&self, config: &AppConfig) {
+        if !config.autostart_monitoring {
+            self.set_paused(true);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitoringStatus {
+    pub paused: bool,
+}
+
+/// Text read from the clipboard. `lossy` means some of the original bytes
+/// weren't valid UTF-8 (or were NULs) and now appear as U+FFFD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardText {
+    pub text: String,
+    pub lossy: bool,
+}
+
+/// Where a monitor reads copied text from. `SystemClipboard` is the real
+/// one; the monitor only needs this, so it can be driven by a scripted
+/// source instead.
+pub trait ClipboardSource {
+    fn selection(&self) -> Selection;
+
+    /// The current text, as `read_selection` returns it.
+    fn read(&mut self, policy: NonUtf8Clipboard) -> Result<Option<ClipboardText>>;
+}
+
+/// The platform clipboard (or primary selection), via arboard.
+pub struct SystemClipboard {
+    clipboard: Clipboard,
+    selection: Selection,
+}
+
+impl SystemClipboard {
+    pub fn new(selection: Selection) -> Result<Self> {
+        let clipboard = Clipboard::new().map_err(|e| anyhow!("Failed to initialize clipboard: {}", e))?;
+        Ok(Self { clipboard, selection })
+    }
+}
+
+impl ClipboardSource for SystemClipboard {
+    fn selection(&self) -> Selection {
+        self.selection
+    }
+
+    fn read(&mut self, policy: NonUtf8Clipboard) -> Result<Option<ClipboardText>> {
+        read_selection(&mut self.clipboard, self.selection, policy)
+    }
+}
+
+/// Reads `selection`'s text, applying `policy` to content that isn't valid
+/// UTF-8. Returns `Ok(None)` when there is no text or it was skipped, and
+/// `Err` when the platform refused to convert it at all.
+pub fn read_selection(clipboard: &mut Clipboard, selection: Selection, policy: NonUtf8Clipboard) -> Result<Option<ClipboardText>> {
+    match get_text(clipboard, selection) {
+        // Some platform backends already substitute U+FFFD for bad bytes
+        Ok(text) => Ok(apply_policy(sanitize(text), policy)),
+        Err(arboard::Error::ContentNotAvailable) => Ok(None),
+        Err(arboard::Error::ConversionFailure) => {
+            Err(anyhow!("Clipboard text is not valid UTF-8 and could not be converted"))
+        }
+        Err(e) => Err(anyhow!("Failed to read clipboard: {}", e)),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn get_text(clipboard: &mut Clipboard, selection: Selection) -> Result<String, arboard::Error> {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+    match selection {
+        Selection::Clipboard => clipboard.get_text(),
+        Selection::Primary => clipboard.get().clipboard(LinuxClipboardKind::Primary).text(),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_text(clipboard: &mut Clipboard, selection: Selection) -> Result<String, arboard::Error> {
+    match selection {
+        Selection::Clipboard => clipboard.get_text(),
+        Selection::Primary => Err(arboard::Error::ContentNotAvailable),
+    }
+}
+
+// NULs would silently cut the text short in FTS, so treat them like any
+// other undecodable byte.
+fn sanitize(text: String) -> ClipboardText {
+    let lossy = text.contains(char::REPLACEMENT_CHARACTER) || text.contains('\0');
+    let text = if text.contains('\0') {
+        text.replace('\0', "\u{FFFD}")
+    } else {
+        text
+    };
+    ClipboardText { text, lossy }
+}
+
+fn apply_policy(decoded: ClipboardText, policy: NonUtf8Clipboard) -> Option<ClipboardText> {
+    match (decoded.lossy, policy) {
+        (true, NonUtf8Clipboard::Skip) => None,
+        _ => Some(decoded),
+    }
+}
+
+fn content_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::VecDeque;
+    use super::*;
+
+    /// A `ClipboardSource` that returns scripted copies in order, then keeps
+    /// returning the last one like a clipboard nobody touches.
+    pub(crate) struct FakeClipboard {
+        selection: Selection,
+        copies: VecDeque<ClipboardText>,
+        current: Option<ClipboardText>,
+    }
+
+    impl FakeClipboard {
+        pub(crate) fn new(selection: Selection, copies: &[&str]) -> Self {
+            let mut clipboard = Self {
+                selection,
+                copies: VecDeque::new(),
+                current: None,
+            };
+            for text in copies {
+                clipboard.copy(text);
+            }
+            clipboard
+        }
+
+        /// Queues `text` as the next thing the user copies.
+        pub(crate) fn copy(&mut self, text: &str) {
+            self.copies.push_back(sanitize(text.to_string()));
+        }
+    }
+
+    impl ClipboardSource for FakeClipboard {
+        fn selection(&self) -> Selection {
+            self.selection
+        }
+
+        fn read(&mut self, policy: NonUtf8Clipboard) -> Result<Option<ClipboardText>> {
+            if let Some(next) = self.copies.pop_front() {
+                self.current = Some(next);
+            }
+            Ok(self.current.clone().and_then(|text| apply_policy(text, policy)))
+        }
+    }
+
+    #[test]
+    fn self_write_is_recognized_once() {
+        let self_write = SelfWriteState::default();
+        self_write.record("copied from history");
+
+        assert!(!self_write.is_self_write("something else"));
+        assert!(self_write.is_self_write("copied from history"));
+        // The user copying the same text again is a real capture
+        assert!(!self_write.is_self_write("copied from history"));
+    }
+
+    #[test]
+    fn expired_self_write_is_ignored() {
+        let self_write = SelfWriteState::default();
+        *self_write.0.lock().unwrap() = Some(LastWrite {
+            hash: content_hash("written a while ago"),
+            expires_at: Instant::now() - Duration::from_millis(1),
+        });
+
+        assert!(!self_write.is_self_write("written a while ago"));
+        assert!(self_write.0.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn fake_clipboard_repeats_the_last_copy() {
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["first", "second"]);
+        let mut read = || clipboard.read(NonUtf8Clipboard::Lossy).unwrap().map(|read| read.text);
+        assert_eq!(read().as_deref(), Some("first"));
+        assert_eq!(read().as_deref(), Some("second"));
+        assert_eq!(read().as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn text_with_nuls_is_lossy_and_skippable() {
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["bad\0byte"]);
+        let read = clipboard.read(NonUtf8Clipboard::Lossy).unwrap().unwrap();
+        assert!(read.lossy);
+        assert_eq!(read.text, "bad\u{FFFD}byte");
+        assert_eq!(clipboard.read(NonUtf8Clipboard::Skip).unwrap(), None);
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::ollama::{DEFAULT_OLLAMA_MODEL, DEFAULT_OLLAMA_URL};
+use crate::terms::DEFAULT_STOPWORDS;
+
+/// Upper bound accepted for `summary_max_tokens`
+pub const MAX_SUMMARY_TOKENS: u32 = 1024;
+
+/// Upper bound accepted for `capture_grace_ms`
+pub const MAX_CAPTURE_GRACE_MS: u64 = 60_000;
+
+/// Bounds accepted for `clipboard_poll_ms`
+pub const MIN_CLIPBOARD_POLL_MS: u64 = 50;
+pub const MAX_CLIPBOARD_POLL_MS: u64 = 10_000;
+
+/// What to do with clips longer than `AppConfig::embedding_max_chars`.
+/// Either way the full content stays in the FTS index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizedEmbedding {
+    /// Embed only the leading `embedding_max_chars` characters
+    Truncate,
+    /// Store the clip without an embedding
+    Skip,
+}
+
+/// How query and clip embeddings are compared in semantic search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimilarityMetric {
+    Cosine,
+    DotProduct,
+    /// Ranked by ascending distance (scored as negative distance)
+    Euclidean,
+}
+
+/// What the monitor does with clipboard text that isn't valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NonUtf8Clipboard {
+    /// Keep it with invalid bytes shown as U+FFFD and tag the clip `binary`
+    Lossy,
+    /// Don't capture it
+    Skip,
+}
+
+/// FTS5 tokenizer used for the search index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FtsTokenizer {
+    /// Plain word matching
+    Unicode61,
+    /// English stemming on top of unicode61: "running" matches "run"
+    Porter,
+}
+
+/// How `merge_duplicates` reconciles the tags of clips it collapses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateTagPolicy {
+    /// Keep every tag from every copy
+    Union,
+    /// Keep only the tags of the most recent copy
+    LastWins,
+}
+
+/// How a clip's summary is written at capture time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SummaryStrategy {
+    /// The first non-blank line, shortened if needed
+    FirstLine,
+    /// The start of the content, or its first line when it's short
+    Truncate,
+    /// Like `Truncate`, then the model rewrites summaries of clips longer
+    /// than `long_clip_chars`
+    LlmWhenLong,
+}
+
+/// How clipboard text copied from a particular app is captured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppCaptureMode {
+    Capture,
+    /// Store only a hash, length, source and time, never the content
+    HashOnly,
+    /// Don't capture at all
+    Exclude,
+}
+
+/// Which captures raise an OS notification. All off by default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationRules {
+    /// Captures tagged with any of these
+    pub on_tags: Vec<String>,
+    /// Copies stored as a hash only (see `AppCaptureMode::HashOnly`)
+    pub on_redaction: bool,
+    /// Captures that couldn't be saved
+    pub on_capture_failure: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Base URL of the Ollama server
+    pub ollama_url: String,
+    /// Model used for embeddings and generation. Clips embedded by another
+    /// model drop out of semantic search until reindexed.
+    pub ollama_model: String,
+    /// Proxy URL for requests to Ollama; unset falls back to the
+    /// `HTTP(S)_PROXY` environment variables
+    pub ollama_proxy: Option<String>,
+    /// Extra root certificate (PEM or DER) trusted for Ollama's TLS
+    pub ollama_ca_cert_path: Option<String>,
+    /// Sent as `Authorization: Bearer` for Ollama behind a reverse proxy
+    pub ollama_bearer_token: Option<String>,
+    /// How often the clipboard is checked for new content
+    pub clipboard_poll_ms: u64,
+    pub embedding_max_chars: usize,
+    pub oversized_embedding: OversizedEmbedding,
+    /// Ask the frontmost browser for its page URL on capture. Off by default.
+    pub capture_browser_urls: bool,
+    /// Browsers (macOS app names or Windows process names) queried for URLs
+    pub browser_url_browsers: Vec<String>,
+    pub browser_url_timeout_ms: u64,
+    pub similarity_metric: SimilarityMetric,
+    /// Passed to Ollama as `num_predict` when generating summaries
+    pub summary_max_tokens: u32,
+    pub summary_strategy: SummaryStrategy,
+    /// Clips longer than this many characters get a model summary under
+    /// `llm-when-long`
+    pub long_clip_chars: usize,
+    /// Characters of content shown in list previews. Applied when listing,
+    /// so changing it needs no migration.
+    pub preview_chars: usize,
+    pub non_utf8_clipboard: NonUtf8Clipboard,
+    /// Lock the history after this many idle minutes; 0 disables. Only
+    /// applies once a PIN is set.
+    pub lock_idle_minutes: u32,
+    pub lock_on_hide: bool,
+    /// `reveal_clip` asks for the app-lock PIN (when one is set)
+    pub reveal_requires_pin: bool,
+    /// Collect operation counts and latencies for `get_metrics`
+    pub metrics_enabled: bool,
+    /// Attach per-stage timings to search responses as `diagnostics`
+    pub search_diagnostics: bool,
+    /// Log a stage breakdown for searches slower than this; 0 disables
+    pub slow_search_warn_ms: u64,
+    /// Changing the tokenizer or diacritics handling rebuilds the whole FTS
+    /// index, which can take a while on large histories
+    pub fts_tokenizer: FtsTokenizer,
+    /// Match "cafe" and "café" alike
+    pub fts_remove_diacritics: bool,
+    /// Register global shortcuts that copy quick-access slots 1-9
+    pub slot_hotkeys_enabled: bool,
+    /// Modifiers combined with the slot digit, e.g. "CommandOrControl+Shift"
+    pub slot_hotkey_modifiers: String,
+    /// Keep a new capture in memory this long before saving it, so it can be
+    /// canceled; 0 saves immediately
+    pub capture_grace_ms: u64,
+    /// File every new or edited clip into a Links/Code/Emails/Notes
+    /// collection matching its content type
+    pub auto_categorize: bool,
+    /// Tag captures larger than this `large` and emit
+    /// `large-clip-captured`; 0 disables the warning
+    pub large_clip_warning_bytes: usize,
+    /// Ask the model to file new clips into opted-in collections
+    pub auto_file_enabled: bool,
+    /// Model confidence (0-1) needed before a clip is filed
+    pub auto_file_min_confidence: f32,
+    /// Keyed by app name (macOS) or process name (Windows); unlisted apps
+    /// are captured normally
+    pub app_capture_modes: HashMap<String, AppCaptureMode>,
+    pub duplicate_tag_policy: DuplicateTagPolicy,
+    /// Periodically merge near-identical clips in the background. The
+    /// interval and threshold are read at startup.
+    pub dedup_job_enabled: bool,
+    pub dedup_interval_minutes: u32,
+    /// Embedding cosine similarity (0-1] at which clips count as duplicates
+    pub dedup_similarity_threshold: f32,
+    /// Skip captures whose SimHash is within `fuzzy_dedup_max_distance`
+    /// bits of a recent clip's, catching formatting-only re-copies
+    pub fuzzy_dedup: bool,
+    pub fuzzy_dedup_max_distance: u32,
+    /// Query parameters `copy_clip` drops from links when asked to clean
+    /// them; a trailing `*` matches any parameter with that prefix
+    pub url_tracking_params: Vec<String>,
+    /// Linux only: also capture the middle-click primary selection
+    pub primary_selection_enabled: bool,
+    /// Selections shorter than this (in bytes) are ignored
+    pub primary_selection_min_bytes: usize,
+    /// Delete primary-selection clips after this many hours; 0 keeps them
+    pub primary_selection_retention_hours: u32,
+    /// Start capturing at launch; when off, capture waits for
+    /// `resume_monitoring`
+    pub autostart_monitoring: bool,
+    /// Delete the source clips after `merge_clips_into_new`, unless the
+    /// call says otherwise
+    pub merge_deletes_sources: bool,
+    /// Words `top_terms` leaves out, compared case-insensitively
+    pub stopwords: Vec<String>,
+    /// Clips with any of these tags are never pruned (case-insensitive)
+    pub retention_exempt_tags: Vec<String>,
+    /// Clips in any of these collections, by name, are never pruned
+    pub retention_exempt_collections: Vec<String>,
+    pub notification_rules: NotificationRules,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            ollama_url: DEFAULT_OLLAMA_URL.to_string(),
+            ollama_model: DEFAULT_OLLAMA_MODEL.to_string(),
+            ollama_proxy: None,
+            ollama_ca_cert_path: None,
+            ollama_bearer_token: None,
+            clipboard_poll_ms: 500,
+            embedding_max_chars: 8000,
+            oversized_embedding: OversizedEmbedding::Truncate,
+            capture_browser_urls: false,
+            browser_url_browsers: vec![
+                "Safari".to_string(),
+                "Google Chrome".to_string(),
+                "Microsoft Edge".to_string(),
+                "Brave Browser".to_string(),
+                "Arc".to_string(),
+                "chrome".to_string(),
+                "msedge".to_string(),
+                "brave".to_string(),
+            ],
+            browser_url_timeout_ms: 300,
+            similarity_metric: SimilarityMetric::Cosine,
+            summary_max_tokens: 64,
+            summary_strategy: SummaryStrategy::Truncate,
+            long_clip_chars: 1000,
+            preview_chars: 120,
+            non_utf8_clipboard: NonUtf8Clipboard::Lossy,
+            lock_idle_minutes: 15,
+            lock_on_hide: true,
+            reveal_requires_pin: true,
+            metrics_enabled: false,
+            search_diagnostics: false,
+            slow_search_warn_ms: 1000,
+            fts_tokenizer: FtsTokenizer::Unicode61,
+            fts_remove_diacritics: true,
+            slot_hotkeys_enabled: true,
+            slot_hotkey_modifiers: "CommandOrControl+Shift".to_string(),
+            capture_grace_ms: 0,
+            auto_categorize: false,
+            large_clip_warning_bytes: 1024 * 1024,
+            auto_file_enabled: false,
+            auto_file_min_confidence: 0.8,
+            app_capture_modes: HashMap::new(),
+            duplicate_tag_policy: DuplicateTagPolicy::Union,
+            dedup_job_enabled: false,
+            dedup_interval_minutes: 60,
+            dedup_similarity_threshold: 0.97,
+            fuzzy_dedup: false,
+            fuzzy_dedup_max_distance: 3,
+            url_tracking_params: vec![
+                "utm_*".to_string(),
+                "fbclid".to_string(),
+                "gclid".to_string(),
+                "dclid".to_string(),
+                "msclkid".to_string(),
+                "mc_cid".to_string(),
+                "mc_eid".to_string(),
+                "igshid".to_string(),
+                "_hsenc".to_string(),
+                "_hsmi".to_string(),
+            ],
+            primary_selection_enabled: false,
+            primary_selection_min_bytes: 16,
+            primary_selection_retention_hours: 24,
+            autostart_monitoring: true,
+            merge_deletes_sources: false,
+            stopwords: DEFAULT_STOPWORDS.iter().map(|word| word.to_string()).collect(),
+            retention_exempt_tags: Vec::new(),
+            retention_exempt_collections: Vec::new(),
+            notification_rules: NotificationRules::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn lock_idle_timeout(&self) -> Option<Duration> {
+        (self.lock_idle_minutes > 0).then(|| Duration::from_secs(self.lock_idle_minutes as u64 * 60))
+    }
+
+    /// Value of the FTS5 `tokenize` option for these settings.
+    pub fn fts_tokenize(&self) -> String {
+        let unicode61 = format!(
+            "unicode61 remove_diacritics {}",
+            if self.fts_remove_diacritics { 2 } else { 0 }
+        );
+        match self.fts_tokenizer {
+            FtsTokenizer::Unicode61 => unicode61,
+            FtsTokenizer::Porter => format!("porter {}", unicode61),
+        }
+    }
+
+    /// Whether a capture of `size_bytes` should be flagged as large.
+    pub fn is_large_clip(&self, size_bytes: usize) -> bool {
+        self.large_clip_warning_bytes > 0 && size_bytes > self.large_clip_warning_bytes
+    }
+
+    /// Capture mode for the app named `app`, compared case-insensitively.
+    pub fn capture_mode_for(&self, app: &str) -> AppCaptureMode {
+        self.app_capture_modes
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(app))
+            .map_or(AppCaptureMode::Capture, |(_, mode)| *mode)
+    }
+
+    /// Whether `other` talks to Ollama the same way (server, model, proxy,
+    /// certificate and token).
+    pub fn same_ollama_connection(&self, other: &AppConfig) -> bool {
+        self.ollama_url == other.ollama_url
+            && self.ollama_model == other.ollama_model
+            && self.ollama_proxy == other.ollama_proxy
+            && self.ollama_ca_cert_path == other.ollama_ca_cert_path
+            && self.ollama_bearer_token == other.ollama_bearer_token
+    }
+
+    pub fn clipboard_poll_interval(&self) -> Duration {
+        Duration::from_millis(self.clipboard_poll_ms)
+    }
+
+    pub fn dedup_interval(&self) -> Duration {
+        Duration::from_secs(self.dedup_interval_minutes as u64 * 60)
+    }
+
+    pub fn capture_grace(&self) -> Option<Duration> {
+        (self.capture_grace_ms > 0).then(|| Duration::from_millis(self.capture_grace_ms))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        validate_summary_max_tokens(self.summary_max_tokens)?;
+        if url::Url::parse(&self.ollama_url).is_err() {
+            return Err(format!("Ollama URL is not a valid URL: {}", self.ollama_url));
+        }
+        if self.ollama_model.trim().is_empty() {
+            return Err("Ollama model must not be empty".to_string());
+        }
+        if !(MIN_CLIPBOARD_POLL_MS..=MAX_CLIPBOARD_POLL_MS).contains(&self.clipboard_poll_ms) {
+            return Err(format!(
+                "Clipboard poll interval must be between {} and {} ms",
+                MIN_CLIPBOARD_POLL_MS, MAX_CLIPBOARD_POLL_MS
+            ));
+        }
+        if self.capture_grace_ms > MAX_CAPTURE_GRACE_MS {
+            return Err(format!(
+                "Capture grace period must be at most {} ms",
+                MAX_CAPTURE_GRACE_MS
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.auto_file_min_confidence) {
+            return Err("Auto-file confidence must be between 0 and 1".to_string());
+        }
+        if self.preview_chars == 0 {
+            return Err("Preview length must be at least 1 character".to_string());
+        }
+        if self.long_clip_chars == 0 {
+            return Err("Long clip threshold must be at least 1 character".to_string());
+        }
+        if self.dedup_interval_minutes == 0 {
+            return Err("Deduplication interval must be at least 1 minute".to_string());
+        }
+        if !(self.dedup_similarity_threshold > 0.0 && self.dedup_similarity_threshold <= 1.0) {
+            return Err("Deduplication threshold must be above 0 and at most 1".to_string());
+        }
+        if self.fuzzy_dedup_max_distance > 64 {
+            return Err("Fuzzy dedup distance must be at most 64 bits".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub fn validate_summary_max_tokens(max_tokens: u32) -> Result<(), String> {
+    if max_tokens == 0 || max_tokens > MAX_SUMMARY_TOKENS {
+        return Err(format!(
+            "Summary length must be between 1 and {} tokens",
+            MAX_SUMMARY_TOKENS
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use crate::config::AppConfig;
+
+/// Optional settings file in the app config directory
+pub const CONFIG_FILE_NAME: &str = "clipsage.toml";
+
+// `CLIPSAGE_OLLAMA_URL` overrides `ollama_url`, and so on for every setting
+const ENV_PREFIX: &str = "CLIPSAGE_";
+
+/// Settings set by one layer, keyed like the fields of `AppConfig`.
+pub type Overrides = Map<String, Value>;
+
+/// Which layer a setting's effective value came from. Later layers win:
+/// defaults < file < environment < settings changed in the app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigOrigin {
+    Default,
+    File,
+    Env,
+    Settings,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveConfig {
+    pub config: AppConfig,
+    pub origins: BTreeMap<String, ConfigOrigin>,
+    pub file_path: Option<String>,
+    /// Why the file was last rejected; its previous contents stay in effect
+    pub file_error: Option<String>,
+}
+
+/// The file and environment layers that sit under the settings stored in
+/// the database.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLayers {
+    file: Overrides,
+    env: Overrides,
+    file_path: Option<PathBuf>,
+    file_error: Option<String>,
+}
+
+impl ConfigLayers {
+    /// Reads `clipsage.toml` from `config_dir` and the `CLIPSAGE_*`
+    /// environment. A missing file is fine; an invalid one is logged and
+    /// ignored, as are invalid variables.
+    pub fn load(config_dir: &Path) -> Self {
+        let mut layers = ConfigLayers {
+            env: env_overrides(),
+            file_path: Some(config_dir.join(CONFIG_FILE_NAME)),
+            ..Default::default()
+        };
+        if let Err(e) = layers.reload_file() {
+            eprintln!("Ignoring invalid config file: {}", e);
+        }
+        layers
+    }
+
+    pub fn file_path(&self) -> Option<&Path> {
+        self.file_path.as_deref()
+    }
+
+    /// Re-reads the config file. On error the previously loaded values stay
+    /// in effect and the error is kept for `get_effective_config`.
+    pub fn reload_file(&mut self) -> Result<(), String> {
+        let Some(path) = &self.file_path else {
+            return Ok(());
+        };
+
+        let parsed = match std::fs::read_to_string(path) {
+            Ok(text) => parse_file(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Overrides::new()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        match parsed {
+            Ok(file) => {
+                self.file = file;
+                self.file_error = None;
+                Ok(())
+            }
+            Err(e) => {
+                let e = format!("{}: {}", path.display(), e);
+                self.file_error = Some(e.clone());
+                Err(e)
+            }
+        }
+    }
+
+    /// Layers `settings` over the file and environment, returning the
+    /// resulting config and where each value came from.
+    pub fn resolve(&self, settings: &Overrides) -> Result<(AppConfig, BTreeMap<String, ConfigOrigin>)> {
+        let mut merged = default_values();
+        let mut origins: BTreeMap<String, ConfigOrigin> =
+            merged.keys().map(|key| (key.clone(), ConfigOrigin::Default)).collect();
+
+        for (origin, layer) in [
+            (ConfigOrigin::File, &self.file),
+            (ConfigOrigin::Env, &self.env),
+            (ConfigOrigin::Settings, settings),
+        ] {
+            for (key, value) in layer {
+                merged.insert(key.clone(), value.clone());
+                origins.insert(key.clone(), origin);
+            }
+        }
+
+        Ok((serde_json::from_value(Value::Object(merged))?, origins))
+    }
+
+    pub fn effective(&self, config: &AppConfig, settings: &Overrides) -> Result<EffectiveConfig> {
+        let (_, origins) = self.resolve(settings)?;
+        Ok(EffectiveConfig {
+            config: config.clone(),
+            origins,
+            file_path: self.file_path.as_ref().map(|path| path.display().to_string()),
+            file_error: self.file_error.clone(),
+        })
+    }
+}
+
+/// Settings in `config` that differ from `base`, which is what gets stored
+/// when settings are changed in the app.
+pub fn overrides_between(base: &AppConfig, config: &AppConfig) -> Result<Overrides> {
+    let base = to_map(base)?;
+    Ok(to_map(config)?
+        .into_iter()
+        .filter(|(key, value)| base.get(key) != Some(value))
+        .collect())
+}
+
+fn to_map(config: &AppConfig) -> Result<Overrides> {
+    match serde_json::to_value(config)? {
+        Value::Object(map) => Ok(map),
+        _ => unreachable!("AppConfig serializes as an object"),
+    }
+}
+
+fn default_values() -> Overrides {
+    to_map(&AppConfig::default()).expect("default config serializes")
+}
+
+// Checks that `value` is acceptable for `key` on its own, so errors name
+// the offending setting
+fn check_override(key: &str, value: &Value) -> Result<(), String> {
+    let mut merged = default_values();
+    merged.insert(key.to_string(), value.clone());
+    let config: AppConfig = serde_json::from_value(Value::Object(merged)).map_err(|e| format!("`{}`: {}", key, e))?;
+    config.validate().map_err(|e| format!("`{}`: {}", key, e))
+}
+
+fn parse_file(text: &str) -> Result<Overrides, String> {
+    let table: toml::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+    let known = default_values();
+
+    let mut file = Overrides::new();
+    for (key, value) in table {
+        if !known.contains_key(&key) {
+            return Err(format!("unknown setting `{}`", key));
+        }
+        let value = serde_json::to_value(value).map_err(|e| format!("`{}`: {}", key, e))?;
+        check_override(&key, &value)?;
+        file.insert(key, value);
+    }
+
+    // Settings that are only invalid in combination
+    let mut merged = known;
+    merged.extend(file.clone());
+    let config: AppConfig = serde_json::from_value(Value::Object(merged)).map_err(|e| e.to_string())?;
+    config.validate()?;
+
+    Ok(file)
+}
+
+fn env_overrides() -> Overrides {
+    let mut env = Overrides::new();
+    for key in default_values().keys() {
+        let var = format!("{}{}", ENV_PREFIX, key.to_uppercase());
+        let Ok(raw) = std::env::var(&var) else {
+            continue;
+        };
+        let value = parse_env_value(&raw);
+        match check_override(key, &value) {
+            Ok(()) => {
+                env.insert(key.clone(), value);
+            }
+            Err(e) => eprintln!("Ignoring {}: {}", var, e),
+        }
+    }
+    env
+}
+
+// Values are read as TOML (numbers, booleans, arrays, inline tables) and
+// otherwise taken as a plain string, so `CLIPSAGE_OLLAMA_URL=http://...`
+// needs no quoting
+fn parse_env_value(raw: &str) -> Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .and_then(|value| serde_json::to_value(value).ok())
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
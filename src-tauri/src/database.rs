@@ -1,8 +1,93 @@
-use sqlx::{SqlitePool, Row};
+use sqlx::{Connection, SqlitePool, Row};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePoolOptions, SqliteRow};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
-use anyhow::Result;
-use crate::ollama::OllamaClient;
+use anyhow::{anyhow, Result};
+use futures::{Stream, StreamExt, TryStreamExt};
+use regex::Regex;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use sha2::{Digest, Sha256};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+use crate::citations::renumber_citations;
+use crate::config::{AppConfig, DuplicateTagPolicy, OversizedEmbedding, SimilarityMetric};
+use crate::config_file::{overrides_between, ConfigLayers, EffectiveConfig, Overrides, CONFIG_FILE_NAME};
+use crate::diff::{diff_texts, line_diff, DiffMode, LineDiff, TextDiffResult};
+use crate::highlight;
+use crate::metrics::{elapsed_ms, Metrics, Operation, SearchTimings};
+use crate::ollama::{EmbeddingProvider, Entity, OllamaClient};
+use crate::readonly::ReadOnlyError;
+use crate::simhash;
+use crate::terms::{TermCount, TermCounter};
+use crate::retention::{self, PruneReport, RetentionCandidate, RetentionRule, RulePrune};
+use crate::wipe::WipeReport;
+
+const CLIP_COLUMNS: &str =
+    "id, content, summary, tags, created_at, last_modified, source, embedding, urls, embed_truncated, content_type, source_url, rewrite_history, masked, keywords, metadata, seq, code_language";
+
+// Embeddings comparable with the current model's: produced by it, or of
+// unknown origin but the same dimension. Binds the model name, then the
+// query vector's dimension.
+const CURRENT_MODEL_EMBEDDING: &str = "(embed_model = ? OR (embed_model IS NULL AND embed_dim = ?))";
+
+// Most recent embedded clips compared pairwise when looking for near-duplicates
+const NEAR_DUPLICATE_CANDIDATE_LIMIT: i32 = 1000;
+
+// Largest set of clips accepted by the similarity matrix endpoints
+const SIMILARITY_MATRIX_LIMIT: usize = 200;
+
+// Rows copied per statement when salvaging a damaged database
+const SALVAGE_BATCH_SIZE: i64 = 500;
+
+// Number of rewrite instructions remembered per clip
+const MAX_REWRITE_HISTORY: usize = 10;
+
+// Most clips a single `split_clip` may create
+const MAX_SPLIT_PIECES: usize = 500;
+
+// Ids bound per statement when deleting in bulk, well under SQLite's
+// default limit of 999 host parameters
+const DELETE_CHUNK_SIZE: usize = 500;
+
+// Clips longer than this many characters are also embedded in chunks
+const CHUNK_MIN_CHARS: usize = 2000;
+
+// Characters per chunk, and how many of them repeat from the previous chunk
+const CHUNK_CHARS: usize = 1000;
+const CHUNK_OVERLAP_CHARS: usize = 200;
+
+// Clips retrieved as sources for `ask_clips`, and the most characters of an
+// unchunked clip passed to the model
+const ASK_SOURCE_LIMIT: i32 = 5;
+const ASK_SOURCE_CHARS: usize = 2000;
+
+// Nearest neighbours whose tags are pooled for tag suggestions
+const TAG_SUGGESTION_NEIGHBORS: usize = 20;
+
+// Keyphrases requested per clip from the model
+const KEYWORDS_PER_CLIP: u8 = 10;
+
+// Embedded once to learn the model's vector size
+const CALIBRATION_TEXT: &str = "ClipSage embedding calibration";
+
+// Texts sent to Ollama per request by `re_embed_all` and `backfill_embeddings`
+const EMBED_BATCH_SIZE: usize = 32;
+
+// `dataType` of plain-text entries in Alfred's clipboard history
+const ALFRED_TEXT_TYPE: i64 = 0;
+
+// Clips whose summaries go into a collection description, and how many
+// clips a collection may gain or lose before the description is redone
+const COLLECTION_DESCRIPTION_CLIPS: i64 = 20;
+const DESCRIPTION_STALE_CLIPS: i64 = 10;
+
+// Most matches returned by a phrase timeline (the oldest ones win)
+const TIMELINE_LIMIT: i32 = 200;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClipItem {
@@ -10,256 +95,8254 @@ pub struct ClipItem {
     pub content: String,
     pub summary: String,
     pub tags: Vec<String>,
-    pub timestamp: DateTime<Utc>,
+    /// When the clip was captured; never changes after insert
+    pub created_at: DateTime<Utc>,
+    /// Bumped whenever the clip is edited
+    pub last_modified: DateTime<Utc>,
     pub source: Option<String>,
     pub embedding: Option<Vec<f32>>,
+    pub urls: Vec<String>,
+    pub embed_truncated: bool,
+    pub content_type: ContentType,
+    pub source_url: Option<String>,
+    pub rewrite_history: Vec<RewriteRecord>,
+    /// Shown as a placeholder in lists and kept out of the FTS content index
+    #[serde(default)]
+    pub masked: bool,
+    /// Keyphrases from `generate_clip_keywords`, indexed in `clip_keywords_fts`
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Changed only through `update_metadata`; `update_clip` leaves it alone
+    #[serde(default)]
+    pub metadata: ClipMetadata,
+    /// Short number shown in lists, assigned on insert and never reused.
+    /// Commands accept it in place of `id`; exports and imports go by `id`.
+    #[serde(default)]
+    pub seq: Option<i64>,
+    /// Set by `detect_code_language`, and cleared when the content changes
+    #[serde(default)]
+    pub code_language: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-pub struct Database {
-    pool: SqlitePool,
-    ollama: OllamaClient,
+/// Placeholder shown instead of a masked clip's content and summary
+pub const MASKED_PLACEHOLDER: &str = "••••••••";
+
+/// Free-form extras for a clip, stored as a JSON object in
+/// `clips.metadata`. Keys without a field here are kept in `extra`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipMetadata {
+    /// Title of the page a link clip points to; full-text searchable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url_title: Option<String>,
+    /// Label color picked by the user, e.g. `#ff8800`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Clip this one was split out of
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split_from: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-impl Database {
-    pub async fn new(database_url: &str) -> Result<Self> {
-        let pool = SqlitePool::connect(database_url).await?;
-        let ollama = OllamaClient::new("nomic-embed-text");
-        
-        // Create tables
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS clips (
-                id TEXT PRIMARY KEY,
-                content TEXT NOT NULL,
-                summary TEXT NOT NULL,
-                tags TEXT NOT NULL, -- JSON array
-                timestamp TEXT NOT NULL,
-                source TEXT,
-                embedding BLOB -- Vector embedding as binary data
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await?;
+/// What list views show for a clip: a bounded content preview (see
+/// `AppConfig::preview_chars`), with masked clips reduced to a placeholder.
+/// Use `get_clip`/`reveal` for the full text.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipPreview {
+    pub id: String,
+    pub preview: String,
+    pub summary: String,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+    pub source: Option<String>,
+    pub content_type: ContentType,
+    pub masked: bool,
+    /// Length of the full content in bytes
+    pub size_bytes: usize,
+    pub seq: Option<i64>,
+}
 
-        // Create FTS5 virtual table for full-text search
-        sqlx::query(
-            r#"
-            CREATE VIRTUAL TABLE IF NOT EXISTS clips_fts USING fts5(
-                id UNINDEXED,
-                content,
-                summary,
-                tags,
-                source,
-                content='clips',
-                content_rowid='rowid'
-            )
-            "#,
-        )
-        .execute(&pool)
-        .await?;
+impl ClipPreview {
+    pub fn new(clip: &ClipItem, preview_chars: usize) -> Self {
+        let (preview, summary) = if clip.masked {
+            (MASKED_PLACEHOLDER.to_string(), MASKED_PLACEHOLDER.to_string())
+        } else {
+            let mut preview: String = clip.content.chars().take(preview_chars).collect();
+            if preview.len() < clip.content.len() {
+                preview.push_str("...");
+            }
+            (preview, clip.summary.clone())
+        };
 
-        // Create triggers to keep FTS table in sync
-        sqlx::query(
-            r#"
-            CREATE TRIGGER IF NOT EXISTS clips_ai AFTER INSERT ON clips BEGIN
-                INSERT INTO clips_fts(id, content, summary, tags, source)
-                VALUES (new.id, new.content, new.summary, new.tags, new.source);
-            END
-            "#,
-        )
-        .execute(&pool)
-        .await?;
+        ClipPreview {
+            id: clip.id.clone(),
+            preview,
+            summary,
+            tags: clip.tags.clone(),
+            created_at: clip.created_at,
+            last_modified: clip.last_modified,
+            source: clip.source.clone(),
+            content_type: clip.content_type,
+            masked: clip.masked,
+            size_bytes: clip.content.len(),
+            seq: clip.seq,
+        }
+    }
+}
 
-        sqlx::query(
-            r#"
-            CREATE TRIGGER IF NOT EXISTS clips_ad AFTER DELETE ON clips BEGIN
-                DELETE FROM clips_fts WHERE id = old.id;
-            END
-            "#,
-        )
-        .execute(&pool)
-        .await?;
+/// Why a clip reference passed to a command didn't resolve to one clip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipRefError {
+    NotFound(String),
+    /// All digits, and both some clip's id and another clip's short number
+    Ambiguous(String),
+}
 
-        sqlx::query(
-            r#"
-            CREATE TRIGGER IF NOT EXISTS clips_au AFTER UPDATE ON clips BEGIN
-                UPDATE clips_fts SET 
-                    content = new.content,
-                    summary = new.summary,
-                    tags = new.tags,
-                    source = new.source
-                WHERE id = new.id;
-            END
-            "#,
-        )
-        .execute(&pool)
-        .await?;
+impl fmt::Display for ClipRefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipRefError::NotFound(reference) => write!(f, "Clip not found: {}", reference),
+            ClipRefError::Ambiguous(reference) => write!(
+                f,
+                "Clip reference {} matches both a clip id and a short number; use #{} for the number",
+                reference, reference
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ClipRefError {}
+
+/// Position in the newest-first clip list; pass the previous page's
+/// `next_cursor` to continue after it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: String,
+}
+
+/// Clips captured on one local calendar day. A page boundary can split a
+/// day, in which case the next page starts with the same `date` again.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipGroup {
+    /// "Today", "Yesterday", a weekday name within the last week, otherwise
+    /// the ISO date
+    pub label: String,
+    /// Local date as YYYY-MM-DD
+    pub date: String,
+    pub clips: Vec<ClipPreview>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupedClips {
+    pub groups: Vec<ClipGroup>,
+    pub next_cursor: Option<ClipCursor>,
+}
+
+/// Which timestamp orders a clip listing, newest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    CreatedAt,
+    LastModified,
+}
 
-        Ok(Database { pool, ollama })
+impl SortField {
+    fn column(self) -> &'static str {
+        match self {
+            SortField::CreatedAt => "created_at",
+            SortField::LastModified => "last_modified",
+        }
     }
+}
 
-    pub async fn insert_clip(&self, clip: &ClipItem) -> Result<()> {
-        let tags_json = serde_json::to_string(&clip.tags)?;
-        
-        // Generate embedding using Ollama if not provided
-        let embedding = if clip.embedding.is_none() {
-            Some(self.ollama.get_embedding(&clip.content).await?)
-        } else {
-            clip.embedding.clone()
+/// Column the data-table view (`list_clips`) sorts by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipTableSort {
+    /// Capture time
+    Timestamp,
+    /// Content size in bytes
+    ContentLength,
+    /// How often the clip was copied or opened (see `record_access`)
+    UseCount,
+    Source,
+}
+
+impl ClipTableSort {
+    // Only these fixed expressions ever reach the ORDER BY clause
+    fn expression(self) -> &'static str {
+        match self {
+            ClipTableSort::Timestamp => "c.created_at",
+            ClipTableSort::ContentLength => "COALESCE(c.content_length, length(CAST(c.content AS BLOB)))",
+            ClipTableSort::UseCount => "use_count",
+            ClipTableSort::Source => "c.source",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipTableRow {
+    #[serde(flatten)]
+    pub clip: ClipPreview,
+    pub use_count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipTablePage {
+    pub rows: Vec<ClipTableRow>,
+    /// Clips in the whole table, not just this page
+    pub total: u64,
+}
+
+/// How `split_clip` cuts a clip's content into pieces. Pieces are trimmed
+/// and empty ones dropped.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "mode", content = "value", rename_all = "snake_case")]
+pub enum SplitMode {
+    Lines,
+    /// Split on a literal delimiter such as "," or "\t"
+    Custom(String),
+    Regex(String),
+}
+
+impl SplitMode {
+    fn split<'a>(&self, content: &'a str) -> Result<Vec<&'a str>> {
+        let pieces: Vec<&str> = match self {
+            SplitMode::Lines => content.lines().collect(),
+            SplitMode::Custom(delimiter) if delimiter.is_empty() => {
+                return Err(anyhow!("Split delimiter is empty"));
+            }
+            SplitMode::Custom(delimiter) => content.split(delimiter.as_str()).collect(),
+            SplitMode::Regex(pattern) => Regex::new(pattern)
+                .map_err(|e| anyhow!("Invalid split pattern: {}", e))?
+                .split(content)
+                .collect(),
         };
 
-        let embedding_bytes = embedding.map(|e| {
-            e.iter().flat_map(|f| f.to_le_bytes()).collect::<Vec<u8>>()
-        });
+        Ok(pieces.into_iter().map(str::trim).filter(|piece| !piece.is_empty()).collect())
+    }
+}
 
-        sqlx::query(
-            r#"
-            INSERT INTO clips (id, content, summary, tags, timestamp, source, embedding)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(&clip.id)
-        .bind(&clip.content)
-        .bind(&clip.summary)
-        .bind(&tags_json)
-        .bind(clip.timestamp.to_rfc3339())
-        .bind(&clip.source)
-        .bind(embedding_bytes)
-        .execute(&self.pool)
-        .await?;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewriteRecord {
+    pub instruction: String,
+    pub rewritten_at: DateTime<Utc>,
+}
 
-        Ok(())
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipVersion {
+    pub id: String,
+    pub clip_id: String,
+    pub content: String,
+    pub summary: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentType {
+    Url,
+    Code,
+    Email,
+    Text,
+    /// Hash-only capture: the row keeps no content (see `insert_hash_only_clip`)
+    Redacted,
+}
+
+impl ContentType {
+    /// Picks the dominant content type from the tags assigned at capture time.
+    /// A clip only counts as a URL when it is essentially just the link.
+    pub fn detect(content: &str, tags: &[String]) -> Self {
+        let has_tag = |tag: &str| tags.iter().any(|t| t == tag);
+
+        if has_tag("url") && content.split_whitespace().count() == 1 {
+            ContentType::Url
+        } else if has_tag("code") {
+            ContentType::Code
+        } else if has_tag("email") {
+            ContentType::Email
+        } else {
+            ContentType::Text
+        }
     }
 
-    pub async fn search_clips(&self, query: &str, limit: i32) -> Result<Vec<ClipItem>> {
-        // Get text search results
-        let text_results = self.text_search(query, limit).await?;
-        
-        // Get semantic search results
-        let query_embedding = self.ollama.get_embedding(query).await?;
-        let semantic_results = self.semantic_search(&query_embedding, limit).await?;
-        
-        // Combine and deduplicate results
-        let mut combined = Vec::new();
-        let mut seen_ids = std::collections::HashSet::new();
-        
-        for clip in text_results.into_iter().chain(semantic_results.into_iter()) {
-            if seen_ids.insert(clip.id.clone()) {
-                combined.push(clip);
-            }
+    /// Collection that auto-categorize files clips of this type into.
+    pub fn category_collection(&self) -> &'static str {
+        match self {
+            ContentType::Url => "Links",
+            ContentType::Code => "Code",
+            ContentType::Email => "Emails",
+            ContentType::Text => "Notes",
+            ContentType::Redacted => "Redacted",
         }
-        
-        Ok(combined.into_iter().take(limit as usize).collect())
     }
 
-    async fn text_search(&self, query: &str, limit: i32) -> Result<Vec<ClipItem>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT c.id, c.content, c.summary, c.tags, c.timestamp, c.source, c.embedding
-            FROM clips c
-            JOIN clips_fts fts ON c.id = fts.id
-            WHERE clips_fts MATCH ?
-            ORDER BY rank
-            LIMIT ?
-            "#,
-        )
-        .bind(query)
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await?;
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentType::Url => "url",
+            ContentType::Code => "code",
+            ContentType::Email => "email",
+            ContentType::Text => "text",
+            ContentType::Redacted => "redacted",
+        }
+    }
 
-        self.rows_to_clips(rows).await
+    fn parse(value: &str) -> Self {
+        match value {
+            "url" => ContentType::Url,
+            "code" => ContentType::Code,
+            "email" => ContentType::Email,
+            "redacted" => ContentType::Redacted,
+            _ => ContentType::Text,
+        }
     }
+}
 
-    pub async fn get_recent_clips(&self, limit: i32) -> Result<Vec<ClipItem>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, content, summary, tags, timestamp, source, embedding
-            FROM clips
-            ORDER BY timestamp DESC
-            LIMIT ?
-            "#,
-        )
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await?;
+/// Rule-based tags assigned at capture time from the content alone.
+pub fn detect_tags(content: &str, urls: &[String]) -> Vec<String> {
+    let mut tags = Vec::new();
+    if !urls.is_empty() {
+        tags.push("url".to_string());
+    }
+    if content.contains("function") || content.contains("const") || content.contains("let") {
+        tags.push("code".to_string());
+    }
+    if content.contains("@") && content.contains(".") {
+        tags.push("email".to_string());
+    }
+    if content.len() > 200 {
+        tags.push("long-text".to_string());
+    }
+    tags
+}
 
-        self.rows_to_clips(rows).await
+/// What a collection rule looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionRuleType {
+    /// `rule_value` is a content type such as "url" or "code"
+    ContentType,
+    /// Some tag contains `rule_value` (case-insensitive)
+    TagContains,
+    SourceEquals,
+    /// `rule_value` is a regex matched against the content
+    RegexMatch,
+}
+
+impl CollectionRuleType {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "content_type" => Ok(CollectionRuleType::ContentType),
+            "tag_contains" => Ok(CollectionRuleType::TagContains),
+            "source_equals" => Ok(CollectionRuleType::SourceEquals),
+            "regex_match" => Ok(CollectionRuleType::RegexMatch),
+            _ => Err(anyhow!("Unknown rule type: {}", value)),
+        }
     }
 
-    pub async fn semantic_search(&self, query_embedding: &[f32], limit: i32) -> Result<Vec<ClipItem>> {
-        let all_clips = self.get_recent_clips(1000).await?;
-        
-        let mut scored_clips: Vec<(f32, ClipItem)> = all_clips
-            .into_iter()
-            .filter_map(|clip| {
-                clip.embedding.as_ref().map(|embedding| {
-                    let similarity = cosine_similarity(query_embedding, embedding);
-                    (similarity, clip.clone())
-                })
-            })
-            .collect();
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CollectionRuleType::ContentType => "content_type",
+            CollectionRuleType::TagContains => "tag_contains",
+            CollectionRuleType::SourceEquals => "source_equals",
+            CollectionRuleType::RegexMatch => "regex_match",
+        }
+    }
 
-        scored_clips.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
-        
-        Ok(scored_clips
-            .into_iter()
-            .take(limit as usize)
-            .map(|(_, clip)| clip)
-            .collect())
+    /// Whether `clip` satisfies a rule of this type with `value`.
+    pub fn matches(&self, value: &str, clip: &ClipItem) -> Result<bool> {
+        Ok(match self {
+            CollectionRuleType::ContentType => clip.content_type.as_str() == value,
+            CollectionRuleType::TagContains => {
+                let needle = value.to_lowercase();
+                clip.tags.iter().any(|tag| tag.to_lowercase().contains(&needle))
+            }
+            CollectionRuleType::SourceEquals => clip.source.as_deref() == Some(value),
+            CollectionRuleType::RegexMatch => Regex::new(value)
+                .map_err(|e| anyhow!("Invalid rule pattern: {}", e))?
+                .is_match(&clip.content),
+        })
     }
+}
 
-    async fn rows_to_clips(&self, rows: Vec<sqlx::sqlite::SqliteRow>) -> Result<Vec<ClipItem>> {
-        let mut clips = Vec::new();
-        
-        for row in rows {
-            let id: String = row.get("id");
-            let content: String = row.get("content");
-            let summary: String = row.get("summary");
-            let tags_json: String = row.get("tags");
-            let timestamp_str: String = row.get("timestamp");
-            let source: Option<String> = row.get("source");
-            let embedding_bytes: Option<Vec<u8>> = row.get("embedding");
-
-            let tags: Vec<String> = serde_json::from_str(&tags_json)?;
-            let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)?.with_timezone(&Utc);
-            
-            let embedding = embedding_bytes.map(|bytes| {
-                bytes
-                    .chunks_exact(4)
-                    .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-                    .collect()
-            });
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionRule {
+    pub id: String,
+    pub collection_id: String,
+    pub rule_type: CollectionRuleType,
+    pub rule_value: String,
+}
 
-            clips.push(ClipItem {
-                id,
-                content,
-                summary,
-                tags,
-                timestamp,
-                source,
-                embedding,
-            });
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportFilter {
+    pub tag: Option<String>,
+    pub content_type: Option<ContentType>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub source: Option<String>,
+}
+
+impl ExportFilter {
+    // Builds a WHERE clause (always valid, possibly `1 = 1`) plus its bind values
+    fn where_clause(&self) -> (String, Vec<String>) {
+        // Hash-only rows have nothing to export
+        let mut conditions = vec!["content_type != 'redacted'".to_string()];
+        let mut binds = Vec::new();
+
+        if let Some(tag) = &self.tag {
+            conditions.push("EXISTS (SELECT 1 FROM json_each(clips.tags) WHERE json_each.value = ?)".to_string());
+            binds.push(tag.clone());
+        }
+        if let Some(content_type) = &self.content_type {
+            conditions.push("content_type = ?".to_string());
+            binds.push(content_type.as_str().to_string());
+        }
+        if let Some(since) = &self.since {
+            conditions.push("created_at >= ?".to_string());
+            binds.push(since.to_rfc3339());
+        }
+        if let Some(until) = &self.until {
+            conditions.push("created_at < ?".to_string());
+            binds.push(until.to_rfc3339());
+        }
+        if let Some(source) = &self.source {
+            conditions.push("source = ?".to_string());
+            binds.push(source.clone());
         }
 
-        Ok(clips)
+        (conditions.join(" AND "), binds)
     }
 }
 
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-    if a.len() != b.len() {
-        return 0.0;
-    }
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceStats {
+    pub source: String,
+    pub clip_count: i64,
+    pub total_bytes: i64,
+    /// Fraction of all clips in the window, 0.0-1.0
+    pub share: f64,
+    pub top_tags: Vec<String>,
+    pub last_capture: DateTime<Utc>,
+}
 
-    let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+/// Clips captured from one source within a window.
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceActivity {
+    pub source: String,
+    pub clip_count: i64,
+}
 
-    if norm_a == 0.0 || norm_b == 0.0 {
-        0.0
-    } else {
-        dot_product / (norm_a * norm_b)
+/// The newest clips carrying one of the most used tags, for the home feed.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaggedClipGroup {
+    pub tag: String,
+    pub clips: Vec<ClipItem>,
+}
+
+/// A pending reminder about a clip. Fired reminders are deleted.
+#[derive(Debug, Clone, Serialize)]
+pub struct Reminder {
+    pub id: String,
+    pub clip_id: String,
+    pub remind_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Clips captured in one month (UTC), for the contribution graph.
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthlyCount {
+    pub year: i32,
+    pub month: u32,
+    pub count: u64,
+}
+
+/// Clips captured on one day (UTC).
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyCount {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub count: u64,
+}
+
+/// One cell of the 7x24 capture heatmap. Hours and weekdays are UTC;
+/// `day_of_week` follows SQLite's `%w` (0 = Sunday).
+#[derive(Debug, Clone, Serialize)]
+pub struct TagHeatmapPoint {
+    pub hour_of_day: u8,
+    pub day_of_week: u8,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionOverlap {
+    pub shared_clip_ids: Vec<String>,
+    /// Shared clips over clips in either collection; 0.0 when both are empty
+    pub jaccard_similarity: f32,
+    pub only_in_a: u64,
+    pub only_in_b: u64,
+    pub shared_count: u64,
+}
+
+/// One clip in a phrase timeline with the lines that changed since the
+/// previous match. The first entry's diff lists all of its lines as added.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEntry {
+    pub clip: ClipItem,
+    #[serde(flatten)]
+    pub diff: LineDiff,
+}
+
+/// Character range (`start..end`, in chars rather than bytes) of one
+/// embedded chunk of a long clip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ChunkSpan {
+    pub chunk_index: u32,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// One write recorded in `audit_log`. `details` never holds clip content.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: String,
+    pub operation: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub performed_at: DateTime<Utc>,
+    pub details: Option<serde_json::Value>,
+}
+
+/// One sentence of a clip. Offsets are in chars, `start_char..end_char`,
+/// and `text` excludes trailing whitespace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SentenceSpan {
+    pub start_char: u32,
+    pub end_char: u32,
+    pub text: String,
+}
+
+/// A semantic search hit. For chunked clips `chunk` is the best-matching
+/// chunk, so the UI can scroll to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticMatch {
+    pub clip: ClipItem,
+    pub score: f32,
+    pub chunk: Option<ChunkSpan>,
+}
+
+/// List-view form of a `SemanticMatch`: the preview fields plus the match.
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticHit {
+    #[serde(flatten)]
+    pub clip: ClipPreview,
+    pub score: f32,
+    pub chunk: Option<ChunkSpan>,
+}
+
+impl SemanticHit {
+    pub fn new(hit: SemanticMatch, preview_chars: usize) -> Self {
+        SemanticHit {
+            clip: ClipPreview::new(&hit.clip, preview_chars),
+            score: hit.score,
+            chunk: hit.chunk,
+        }
     }
-} 
\ No newline at end of file
+}
+
+/// A retrieved clip the answer cites. `start..end` is the character range of
+/// the clip that was given to the model.
+#[derive(Debug, Clone, Serialize)]
+pub struct CitedSource {
+    pub clip_id: String,
+    pub summary: String,
+    pub score: f32,
+    pub start: usize,
+    pub end: usize,
+    pub chunk_index: Option<u32>,
+}
+
+/// An answer whose `[n]` markers refer to `sources[n - 1]`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CitedAnswer {
+    pub answer: String,
+    pub sources: Vec<CitedSource>,
+}
+
+/// A tag worth adding to a clip. `score` is the summed similarity of the
+/// neighbouring clips carrying it, or 1.0 for rule-based suggestions.
+#[derive(Debug, Clone, Serialize)]
+pub struct TagSuggestion {
+    pub tag: String,
+    pub score: f32,
+}
+
+/// Number of quick-access slots (1..=SLOT_COUNT)
+pub const SLOT_COUNT: u8 = 9;
+
+/// Number of globally pinned clips (positions 0..GLOBAL_PIN_COUNT)
+pub const GLOBAL_PIN_COUNT: usize = 10;
+
+// Tag marking clips the model filed into a collection
+const AUTO_FILED_TAG: &str = "auto-filed";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SlotAssignment {
+    pub slot: u8,
+    pub clip: ClipPreview,
+    pub assigned_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    /// The model may file new clips here (see `auto_file_clip`)
+    pub auto_file: bool,
+    /// Model-written summary of the collection's theme
+    pub description: Option<String>,
+    pub last_described_at: Option<DateTime<Utc>>,
+}
+
+/// A clip the model filed into a collection, awaiting review.
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoFiling {
+    pub clip_id: String,
+    pub summary: String,
+    pub collection_id: String,
+    pub collection_name: String,
+    pub confidence: f32,
+    pub filed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SalvageReport {
+    pub recovered_clips: u64,
+    pub unreadable_rows: u64,
+}
+
+/// One page of a longer result list.
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total_count: u64,
+    pub offset: i32,
+    pub limit: i32,
+    pub has_next: bool,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total_count: u64, offset: i32, limit: i32) -> Self {
+        let has_next = (offset.max(0) as u64 + items.len() as u64) < total_count;
+        Page { items, total_count, offset, limit, has_next }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(Vec<T>) -> Vec<U>) -> Page<U> {
+        Page {
+            items: f(self.items),
+            total_count: self.total_count,
+            offset: self.offset,
+            limit: self.limit,
+            has_next: self.has_next,
+        }
+    }
+}
+
+pub type SearchPage = Page<ClipItem>;
+
+/// The same query run through each search method, for judging search
+/// quality during development. Overlaps are lists of clip ids.
+#[cfg(debug_assertions)]
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchComparison {
+    pub text_results: Vec<ClipItem>,
+    pub semantic_results: Vec<ClipItem>,
+    pub hybrid_results: Vec<ClipItem>,
+    pub overlap_text_semantic: Vec<String>,
+    pub exclusive_to_text: Vec<String>,
+    pub exclusive_to_semantic: Vec<String>,
+    pub overlap_all_three: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    pub results: Vec<ClipItem>,
+    pub total: u64,
+    /// Stage timings, only when `search_diagnostics` is on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<SearchTimings>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ShareConfig {
+    pub expires_in_hours: Option<u64>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipShare {
+    pub share_id: String,
+    pub clip_id: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub view_count: i64,
+    pub password_protected: bool,
+}
+
+// Tag on clips made by `merge_clips_into_new`
+const MERGED_TAG: &str = "merged";
+
+// How many of the newest clips a capture is compared with for fuzzy dedup
+const FUZZY_DEDUP_WINDOW: i64 = 100;
+
+// Maximum number of ids returned per category in an index report
+const INDEX_SAMPLE_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexReport {
+    pub clip_count: i64,
+    pub fts_count: i64,
+    pub missing_count: u64,
+    pub orphaned_count: u64,
+    pub missing_sample: Vec<String>,
+    pub orphaned_sample: Vec<String>,
+}
+
+/// Where the search index and `clips` disagree, from `verify_fts_sync`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FtsSyncReport {
+    /// Clips that should be searchable but have nothing in the index
+    pub missing_from_fts: Vec<String>,
+    /// Index entries for rows that are gone (reported as `rowid:<n>`) or
+    /// shouldn't be indexed (reported by clip id)
+    pub extra_in_fts: Vec<String>,
+    /// Clips whose indexed summary doesn't match the stored one
+    pub content_mismatches: Vec<String>,
+}
+
+impl FtsSyncReport {
+    /// Whether search is missing or misreporting clips, which
+    /// `repair_index` fixes.
+    pub fn needs_repair(&self) -> bool {
+        !self.missing_from_fts.is_empty() || !self.content_mismatches.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexRepairReport {
+    pub reinserted: u64,
+    pub removed: u64,
+}
+
+/// Format version written by `full_export`; imports reject newer files.
+pub const FULL_EXPORT_VERSION: u32 = 1;
+
+// Progress is reported after this many records during export/import
+const TRANSFER_PROGRESS_INTERVAL: u64 = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipItemWithVersions {
+    pub clip: ClipItem,
+    pub versions: Vec<ClipVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionExport {
+    #[serde(flatten)]
+    pub collection: Collection,
+    pub clip_ids: Vec<String>,
+}
+
+/// Everything needed to rebuild a library elsewhere: clips with their
+/// version history, collections with membership, and settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FullExport {
+    pub export_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub clips: Vec<ClipItemWithVersions>,
+    pub collections: Vec<CollectionExport>,
+    pub settings: AppConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FullImportReport {
+    pub clips_imported: u64,
+    /// Clips whose content already existed; their versions and collection
+    /// memberships attach to the existing clip
+    pub duplicate_clips: u64,
+    pub versions_imported: u64,
+    pub collections_imported: u64,
+    pub settings_imported: bool,
+    /// Clips collapsed by `merge_duplicates` after the import, when requested
+    pub duplicates_merged: u64,
+}
+
+/// Outcome of importing another app's clipboard history.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub clips_imported: u64,
+    /// Entries whose text is already stored
+    pub duplicates_skipped: u64,
+    /// Images, files and other entries without text
+    pub non_text_skipped: u64,
+}
+
+/// Which clips `regenerate_summaries` rewrites.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryScope {
+    All,
+    Collection(String),
+    Clips(Vec<String>),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferProgress {
+    pub stage: String,
+    pub processed: u64,
+    pub total: u64,
+}
+
+impl TransferProgress {
+    fn new(stage: &str, processed: u64, total: u64) -> Self {
+        Self {
+            stage: stage.to_string(),
+            processed,
+            total,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexProgress {
+    pub stage: String,
+    pub step: u32,
+    pub total_steps: u32,
+}
+
+impl IndexProgress {
+    fn new(stage: &str, step: u32, total_steps: u32) -> Self {
+        Self {
+            stage: stage.to_string(),
+            step,
+            total_steps,
+        }
+    }
+}
+
+/// Connection pool and SQLite settings used when opening the database.
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub pool_max_connections: u32,
+    pub pool_min_connections: u32,
+    /// How long a command waits for a free connection before failing
+    pub acquire_timeout: Duration,
+    /// How long a connection waits on another's write lock
+    pub busy_timeout: Duration,
+    /// Bytes the WAL file is truncated to after a checkpoint
+    pub journal_size_limit: i64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_connections: 5,
+            pool_min_connections: 1,
+            acquire_timeout: Duration::from_secs(10),
+            busy_timeout: Duration::from_secs(5),
+            journal_size_limit: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Embedding size the configured model produced when probed, stored in
+/// `settings.embedding_calibration`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingCalibration {
+    pub model: String,
+    pub expected_dim: usize,
+    pub calibrated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Database {
+    pool: SqlitePool,
+    ollama: OllamaClient,
+    /// `ollama` outside of tests
+    embedder: Arc<dyn EmbeddingProvider>,
+    config: AppConfig,
+    config_layers: ConfigLayers,
+    /// Settings changed in the app, stored in `settings.app_config`
+    settings: Overrides,
+    archive_path: Option<PathBuf>,
+    metrics: Arc<Metrics>,
+    read_only: bool,
+    calibration: Option<EmbeddingCalibration>,
+}
+
+impl Database {
+    pub async fn new(database_url: &str, db_config: &DatabaseConfig) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(db_config.busy_timeout)
+            .pragma("journal_size_limit", db_config.journal_size_limit.to_string());
+        let pool = SqlitePoolOptions::new()
+            .max_connections(db_config.pool_max_connections)
+            .min_connections(db_config.pool_min_connections)
+            .acquire_timeout(db_config.acquire_timeout)
+            .connect_with(options)
+            .await?;
+
+        // Create tables
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS clips (
+                id TEXT PRIMARY KEY,
+                content TEXT NOT NULL,
+                summary TEXT NOT NULL,
+                tags TEXT NOT NULL, -- JSON array
+                created_at TEXT NOT NULL,
+                last_modified TEXT NOT NULL,
+                source TEXT,
+                embedding BLOB -- Vector embedding as binary data
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        // `timestamp` used to double as capture and edit time. It becomes
+        // `last_modified`, and `created_at` is seeded from it once.
+        if rename_column_if_exists(&pool, "clips", "timestamp", "last_modified").await? {
+            add_column_if_missing(&pool, "clips", "created_at", "TEXT NOT NULL DEFAULT ''").await?;
+            sqlx::query("UPDATE clips SET created_at = last_modified WHERE created_at = ''")
+                .execute(&pool)
+                .await?;
+        }
+
+        // Columns added after the initial schema
+        add_column_if_missing(&pool, "clips", "urls", "TEXT NOT NULL DEFAULT '[]'").await?; // JSON array
+        add_column_if_missing(&pool, "clips", "embed_truncated", "INTEGER NOT NULL DEFAULT 0").await?;
+        add_column_if_missing(&pool, "clips", "content_type", "TEXT NOT NULL DEFAULT 'text'").await?;
+        add_column_if_missing(&pool, "clips", "source_url", "TEXT").await?;
+        add_column_if_missing(&pool, "clips", "rewrite_history", "TEXT NOT NULL DEFAULT '[]'").await?; // JSON array
+        add_column_if_missing(&pool, "clips", "masked", "INTEGER NOT NULL DEFAULT 0").await?;
+        add_column_if_missing(&pool, "clips", "keywords", "TEXT NOT NULL DEFAULT '[]'").await?; // JSON array
+        add_column_if_missing(&pool, "clips", "metadata", "TEXT NOT NULL DEFAULT '{}'").await?; // JSON object
+        // Only set on hash-only rows, whose content is empty
+        add_column_if_missing(&pool, "clips", "content_hash", "TEXT").await?;
+        add_column_if_missing(&pool, "clips", "content_length", "INTEGER").await?;
+        add_column_if_missing(&pool, "clips", "simhash", "INTEGER").await?;
+        // Which model produced each embedding, so vectors from different
+        // models are never compared. NULL for embeddings stored before this
+        // was tracked, or supplied by the caller.
+        add_column_if_missing(&pool, "clips", "embed_model", "TEXT").await?;
+        add_column_if_missing(&pool, "clips", "embed_dim", "INTEGER").await?;
+        add_column_if_missing(&pool, "clips", "code_language", "TEXT").await?;
+
+        // Short clip numbers. `clip_seq` holds the last one handed out, so
+        // numbers of deleted clips aren't reused; `insert_clip_row` takes
+        // the next one and the trigger moves the counter past it.
+        add_column_if_missing(&pool, "clips", "seq", "INTEGER").await?;
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_clips_seq ON clips(seq)")
+            .execute(&pool)
+            .await?;
+        sqlx::query("CREATE TABLE IF NOT EXISTS clip_seq (id INTEGER PRIMARY KEY CHECK (id = 0), last INTEGER NOT NULL)")
+            .execute(&pool)
+            .await?;
+        sqlx::query("INSERT OR IGNORE INTO clip_seq (id, last) SELECT 0, COALESCE(MAX(seq), 0) FROM clips")
+            .execute(&pool)
+            .await?;
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS clip_seq_advance AFTER INSERT ON clips WHEN new.seq IS NOT NULL BEGIN
+                UPDATE clip_seq SET last = new.seq WHERE last < new.seq;
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        // Backs the per-year counts; queries must use the same expression
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_clips_created_year ON clips(strftime('%Y', created_at))")
+            .execute(&pool)
+            .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_clips_source ON clips(source)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL -- JSON
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS clip_accesses (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                clip_id TEXT NOT NULL,
+                accessed_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_clip_accesses_clip ON clip_accesses(clip_id, accessed_at)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS clip_shares (
+                share_id TEXT PRIMARY KEY,
+                clip_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT,
+                view_count INTEGER DEFAULT 0,
+                password_hash TEXT
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS clip_versions (
+                id TEXT PRIMARY KEY,
+                clip_id TEXT NOT NULL,
+                content TEXT NOT NULL,
+                summary TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_clip_versions_clip ON clip_versions(clip_id, created_at)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS entities (
+                clip_id TEXT NOT NULL,
+                entity_type TEXT NOT NULL,
+                entity_value TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_entities_lookup ON entities(entity_type, entity_value)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS collections (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        add_column_if_missing(&pool, "collections", "auto_file", "INTEGER NOT NULL DEFAULT 0").await?;
+        add_column_if_missing(&pool, "collections", "description", "TEXT").await?;
+        add_column_if_missing(&pool, "collections", "last_described_at", "TEXT").await?;
+        // Clip count when the description was written, to tell when it's stale
+        add_column_if_missing(&pool, "collections", "described_clip_count", "INTEGER").await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS clip_collections (
+                clip_id TEXT NOT NULL,
+                collection_id TEXT NOT NULL,
+                added_at TEXT NOT NULL,
+                PRIMARY KEY (clip_id, collection_id)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_clip_collections_collection ON clip_collections(collection_id)")
+            .execute(&pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS slots (
+                slot INTEGER PRIMARY KEY CHECK (slot BETWEEN 1 AND 9),
+                clip_id TEXT NOT NULL,
+                assigned_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        // A clip leaving the table (deleted or archived) frees its slot
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS slots_clip_removed AFTER DELETE ON clips BEGIN
+                DELETE FROM slots WHERE clip_id = old.id;
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS clip_chunks (
+                clip_id TEXT NOT NULL,
+                chunk_index INTEGER NOT NULL,
+                start_char INTEGER NOT NULL,
+                end_char INTEGER NOT NULL,
+                embedding BLOB NOT NULL,
+                PRIMARY KEY (clip_id, chunk_index)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        // Chunks describe the content they were cut from: they go when the
+        // clip leaves the table or its content changes, and the chunk
+        // worker re-creates them for the new content
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS clip_chunks_clip_removed AFTER DELETE ON clips BEGIN
+                DELETE FROM clip_chunks WHERE clip_id = old.id;
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS clip_chunks_content_changed AFTER UPDATE OF content ON clips
+            WHEN new.content IS NOT old.content BEGIN
+                DELETE FROM clip_chunks WHERE clip_id = old.id;
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        add_column_if_missing(&pool, "clip_chunks", "embed_model", "TEXT").await?;
+
+        // Dimensions of embeddings stored before they were recorded
+        sqlx::query("UPDATE clips SET embed_dim = LENGTH(embedding) / 4 WHERE embed_dim IS NULL AND embedding IS NOT NULL")
+            .execute(&pool)
+            .await?;
+        clear_placeholder_embeddings(&pool).await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS global_pins (
+                clip_id TEXT PRIMARY KEY,
+                position INTEGER NOT NULL UNIQUE CHECK (position BETWEEN 0 AND 9),
+                pinned_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS global_pins_clip_removed AFTER DELETE ON clips BEGIN
+                DELETE FROM global_pins WHERE clip_id = old.id;
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS auto_filings (
+                clip_id TEXT NOT NULL,
+                collection_id TEXT NOT NULL,
+                confidence REAL NOT NULL,
+                filed_at TEXT NOT NULL,
+                PRIMARY KEY (clip_id, collection_id)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS auto_filings_clip_removed AFTER DELETE ON clips BEGIN
+                DELETE FROM auto_filings WHERE clip_id = old.id;
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        // Undone auto-filings, kept as future few-shot examples; the summary
+        // is copied so a correction outlives its clip
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS auto_file_corrections (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                clip_id TEXT NOT NULL,
+                summary TEXT NOT NULL,
+                wrong_collection_id TEXT NOT NULL,
+                correct_collection_id TEXT,
+                corrected_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS collection_rules (
+                id TEXT PRIMARY KEY,
+                collection_id TEXT NOT NULL,
+                rule_type TEXT NOT NULL,
+                rule_value TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS retention_rules (
+                id TEXT PRIMARY KEY,
+                tag TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                max_age_days INTEGER,
+                max_count INTEGER
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS reminders (
+                id TEXT PRIMARY KEY,
+                clip_id TEXT NOT NULL,
+                remind_at TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_reminders_due ON reminders(remind_at)")
+            .execute(&pool)
+            .await?;
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS reminders_clip_removed AFTER DELETE ON clips BEGIN
+                DELETE FROM reminders WHERE clip_id = old.id;
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id TEXT PRIMARY KEY,
+                operation TEXT NOT NULL,
+                entity_type TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                performed_at TEXT NOT NULL,
+                details_json TEXT
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_audit_log_entity ON audit_log(entity_id, performed_at)")
+            .execute(&pool)
+            .await?;
+
+        // Filled lazily by `get_clip_sentences`
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS clip_sentences (
+                clip_id TEXT NOT NULL,
+                sentence_index INTEGER NOT NULL,
+                start_char INTEGER NOT NULL,
+                end_char INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                PRIMARY KEY (clip_id, sentence_index)
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS clip_sentences_clip_removed AFTER DELETE ON clips BEGIN
+                DELETE FROM clip_sentences WHERE clip_id = old.id;
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS clip_sentences_content_changed AFTER UPDATE OF content ON clips
+            WHEN new.content IS NOT old.content BEGIN
+                DELETE FROM clip_sentences WHERE clip_id = old.id;
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS code_language_content_changed AFTER UPDATE OF content ON clips
+            WHEN new.content IS NOT old.content BEGIN
+                UPDATE clips SET code_language = NULL WHERE id = old.id;
+            END
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        let settings = load_settings(&pool).await?;
+        let calibration = load_calibration(&pool).await?;
+        let config_layers = ConfigLayers::default();
+        let (config, _) = config_layers.resolve(&settings)?;
+
+        ensure_fts_schema(&pool, &config.fts_tokenize()).await?;
+        ensure_keywords_fts_schema(&pool, &config.fts_tokenize()).await?;
+
+        // After the FTS triggers are current, since numbering updates clips
+        number_unnumbered_clips(&mut *pool.acquire().await?).await?;
+
+        let ollama = ollama_client(&config);
+        let metrics = Arc::new(Metrics::default());
+        metrics.set_enabled(config.metrics_enabled);
+
+        Ok(Database {
+            pool,
+            embedder: Arc::new(ollama.clone()),
+            ollama,
+            config,
+            config_layers,
+            settings,
+            archive_path: None,
+            metrics,
+            read_only: false,
+            calibration,
+        })
+    }
+
+    /// Opens the library at `path` without the ability to write to it, for
+    /// looking things up in a backup. Nothing is migrated, so a file from
+    /// a much older version may be missing columns that queries expect.
+    /// Settings, the Ollama client and metrics are shared with `self`.
+    pub async fn open_read_only(&self, path: &Path, db_config: &DatabaseConfig) -> Result<Database> {
+        if !path.is_file() {
+            return Err(anyhow!("No database file at {}", path.display()));
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .read_only(true)
+            .busy_timeout(db_config.busy_timeout);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(db_config.pool_max_connections)
+            .acquire_timeout(db_config.acquire_timeout)
+            .connect_with(options)
+            .await?;
+
+        let has_clips: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'clips')")
+                .fetch_one(&pool)
+                .await?;
+        if !has_clips {
+            pool.close().await;
+            return Err(anyhow!("{} is not a ClipSage database", path.display()));
+        }
+
+        Ok(Database {
+            pool,
+            ollama: self.ollama.clone(),
+            embedder: self.embedder.clone(),
+            config: self.config.clone(),
+            config_layers: self.config_layers.clone(),
+            settings: self.settings.clone(),
+            archive_path: None,
+            metrics: self.metrics.clone(),
+            read_only: true,
+            calibration: self.calibration.clone(),
+        })
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fails with `ReadOnlyError` when this database was opened read-only.
+    pub fn ensure_writable(&self) -> std::result::Result<(), ReadOnlyError> {
+        if self.read_only {
+            Err(ReadOnlyError)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    /// Location of the cold-storage database used by `archive_clips`.
+    pub fn set_archive_path(&mut self, path: PathBuf) {
+        self.archive_path = Some(path);
+    }
+
+    pub fn config(&self) -> &AppConfig {
+        &self.config
+    }
+
+    /// Validates and stores `config`. Only settings that differ from the
+    /// config file and environment are stored, so later edits to those still
+    /// show through. A tokenizer change rebuilds the FTS index before
+    /// anything is saved.
+    pub async fn set_config(&mut self, config: AppConfig) -> Result<()> {
+        config.validate().map_err(|e| anyhow!(e))?;
+
+        let (base, _) = self.config_layers.resolve(&Overrides::new())?;
+        let settings = overrides_between(&base, &config)?;
+
+        self.apply_config(config).await?;
+
+        sqlx::query("INSERT OR REPLACE INTO settings (key, value) VALUES ('app_config', ?)")
+            .bind(serde_json::to_string(&settings)?)
+            .execute(&self.pool)
+            .await?;
+
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Puts the config file and environment layers under the stored
+    /// settings and applies the result.
+    pub async fn set_config_layers(&mut self, layers: ConfigLayers) -> Result<()> {
+        let (config, _) = layers.resolve(&self.settings)?;
+        config.validate().map_err(|e| anyhow!(e))?;
+        self.config_layers = layers;
+        self.apply_config(config).await
+    }
+
+    /// Re-reads the config file and applies what changed. Returns whether
+    /// the effective config changed. An invalid file is rejected and the
+    /// previous values stay in effect. Tokenizer changes wait for a restart
+    /// or a settings change, since they rebuild the whole FTS index.
+    pub async fn reload_config_file(&mut self) -> Result<bool> {
+        self.config_layers.reload_file().map_err(|e| anyhow!(e))?;
+
+        let (mut config, _) = self.config_layers.resolve(&self.settings)?;
+        if config.fts_tokenize() != self.config.fts_tokenize() {
+            eprintln!("Tokenizer changes in {} apply after a restart", CONFIG_FILE_NAME);
+            config.fts_tokenizer = self.config.fts_tokenizer;
+            config.fts_remove_diacritics = self.config.fts_remove_diacritics;
+        }
+        if config == self.config {
+            return Ok(false);
+        }
+
+        self.apply_config(config).await?;
+        Ok(true)
+    }
+
+    pub fn config_file_path(&self) -> Option<&Path> {
+        self.config_layers.file_path()
+    }
+
+    /// The config in effect and which layer each setting came from.
+    pub fn effective_config(&self) -> Result<EffectiveConfig> {
+        self.config_layers.effective(&self.config, &self.settings)
+    }
+
+    async fn apply_config(&mut self, config: AppConfig) -> Result<()> {
+        // Built first so broken connection settings change nothing
+        let ollama = if config.same_ollama_connection(&self.config) {
+            None
+        } else {
+            Some(OllamaClient::from_config(&config)?)
+        };
+
+        if config.fts_tokenize() != self.config.fts_tokenize() {
+            ensure_fts_schema(&self.pool, &config.fts_tokenize()).await?;
+            ensure_keywords_fts_schema(&self.pool, &config.fts_tokenize()).await?;
+        }
+        if let Some(ollama) = ollama {
+            self.embedder = Arc::new(ollama.clone());
+            self.ollama = ollama;
+        }
+
+        self.metrics.set_enabled(config.metrics_enabled);
+        self.config = config;
+        Ok(())
+    }
+
+    /// Deletes every row of every table (except `settings` with
+    /// `keep_settings`), recreates the search indexes and vacuums the file so
+    /// deleted content doesn't linger in free pages. Without `keep_settings`
+    /// the config returns to its defaults and the PIN is removed. Short clip
+    /// numbers carry on, so an old `#12` never names a new clip.
+    pub async fn wipe_all_data(&mut self, keep_settings: bool) -> Result<WipeReport> {
+        let tables: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT name FROM sqlite_master
+            WHERE type = 'table'
+              AND name NOT LIKE 'sqlite_%'
+              AND name NOT LIKE 'clips_fts%'
+              AND name NOT LIKE 'clip_keywords_fts%'
+              AND name != 'clip_seq'
+            ORDER BY name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut report = WipeReport::default();
+        let mut tx = self.pool.begin().await?;
+        for table in tables {
+            if keep_settings && table == "settings" {
+                continue;
+            }
+            let deleted = sqlx::query(&format!("DELETE FROM {}", table))
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            report.tables.insert(table, deleted);
+        }
+        // The calibration is probed, not chosen, so it goes even when the
+        // rest of the settings stay
+        sqlx::query("DELETE FROM settings WHERE key = 'embedding_calibration'")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        self.calibration = None;
+
+        if !keep_settings {
+            self.settings = Overrides::new();
+            let (config, _) = self.config_layers.resolve(&self.settings)?;
+            self.config = config;
+            self.ollama = ollama_client(&self.config);
+            self.embedder = Arc::new(self.ollama.clone());
+            self.metrics.set_enabled(self.config.metrics_enabled);
+            report.settings_cleared = true;
+        }
+
+        for statement in ["DROP TABLE IF EXISTS clips_fts", "DROP TABLE IF EXISTS clip_keywords_fts"] {
+            sqlx::query(statement).execute(&self.pool).await?;
+        }
+        ensure_fts_schema(&self.pool, &self.config.fts_tokenize()).await?;
+        ensure_keywords_fts_schema(&self.pool, &self.config.fts_tokenize()).await?;
+
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+        Ok(report)
+    }
+
+    /// Argon2 hash of the app lock PIN, if one is set.
+    pub async fn get_pin_hash(&self) -> Result<Option<String>> {
+        Ok(sqlx::query_scalar("SELECT value FROM settings WHERE key = 'app_lock_pin'")
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+
+    pub async fn set_pin_hash(&self, hash: Option<&str>) -> Result<()> {
+        match hash {
+            Some(hash) => {
+                sqlx::query("INSERT OR REPLACE INTO settings (key, value) VALUES ('app_lock_pin', ?)")
+                    .bind(hash)
+                    .execute(&self.pool)
+                    .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM settings WHERE key = 'app_lock_pin'")
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn insert_clip(&self, clip: &ClipItem) -> Result<()> {
+        let (embedding, embed_truncated) = self.clip_embedding_or_none(clip).await;
+        let embed_model = self.clip_embed_model(clip);
+        self.metrics
+            .time(Operation::Insert, async {
+                let mut tx = self.pool.begin().await?;
+                insert_clip_row(&mut *tx, clip, embedding.as_deref(), embed_truncated, embed_model).await?;
+                record_audit(&mut *tx, "insert", "clip", &clip.id, Some(clip_audit_details(clip))).await?;
+                tx.commit().await?;
+                Ok::<_, anyhow::Error>(())
+            })
+            .await?;
+
+        if self.config.auto_categorize {
+            self.categorize_clip(&clip.id, clip.content_type).await?;
+        }
+        self.apply_collection_rules(clip).await
+    }
+
+    /// Records that `content` was copied without keeping it: the row holds
+    /// its SHA-256 and byte length but empty content and summary, is masked,
+    /// and is left out of search, exports and embedding.
+    pub async fn insert_hash_only_clip(&self, content: &str, source: Option<&str>) -> Result<String> {
+        let now = Utc::now();
+        let clip = ClipItem {
+            id: Uuid::new_v4().to_string(),
+            content: String::new(),
+            summary: String::new(),
+            tags: Vec::new(),
+            created_at: now,
+            last_modified: now,
+            source: source.map(str::to_string),
+            embedding: None,
+            urls: Vec::new(),
+            embed_truncated: false,
+            content_type: ContentType::Redacted,
+            source_url: None,
+            rewrite_history: Vec::new(),
+            masked: true,
+            keywords: Vec::new(),
+            metadata: ClipMetadata::default(),
+            seq: None,
+            code_language: None,
+        };
+
+        let mut tx = self.pool.begin().await?;
+        insert_clip_row(&mut *tx, &clip, None, false, None).await?;
+        sqlx::query("UPDATE clips SET content_hash = ?, content_length = ? WHERE id = ?")
+            .bind(sha256_hex(content))
+            .bind(content.len() as i64)
+            .bind(&clip.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(clip.id)
+    }
+
+    /// With `fuzzy_dedup` on, the id of a recent clip whose SimHash is close
+    /// enough to `content`'s to count as the same text. Clips stored before
+    /// fingerprints existed are never matched.
+    pub async fn find_fuzzy_duplicate(&self, content: &str) -> Result<Option<String>> {
+        if !self.config.fuzzy_dedup {
+            return Ok(None);
+        }
+        let Some(fingerprint) = simhash::fingerprint(content) else {
+            return Ok(None);
+        };
+
+        let recent: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT id, simhash FROM clips WHERE simhash IS NOT NULL ORDER BY created_at DESC LIMIT ?",
+        )
+        .bind(FUZZY_DEDUP_WINDOW)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(recent
+            .into_iter()
+            .map(|(id, other)| (id, simhash::distance(fingerprint, other as u64)))
+            .filter(|(_, distance)| *distance <= self.config.fuzzy_dedup_max_distance)
+            .min_by_key(|(_, distance)| *distance)
+            .map(|(id, _)| id))
+    }
+
+    /// Whether the most recently captured clip holds `content`, either in
+    /// full or, for a hash-only row, as its content hash.
+    pub async fn is_latest_clip(&self, content: &str) -> Result<bool> {
+        let latest: Option<(String, Option<String>)> =
+            sqlx::query_as("SELECT content, content_hash FROM clips ORDER BY created_at DESC LIMIT 1")
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(match latest {
+            Some((_, Some(hash))) => hash == sha256_hex(content),
+            Some((stored, None)) => stored == content,
+            None => false,
+        })
+    }
+
+    /// Inserts all of `clips` in one transaction. Embeddings are generated
+    /// up front, so the transaction doesn't wait on Ollama.
+    pub async fn batch_insert_clips(&self, clips: &[ClipItem]) -> Result<()> {
+        let mut embeddings = Vec::with_capacity(clips.len());
+        for clip in clips {
+            embeddings.push(self.clip_embedding_or_none(clip).await);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for (clip, (embedding, embed_truncated)) in clips.iter().zip(&embeddings) {
+            insert_clip_row(&mut *tx, clip, embedding.as_deref(), *embed_truncated, self.clip_embed_model(clip)).await?;
+            record_audit(&mut *tx, "insert", "clip", &clip.id, Some(clip_audit_details(clip))).await?;
+        }
+        tx.commit().await?;
+
+        for clip in clips {
+            if self.config.auto_categorize {
+                self.categorize_clip(&clip.id, clip.content_type).await?;
+            }
+            self.apply_collection_rules(clip).await?;
+        }
+        Ok(())
+    }
+
+    /// Imports the text entries of Alfred's clipboard history
+    /// (`clipboard.alfdb`), opened read-only. Entries keep their last-used
+    /// time and Alfred's source app; text that's already stored is skipped.
+    pub async fn import_from_alfred(&self, alfred_db_path: &Path) -> Result<ImportReport> {
+        let options = SqliteConnectOptions::new().filename(alfred_db_path).read_only(true);
+        let mut alfred = SqliteConnection::connect_with(&options).await?;
+        let rows = sqlx::query("SELECT dataHash, dataType, item, app, lastUsedDate FROM clipboard ORDER BY lastUsedDate")
+            .fetch_all(&mut alfred)
+            .await?;
+        alfred.close().await?;
+
+        let mut existing: std::collections::HashSet<String> = sqlx::query_scalar::<_, String>("SELECT content FROM clips")
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|content| sha256_hex(content))
+            .collect();
+
+        let mut report = ImportReport::default();
+        let mut clips = Vec::new();
+        for row in &rows {
+            let item: Option<String> = row.get("item");
+            let content = match (row.get::<i64, _>("dataType"), item) {
+                (ALFRED_TEXT_TYPE, Some(item)) if !item.trim().is_empty() => item,
+                _ => {
+                    report.non_text_skipped += 1;
+                    continue;
+                }
+            };
+            if !existing.insert(sha256_hex(&content)) {
+                report.duplicates_skipped += 1;
+                continue;
+            }
+
+            let app: Option<String> = row.get("app");
+            let mut clip = crate::capture::build_clip(&content, false, app.as_deref().unwrap_or("alfred"), None, &self.config);
+            clip.embedding = None;
+            clip.created_at = core_data_date(row.get("lastUsedDate"))?;
+            clip.last_modified = clip.created_at;
+            clips.push(clip);
+        }
+
+        self.batch_insert_clips(&clips).await?;
+        report.clips_imported = clips.len() as u64;
+        Ok(report)
+    }
+
+    pub async fn create_collection_rule(
+        &self,
+        collection_id: &str,
+        rule_type: CollectionRuleType,
+        rule_value: &str,
+    ) -> Result<CollectionRule> {
+        let exists: Option<String> = sqlx::query_scalar("SELECT id FROM collections WHERE id = ?")
+            .bind(collection_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if exists.is_none() {
+            return Err(anyhow!("Collection not found: {}", collection_id));
+        }
+        if rule_type == CollectionRuleType::RegexMatch {
+            Regex::new(rule_value).map_err(|e| anyhow!("Invalid rule pattern: {}", e))?;
+        }
+
+        let rule = CollectionRule {
+            id: Uuid::new_v4().to_string(),
+            collection_id: collection_id.to_string(),
+            rule_type,
+            rule_value: rule_value.to_string(),
+        };
+
+        sqlx::query("INSERT INTO collection_rules (id, collection_id, rule_type, rule_value) VALUES (?, ?, ?, ?)")
+            .bind(&rule.id)
+            .bind(&rule.collection_id)
+            .bind(rule.rule_type.as_str())
+            .bind(&rule.rule_value)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(rule)
+    }
+
+    pub async fn delete_collection_rule(&self, rule_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM collection_rules WHERE id = ?")
+            .bind(rule_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_collection_rules(&self) -> Result<Vec<CollectionRule>> {
+        let rows = sqlx::query("SELECT id, collection_id, rule_type, rule_value FROM collection_rules")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(CollectionRule {
+                    id: row.get("id"),
+                    collection_id: row.get("collection_id"),
+                    rule_type: CollectionRuleType::parse(&row.get::<String, _>("rule_type"))?,
+                    rule_value: row.get("rule_value"),
+                })
+            })
+            .collect()
+    }
+
+    /// Adds a retention rule for `tag` (or `default`). With neither limit
+    /// set, clips with `tag` are kept forever.
+    pub async fn create_retention_rule(
+        &self,
+        tag: &str,
+        max_age_days: Option<u32>,
+        max_count: Option<u32>,
+    ) -> Result<RetentionRule> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(anyhow!("Retention rule needs a tag"));
+        }
+        validate_retention_limits(max_age_days, max_count)?;
+
+        let rule = RetentionRule {
+            id: Uuid::new_v4().to_string(),
+            tag: tag.to_string(),
+            max_age_days,
+            max_count,
+        };
+
+        sqlx::query("INSERT INTO retention_rules (id, tag, max_age_days, max_count) VALUES (?, ?, ?, ?)")
+            .bind(&rule.id)
+            .bind(&rule.tag)
+            .bind(rule.max_age_days.map(|days| days as i64))
+            .bind(rule.max_count.map(|count| count as i64))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+                    anyhow!("A retention rule for `{}` already exists", rule.tag)
+                }
+                e => e.into(),
+            })?;
+
+        Ok(rule)
+    }
+
+    pub async fn update_retention_rule(&self, rule_id: &str, max_age_days: Option<u32>, max_count: Option<u32>) -> Result<()> {
+        validate_retention_limits(max_age_days, max_count)?;
+
+        let result = sqlx::query("UPDATE retention_rules SET max_age_days = ?, max_count = ? WHERE id = ?")
+            .bind(max_age_days.map(|days| days as i64))
+            .bind(max_count.map(|count| count as i64))
+            .bind(rule_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(anyhow!("Retention rule not found: {}", rule_id));
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete_retention_rule(&self, rule_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM retention_rules WHERE id = ?")
+            .bind(rule_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_retention_rules(&self) -> Result<Vec<RetentionRule>> {
+        let rows = sqlx::query("SELECT id, tag, max_age_days, max_count FROM retention_rules ORDER BY tag")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| RetentionRule {
+                id: row.get("id"),
+                tag: row.get("tag"),
+                max_age_days: row.get::<Option<i64>, _>("max_age_days").map(|days| days as u32),
+                max_count: row.get::<Option<i64>, _>("max_count").map(|count| count as u32),
+            })
+            .collect())
+    }
+
+    pub async fn set_clip_reminder(&self, clip_id: &str, remind_at: DateTime<Utc>) -> Result<Reminder> {
+        if self.get_clip(clip_id).await?.is_none() {
+            return Err(anyhow!("Clip not found: {}", clip_id));
+        }
+
+        let reminder = Reminder {
+            id: Uuid::new_v4().to_string(),
+            clip_id: clip_id.to_string(),
+            remind_at,
+            created_at: Utc::now(),
+        };
+        sqlx::query("INSERT INTO reminders (id, clip_id, remind_at, created_at) VALUES (?, ?, ?, ?)")
+            .bind(&reminder.id)
+            .bind(&reminder.clip_id)
+            .bind(reminder.remind_at.to_rfc3339())
+            .bind(reminder.created_at.to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(reminder)
+    }
+
+    /// Pending reminders, soonest first.
+    pub async fn list_reminders(&self) -> Result<Vec<Reminder>> {
+        let rows = sqlx::query("SELECT id, clip_id, remind_at, created_at FROM reminders ORDER BY remind_at")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(row_to_reminder).collect()
+    }
+
+    pub async fn cancel_reminder(&self, id: &str) -> Result<()> {
+        let deleted = sqlx::query("DELETE FROM reminders WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        if deleted == 0 {
+            return Err(anyhow!("Reminder not found: {}", id));
+        }
+        Ok(())
+    }
+
+    /// Removes and returns the reminders due by `now`, each with its clip.
+    pub async fn take_due_reminders(&self, now: DateTime<Utc>) -> Result<Vec<(Reminder, ClipItem)>> {
+        let mut tx = self.pool.begin().await?;
+        // RFC 3339 strings in UTC sort chronologically
+        let rows = sqlx::query("SELECT id, clip_id, remind_at, created_at FROM reminders WHERE remind_at <= ? ORDER BY remind_at")
+            .bind(now.to_rfc3339())
+            .fetch_all(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM reminders WHERE remind_at <= ?")
+            .bind(now.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        let mut due = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let reminder = row_to_reminder(row)?;
+            if let Some(clip) = self.get_clip(&reminder.clip_id).await? {
+                due.push((reminder, clip));
+            }
+        }
+        Ok(due)
+    }
+
+    /// What `apply_retention_rules` would delete right now, per rule.
+    /// Clips `prune_protected` covers are never included.
+    pub async fn preview_prune(&self) -> Result<PruneReport> {
+        let rules = self.list_retention_rules().await?;
+        if rules.is_empty() {
+            return Ok(PruneReport::default());
+        }
+
+        let (protected, binds) = self.prune_protected();
+        let sql = format!(
+            "SELECT id, tags, created_at FROM clips WHERE NOT ({}) ORDER BY created_at DESC",
+            protected
+        );
+        let mut query = sqlx::query(&sql);
+        for value in binds {
+            query = query.bind(value);
+        }
+        let rows = query.fetch_all(&self.pool).await?;
+        let candidates = rows
+            .iter()
+            .map(|row| {
+                let created_at: String = row.get("created_at");
+                Ok(RetentionCandidate {
+                    id: row.get("id"),
+                    tags: serde_json::from_str(&row.get::<String, _>("tags")).unwrap_or_default(),
+                    created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut by_rule: Vec<RulePrune> = rules
+            .iter()
+            .map(|rule| RulePrune {
+                rule_id: rule.id.clone(),
+                tag: rule.tag.clone(),
+                clips: Vec::new(),
+            })
+            .collect();
+        let mut deleted = 0;
+        for (clip_id, rule) in retention::plan(&rules, &candidates, Utc::now()) {
+            if let Some(clip) = self.get_clip(&clip_id).await? {
+                by_rule[rule].clips.push(ClipPreview::new(&clip, self.config.preview_chars));
+                deleted += 1;
+            }
+        }
+        by_rule.retain(|rule| !rule.clips.is_empty());
+
+        Ok(PruneReport { deleted, by_rule })
+    }
+
+    // SQL condition on `clips`, with its bind values, for clips no pruning
+    // may delete: those in a quick-access slot, pinned, or exempted by tag
+    // or collection in the config
+    fn prune_protected(&self) -> (String, Vec<&str>) {
+        let mut conditions = vec![
+            "id IN (SELECT clip_id FROM slots)".to_string(),
+            "id IN (SELECT clip_id FROM global_pins)".to_string(),
+        ];
+        let mut binds = Vec::new();
+
+        let tags = &self.config.retention_exempt_tags;
+        if !tags.is_empty() {
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM json_each(clips.tags) WHERE json_each.value COLLATE NOCASE IN ({}))",
+                vec!["?"; tags.len()].join(", ")
+            ));
+            binds.extend(tags.iter().map(String::as_str));
+        }
+        let collections = &self.config.retention_exempt_collections;
+        if !collections.is_empty() {
+            conditions.push(format!(
+                r#"id IN (
+                    SELECT cc.clip_id FROM clip_collections cc
+                    JOIN collections col ON col.id = cc.collection_id
+                    WHERE col.name COLLATE NOCASE IN ({})
+                )"#,
+                vec!["?"; collections.len()].join(", ")
+            ));
+            binds.extend(collections.iter().map(String::as_str));
+        }
+
+        (conditions.join(" OR "), binds)
+    }
+
+    /// Deletes every clip the retention rules no longer keep and reports
+    /// them per rule.
+    pub async fn apply_retention_rules(&self) -> Result<PruneReport> {
+        let report = self.preview_prune().await?;
+        for clip in report.by_rule.iter().flat_map(|rule| &rule.clips) {
+            self.delete_clip(&clip.id).await?;
+        }
+
+        Ok(report)
+    }
+
+    /// Collections whose rules `clip` satisfies, each listed once. Rules that
+    /// fail to evaluate (e.g. a broken regex) are skipped.
+    pub async fn evaluate_rules_for_clip(&self, clip: &ClipItem) -> Result<Vec<String>> {
+        let mut collection_ids: Vec<String> = Vec::new();
+        for rule in self.list_collection_rules().await? {
+            if collection_ids.contains(&rule.collection_id) {
+                continue;
+            }
+            if rule.rule_type.matches(&rule.rule_value, clip).unwrap_or(false) {
+                collection_ids.push(rule.collection_id);
+            }
+        }
+
+        Ok(collection_ids)
+    }
+
+    async fn apply_collection_rules(&self, clip: &ClipItem) -> Result<()> {
+        for collection_id in self.evaluate_rules_for_clip(clip).await? {
+            self.add_clips_to_collection(&collection_id, std::slice::from_ref(&clip.id)).await?;
+        }
+        Ok(())
+    }
+
+    /// Files a clip into the collection for its content type (creating it if
+    /// needed) and takes it out of the other content-type collections, so a
+    /// re-detected clip moves rather than piling up memberships.
+    pub async fn categorize_clip(&self, clip_id: &str, content_type: ContentType) -> Result<()> {
+        let target = content_type.category_collection();
+        let mut tx = self.pool.begin().await?;
+
+        let existing: Option<String> =
+            sqlx::query_scalar("SELECT id FROM collections WHERE name = ? ORDER BY created_at LIMIT 1")
+                .bind(target)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let collection_id = match existing {
+            Some(id) => id,
+            None => {
+                let id = Uuid::new_v4().to_string();
+                sqlx::query("INSERT INTO collections (id, name, created_at) VALUES (?, ?, ?)")
+                    .bind(&id)
+                    .bind(target)
+                    .bind(Utc::now().to_rfc3339())
+                    .execute(&mut *tx)
+                    .await?;
+                id
+            }
+        };
+
+        let others: Vec<&str> = [ContentType::Url, ContentType::Code, ContentType::Email, ContentType::Text]
+            .iter()
+            .map(ContentType::category_collection)
+            .filter(|name| *name != target)
+            .collect();
+        let sql = format!(
+            r#"
+            DELETE FROM clip_collections
+            WHERE clip_id = ? AND collection_id IN (SELECT id FROM collections WHERE name IN ({}))
+            "#,
+            vec!["?"; others.len()].join(", ")
+        );
+        let mut query = sqlx::query(&sql).bind(clip_id);
+        for name in &others {
+            query = query.bind(*name);
+        }
+        query.execute(&mut *tx).await?;
+
+        sqlx::query("INSERT OR IGNORE INTO clip_collections (clip_id, collection_id, added_at) VALUES (?, ?, ?)")
+            .bind(clip_id)
+            .bind(&collection_id)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // Generate embedding using Ollama if not provided. Over-long content is
+    // truncated or skipped per config, but FTS always indexes the full text.
+    async fn clip_embedding(&self, clip: &ClipItem) -> Result<(Option<Vec<f32>>, bool)> {
+        if clip.embedding.is_some() {
+            return Ok((clip.embedding.clone(), clip.embed_truncated));
+        }
+
+        match self.embedding_input(&clip.content) {
+            (Some(text), truncated) => Ok((Some(self.embed_text(text).await?), truncated || clip.embed_truncated)),
+            (None, truncated) => Ok((None, truncated)),
+        }
+    }
+
+    // The part of `content` to embed, `None` when the config skips it, and
+    // whether it was cut short
+    fn embedding_input<'a>(&self, content: &'a str) -> (Option<&'a str>, bool) {
+        match content.char_indices().nth(self.config.embedding_max_chars) {
+            None => (Some(content), false),
+            Some((cutoff, _)) => match self.config.oversized_embedding {
+                OversizedEmbedding::Truncate => (Some(&content[..cutoff]), true),
+                OversizedEmbedding::Skip => (None, true),
+            },
+        }
+    }
+
+    // `clip_embedding` for inserts: a capture is stored without an
+    // embedding rather than lost when Ollama is unavailable, and
+    // `backfill_embeddings` fills it in later
+    async fn clip_embedding_or_none(&self, clip: &ClipItem) -> (Option<Vec<f32>>, bool) {
+        match self.clip_embedding(clip).await {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("Storing clip {} without an embedding: {}", clip.id, e);
+                (None, clip.embed_truncated)
+            }
+        }
+    }
+
+    // Model behind the embedding `clip_embedding` returns: ours when it
+    // embedded the content, unknown when the caller supplied a vector
+    fn clip_embed_model(&self, clip: &ClipItem) -> Option<&str> {
+        clip.embedding.is_none().then(|| self.ollama.model())
+    }
+
+    /// Creates one clip per line of `clip_id`'s content, skipping lines
+    /// shorter than `min_line_length` characters. Each child records its
+    /// parent in `source` as `split:{clip_id}`.
+    pub async fn split_clip_by_lines(&self, clip_id: &str, min_line_length: usize) -> Result<Vec<ClipItem>> {
+        let parent = self
+            .get_clip(clip_id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", clip_id))?;
+
+        let now = Utc::now();
+        let source = Some(format!("split:{}", clip_id));
+        let children: Vec<ClipItem> = parent
+            .content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && line.chars().count() >= min_line_length)
+            .map(|line| split_child(&parent, line, source.clone(), now))
+            .collect();
+
+        if children.is_empty() {
+            return Err(anyhow!("No lines of at least {} characters to split", min_line_length));
+        }
+
+        self.batch_insert_clips(&children).await?;
+        Ok(children)
+    }
+
+    /// Creates one clip per non-empty piece of `clip_id`'s content, in order.
+    /// Children keep the parent's source and carry a `split-from:{clip_id}`
+    /// tag; the original clip is left untouched.
+    pub async fn split_clip(&self, clip_id: &str, mode: &SplitMode) -> Result<Vec<ClipItem>> {
+        let parent = self
+            .get_clip(clip_id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", clip_id))?;
+
+        let pieces = mode.split(&parent.content)?;
+        if pieces.is_empty() {
+            return Err(anyhow!("Nothing to split"));
+        }
+        if pieces.len() > MAX_SPLIT_PIECES {
+            return Err(anyhow!(
+                "Splitting would create {} clips (limit {})",
+                pieces.len(),
+                MAX_SPLIT_PIECES
+            ));
+        }
+
+        let now = Utc::now();
+        let marker = format!("split-from:{}", clip_id);
+        let children: Vec<ClipItem> = pieces
+            .into_iter()
+            .map(|piece| {
+                let mut child = split_child(&parent, piece, parent.source.clone(), now);
+                child.tags.push(marker.clone());
+                child
+            })
+            .collect();
+
+        self.batch_insert_clips(&children).await?;
+        Ok(children)
+    }
+
+    /// Joins the clips `ids`, in that order and separated by `separator`,
+    /// into a new clip tagged `merged` with a fresh summary and embedding.
+    /// The result is masked if any source was. The sources are deleted
+    /// afterwards if `delete_sources`.
+    pub async fn merge_clips_into_new(&self, ids: &[String], separator: &str, delete_sources: bool) -> Result<ClipItem> {
+        if ids.len() < 2 {
+            return Err(anyhow!("Select at least two clips to merge"));
+        }
+        let mut sources = Vec::with_capacity(ids.len());
+        for id in ids {
+            let clip = self.get_clip(id).await?.ok_or_else(|| anyhow!("Clip not found: {}", id))?;
+            if clip.content_type == ContentType::Redacted {
+                return Err(anyhow!("Clip {} was stored as a hash only and can't be merged", id));
+            }
+            sources.push(clip);
+        }
+
+        let content = sources.iter().map(|clip| clip.content.as_str()).collect::<Vec<_>>().join(separator);
+        let source = sources[0].source.as_deref().unwrap_or(crate::capture::CLIPBOARD_SOURCE);
+        let mut merged = crate::capture::build_clip(&content, false, source, None, &self.config);
+        merged.summary = self.summarize(&content).await?;
+        merged.tags.push(MERGED_TAG.to_string());
+        merged.masked = sources.iter().any(|clip| clip.masked);
+        merged.metadata.extra.insert("merged_from".to_string(), serde_json::json!(ids));
+
+        self.insert_clip(&merged).await?;
+        if delete_sources {
+            self.delete_clips(ids).await?;
+        }
+        Ok(self.get_clip(&merged.id).await?.unwrap_or(merged))
+    }
+
+    pub async fn get_clip(&self, id: &str) -> Result<Option<ClipItem>> {
+        let row = sqlx::query(&format!("SELECT {} FROM clips WHERE id = ?", CLIP_COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.as_ref().map(row_to_clip).transpose()
+    }
+
+    /// The clip id `reference` stands for: a clip's short number, bare or
+    /// as `#12`, or its id. Anything that isn't a number is taken to be an
+    /// id and returned as is. Fails with `ClipRefError` when a number
+    /// matches no clip, or a bare one is both a short number and an id.
+    pub async fn resolve_clip_id(&self, reference: &str) -> Result<String> {
+        let reference = reference.trim();
+        let (number, explicit) = match reference.strip_prefix('#') {
+            Some(number) => (number, true),
+            None => (reference, false),
+        };
+        let Ok(seq) = number.parse::<i64>() else {
+            return Ok(reference.to_string());
+        };
+
+        let by_seq: Option<String> = sqlx::query_scalar("SELECT id FROM clips WHERE seq = ?")
+            .bind(seq)
+            .fetch_optional(&self.pool)
+            .await?;
+        if explicit {
+            return by_seq.ok_or_else(|| ClipRefError::NotFound(reference.to_string()).into());
+        }
+
+        let by_id: Option<String> = sqlx::query_scalar("SELECT id FROM clips WHERE id = ?")
+            .bind(reference)
+            .fetch_optional(&self.pool)
+            .await?;
+        match (by_seq, by_id) {
+            (Some(a), Some(b)) if a != b => Err(ClipRefError::Ambiguous(reference.to_string()).into()),
+            (Some(id), _) | (None, Some(id)) => Ok(id),
+            (None, None) => Err(ClipRefError::NotFound(reference.to_string()).into()),
+        }
+    }
+
+    /// `resolve_clip_id` for each of `references`, in order.
+    pub async fn resolve_clip_ids(&self, references: &[String]) -> Result<Vec<String>> {
+        let mut ids = Vec::with_capacity(references.len());
+        for reference in references {
+            ids.push(self.resolve_clip_id(reference).await?);
+        }
+        Ok(ids)
+    }
+
+    /// Merges `patch` into the clip's metadata and returns the result. A
+    /// `null` value removes that key. Fails, changing nothing, if a known
+    /// field would get a value of the wrong type.
+    pub async fn update_metadata(&self, clip_id: &str, patch: serde_json::Map<String, serde_json::Value>) -> Result<ClipMetadata> {
+        let mut tx = self.pool.begin().await?;
+
+        let stored: String = sqlx::query_scalar("SELECT metadata FROM clips WHERE id = ?")
+            .bind(clip_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", clip_id))?;
+        let mut merged: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&stored)?;
+        for (key, value) in &patch {
+            if value.is_null() {
+                merged.remove(key);
+            } else {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+        let metadata: ClipMetadata = serde_json::from_value(serde_json::Value::Object(merged))
+            .map_err(|e| anyhow!("Invalid metadata: {}", e))?;
+
+        sqlx::query("UPDATE clips SET metadata = ? WHERE id = ?")
+            .bind(serde_json::to_string(&metadata)?)
+            .bind(clip_id)
+            .execute(&mut *tx)
+            .await?;
+        record_audit(&mut *tx, "update_metadata", "clip", clip_id, Some(serde_json::Value::Object(patch))).await?;
+        tx.commit().await?;
+
+        Ok(metadata)
+    }
+
+    /// Records the web page `clip_id` was copied from, e.g. as reported by the
+    /// browser extension. `url` is stored in canonical form.
+    pub async fn set_clip_source_url(&self, clip_id: &str, url: &str) -> Result<()> {
+        let url = crate::urls::canonicalize(url.trim()).ok_or_else(|| anyhow!("Not a URL: {}", url))?;
+        let mut clip = self
+            .get_clip(clip_id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", clip_id))?;
+
+        clip.source_url = Some(url);
+        self.update_clip(&clip).await
+    }
+
+    /// Detects the programming language of the clip's content and stores it
+    /// as `code_language`: a syntect syntax name such as `Rust` or `SQL`, or
+    /// `highlight::UNKNOWN_LANGUAGE` when the snippet is ambiguous.
+    pub async fn detect_code_language(&self, clip_id: &str) -> Result<String> {
+        let content: String = sqlx::query_scalar("SELECT content FROM clips WHERE id = ?")
+            .bind(clip_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", clip_id))?;
+        let language = highlight::detect_language(&content).unwrap_or_else(|| highlight::UNKNOWN_LANGUAGE.to_string());
+
+        sqlx::query("UPDATE clips SET code_language = ? WHERE id = ?")
+            .bind(&language)
+            .bind(clip_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(language)
+    }
+
+    /// Clips `detect_code_language` found to be in `language` (matched
+    /// case-insensitively), newest first.
+    pub async fn get_clips_by_language(&self, language: &str, limit: i32) -> Result<Vec<ClipItem>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM clips WHERE code_language = ? COLLATE NOCASE ORDER BY created_at DESC LIMIT ?",
+            CLIP_COLUMNS
+        ))
+        .bind(language.trim())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(row_to_clip).collect()
+    }
+
+    /// Clips copied from the page at `url`, newest first. `url` is
+    /// canonicalized and matched as a prefix, so anchors and query strings
+    /// on the stored page URL still match.
+    pub async fn get_clips_by_source_url(&self, url: &str, limit: i32) -> Result<Vec<ClipItem>> {
+        let url = crate::urls::canonicalize(url.trim()).unwrap_or_else(|| url.trim().to_string());
+        let pattern = format!("{}%", escape_like(&url));
+
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM clips WHERE source_url LIKE ? ESCAPE '\\' ORDER BY created_at DESC LIMIT ?",
+            CLIP_COLUMNS
+        ))
+        .bind(pattern)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(row_to_clip).collect()
+    }
+
+    /// Persists every mutable field of `clip` and bumps `last_modified`;
+    /// `created_at` is never rewritten.
+    pub async fn update_clip(&self, clip: &ClipItem) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE clips SET
+                content = ?, summary = ?, tags = ?, last_modified = ?, source = ?, embedding = ?,
+                urls = ?, embed_truncated = ?, content_type = ?, source_url = ?, rewrite_history = ?,
+                masked = ?, keywords = ?, embed_dim = ?, simhash = ?,
+                -- SET sees the old row: keep the model only if the vector is unchanged
+                embed_model = CASE WHEN embedding IS ? THEN embed_model END
+            WHERE id = ?
+            "#,
+        )
+        .bind(&clip.content)
+        .bind(&clip.summary)
+        .bind(serde_json::to_string(&clip.tags)?)
+        .bind(Utc::now().to_rfc3339())
+        .bind(&clip.source)
+        .bind(clip.embedding.as_deref().map(encode_embedding))
+        .bind(serde_json::to_string(&clip.urls)?)
+        .bind(clip.embed_truncated)
+        .bind(clip.content_type.as_str())
+        .bind(&clip.source_url)
+        .bind(serde_json::to_string(&clip.rewrite_history)?)
+        .bind(clip.masked)
+        .bind(serde_json::to_string(&clip.keywords)?)
+        .bind(clip.embedding.as_ref().map(|embedding| embedding.len() as i64))
+        .bind(simhash::fingerprint(&clip.content).map(|fingerprint| fingerprint as i64))
+        .bind(clip.embedding.as_deref().map(encode_embedding))
+        .bind(&clip.id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow!("Clip not found: {}", clip.id));
+        }
+        record_audit(&mut *tx, "update", "clip", &clip.id, Some(clip_audit_details(clip))).await?;
+        tx.commit().await?;
+
+        if self.config.auto_categorize {
+            self.categorize_clip(&clip.id, clip.content_type).await?;
+        }
+        Ok(())
+    }
+
+    /// Diffs clip `id_a` (old) against `id_b` (new). Masked clips and clips
+    /// captured from undecodable clipboard data (tagged `binary`) are refused.
+    pub async fn diff_clips(&self, id_a: &str, id_b: &str, mode: DiffMode) -> Result<TextDiffResult> {
+        let mut clips = Vec::with_capacity(2);
+        for id in [id_a, id_b] {
+            let clip = self
+                .get_clip(id)
+                .await?
+                .ok_or_else(|| anyhow!("Clip not found: {}", id))?;
+            if clip.tags.iter().any(|tag| tag == "binary") {
+                return Err(anyhow!("Clip is not text: {}", id));
+            }
+            if clip.masked {
+                return Err(anyhow!("Clip is masked: {}", id));
+            }
+            clips.push(clip);
+        }
+
+        Ok(diff_texts(&clips[0].content, &clips[1].content, mode, id_a, id_b))
+    }
+
+    /// Asks the model for keyphrases describing a clip and stores them,
+    /// replacing any earlier set. `last_modified` is left alone.
+    pub async fn generate_clip_keywords(&self, id: &str) -> Result<Vec<String>> {
+        let clip = self
+            .get_clip(id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", id))?;
+
+        let keywords = self.ollama.generate_keywords(&clip.content, KEYWORDS_PER_CLIP).await?;
+
+        sqlx::query("UPDATE clips SET keywords = ? WHERE id = ?")
+            .bind(serde_json::to_string(&keywords)?)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(keywords)
+    }
+
+    pub async fn search_by_keywords(&self, query: &str, limit: i32) -> Result<Vec<ClipItem>> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM clips c
+            JOIN clip_keywords_fts kw ON c.id = kw.id
+            WHERE clip_keywords_fts MATCH ?
+            ORDER BY rank
+            LIMIT ?
+            "#,
+            prefixed_clip_columns("c")
+        ))
+        .bind(query)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.rows_to_clips(rows).await
+    }
+
+    /// Marks a clip as masked (or not). The update trigger re-indexes it, so
+    /// a masked clip's content and summary drop out of search immediately.
+    pub async fn set_clip_masked(&self, id: &str, masked: bool) -> Result<()> {
+        let result = sqlx::query("UPDATE clips SET masked = ? WHERE id = ?")
+            .bind(masked)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow!("Clip not found: {}", id));
+        }
+
+        Ok(())
+    }
+
+    /// Records the clip's current content and summary in `clip_versions`.
+    pub async fn save_version(&self, clip: &ClipItem) -> Result<ClipVersion> {
+        let version = ClipVersion {
+            id: Uuid::new_v4().to_string(),
+            clip_id: clip.id.clone(),
+            content: clip.content.clone(),
+            summary: clip.summary.clone(),
+            created_at: Utc::now(),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO clip_versions (id, clip_id, content, summary, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&version.id)
+        .bind(&version.clip_id)
+        .bind(&version.content)
+        .bind(&version.summary)
+        .bind(version.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(version)
+    }
+
+    /// Replaces the capture-time summary with the model's, for clips that
+    /// `llm-when-long` applies to.
+    pub async fn summarize_with_model(&self, id: &str) -> Result<()> {
+        let clip = self
+            .get_clip(id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", id))?;
+
+        let summary = self.summarize(&clip.content).await?;
+        self.set_summary(id, &summary).await
+    }
+
+    /// Rewrites the summaries of the clips in `scope` with the current
+    /// `summary_strategy`, reporting progress as it goes. Hash-only clips
+    /// have nothing to summarize and are skipped. Returns how many changed.
+    pub async fn regenerate_summaries(&self, scope: &SummaryScope, progress: impl Fn(TransferProgress)) -> Result<u64> {
+        let rows: Vec<(String, String, String)> = match scope {
+            SummaryScope::All => {
+                sqlx::query_as("SELECT id, content, summary FROM clips WHERE content != '' ORDER BY created_at DESC")
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+            SummaryScope::Collection(collection_id) => {
+                sqlx::query_as(
+                    r#"
+                    SELECT c.id, c.content, c.summary FROM clips c
+                    JOIN clip_collections cc ON cc.clip_id = c.id
+                    WHERE cc.collection_id = ? AND c.content != ''
+                    ORDER BY c.created_at DESC
+                    "#,
+                )
+                .bind(collection_id)
+                .fetch_all(&self.pool)
+                .await?
+            }
+            SummaryScope::Clips(ids) => {
+                let sql = format!(
+                    "SELECT id, content, summary FROM clips WHERE content != '' AND id IN ({}) ORDER BY created_at DESC",
+                    vec!["?"; ids.len()].join(", ")
+                );
+                let mut query = sqlx::query_as::<_, (String, String, String)>(&sql);
+                for id in ids {
+                    query = query.bind(id);
+                }
+                query.fetch_all(&self.pool).await?
+            }
+        };
+
+        let total = rows.len() as u64;
+        let mut changed = 0;
+        progress(TransferProgress::new("summaries", 0, total));
+        for (processed, (id, content, old_summary)) in rows.iter().enumerate() {
+            let summary = self.summarize(content).await?;
+            if summary != *old_summary {
+                self.set_summary(id, &summary).await?;
+                changed += 1;
+            }
+            // The model makes each clip slow enough to report every one
+            progress(TransferProgress::new("summaries", processed as u64 + 1, total));
+        }
+
+        Ok(changed)
+    }
+
+    // The summary `summary_strategy` gives `content`
+    async fn summarize(&self, content: &str) -> Result<String> {
+        if crate::capture::needs_model_summary(content, &self.config) {
+            Ok(self
+                .ollama
+                .generate_summary(content, self.config.summary_max_tokens)
+                .await?
+                .trim()
+                .to_string())
+        } else {
+            Ok(crate::capture::quick_summary(content, self.config.summary_strategy))
+        }
+    }
+
+    async fn set_summary(&self, id: &str, summary: &str) -> Result<()> {
+        sqlx::query("UPDATE clips SET summary = ? WHERE id = ?")
+            .bind(summary)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Rewrites a clip's content with the LLM, then regenerates its summary and
+    /// embedding. The previous content is kept in `clip_versions` when asked.
+    pub async fn rewrite_clip(&self, id: &str, instruction: &str, create_new_version: bool) -> Result<ClipItem> {
+        let mut clip = self
+            .get_clip(id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", id))?;
+
+        let rewritten = self.ollama.rewrite(&clip.content, instruction).await?;
+        if rewritten.is_empty() {
+            return Err(anyhow!("Model returned an empty rewrite"));
+        }
+
+        if create_new_version {
+            self.save_version(&clip).await?;
+        }
+
+        clip.summary = self
+            .ollama
+            .generate_summary(&rewritten, self.config.summary_max_tokens)
+            .await?
+            .trim()
+            .to_string();
+        clip.embedding = Some(self.embed_text(&rewritten).await?);
+        clip.embed_truncated = false;
+        clip.urls = crate::urls::extract_urls(&rewritten);
+        clip.content = rewritten;
+
+        clip.rewrite_history.push(RewriteRecord {
+            instruction: instruction.to_string(),
+            rewritten_at: Utc::now(),
+        });
+        if clip.rewrite_history.len() > MAX_REWRITE_HISTORY {
+            let excess = clip.rewrite_history.len() - MAX_REWRITE_HISTORY;
+            clip.rewrite_history.drain(..excess);
+        }
+
+        self.update_clip(&clip).await?;
+        sqlx::query("UPDATE clips SET embed_model = ? WHERE id = ?")
+            .bind(self.ollama.model())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        self.get_clip(id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", id))
+    }
+
+    pub async fn search_clips(&self, query: &str, limit: i32) -> Result<Vec<ClipItem>> {
+        Ok(self.search_clips_timed(query, limit).await?.0)
+    }
+
+    /// `search_clips` with the duration of each stage. Slow searches are
+    /// logged per `slow_search_warn_ms`.
+    pub async fn search_clips_timed(&self, query: &str, limit: i32) -> Result<(Vec<ClipItem>, SearchTimings)> {
+        let started = std::time::Instant::now();
+        let mut timings = SearchTimings::default();
+
+        // Get text search results
+        let stage = std::time::Instant::now();
+        let text_results = self.text_search_items(query, limit, 0).await?;
+        timings.fts_ms = elapsed_ms(stage);
+
+        // Get semantic search results
+        let stage = std::time::Instant::now();
+        let query_embedding = self.embed_text(query).await?;
+        timings.embedding_ms = elapsed_ms(stage);
+
+        let stage = std::time::Instant::now();
+        let semantic_results = self.semantic_search(&query_embedding, limit).await?;
+        timings.similarity_ms = elapsed_ms(stage);
+
+        // Combine and deduplicate results
+        let stage = std::time::Instant::now();
+        let mut combined = Vec::new();
+        let mut seen_ids = std::collections::HashSet::new();
+
+        for clip in text_results.into_iter().chain(semantic_results.into_iter()) {
+            if seen_ids.insert(clip.id.clone()) {
+                combined.push(clip);
+            }
+        }
+        combined.truncate(limit as usize);
+        timings.fusion_ms = elapsed_ms(stage);
+
+        timings.total_ms = elapsed_ms(started);
+        self.warn_if_slow_search(query, &timings);
+        Ok((combined, timings))
+    }
+
+    /// Logs the stage breakdown of a search that took longer than
+    /// `slow_search_warn_ms`.
+    pub fn warn_if_slow_search(&self, query: &str, timings: &SearchTimings) {
+        let threshold = self.config.slow_search_warn_ms;
+        if threshold > 0 && timings.total_ms > threshold as f64 {
+            eprintln!(
+                "Slow search ({} chars) took {:.1} ms: {}",
+                query.chars().count(),
+                timings.total_ms,
+                timings
+            );
+        }
+    }
+
+    /// Full-text matches for `query` by rank, `limit` at a time starting at
+    /// `offset`, with the total number of matches.
+    pub async fn text_search(&self, query: &str, limit: i32, offset: i32) -> Result<SearchPage> {
+        let items = self.text_search_items(query, limit, offset).await?;
+
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clips_fts WHERE clips_fts MATCH ?")
+            .bind(query)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(Page::new(items, total as u64, offset, limit))
+    }
+
+    async fn text_search_items(&self, query: &str, limit: i32, offset: i32) -> Result<Vec<ClipItem>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT c.*
+            FROM clips c
+            JOIN clips_fts fts ON c.id = fts.id
+            WHERE clips_fts MATCH ?
+            ORDER BY rank
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(query)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.rows_to_clips(rows).await
+    }
+
+    /// A page of hybrid results: full-text matches by rank, then clips
+    /// scoring at least `min_similarity` against the query that didn't match
+    /// the text, by score. The total counts both without duplicates.
+    pub async fn search_clips_page(
+        &self,
+        query: &str,
+        limit: i32,
+        offset: i32,
+        min_similarity: f32,
+    ) -> Result<SearchPage> {
+        let mut ids: Vec<String> = sqlx::query_scalar("SELECT id FROM clips_fts WHERE clips_fts MATCH ? ORDER BY rank")
+            .bind(query)
+            .fetch_all(&self.pool)
+            .await?;
+        let mut seen_ids: std::collections::HashSet<String> = ids.iter().cloned().collect();
+
+        let query_embedding = self.embed_text(query).await?;
+        for (score, id) in self.score_clips(&query_embedding).await? {
+            if score >= min_similarity && seen_ids.insert(id.clone()) {
+                ids.push(id);
+            }
+        }
+
+        let page_ids: Vec<String> = ids
+            .iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect();
+        let items = self.get_clips_by_ids(&page_ids).await?;
+
+        Ok(Page::new(items, ids.len() as u64, offset, limit))
+    }
+
+    /// Clips whose text contains `phrase`, oldest first, each paired with a
+    /// line diff against the match before it. Masked clips aren't in the
+    /// content index and so never appear.
+    pub async fn get_clip_timeline(&self, phrase: &str) -> Result<Vec<TimelineEntry>> {
+        let phrase = phrase.trim();
+        if phrase.is_empty() {
+            return Err(anyhow!("Timeline phrase is empty"));
+        }
+
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM clips c
+            JOIN clips_fts fts ON c.id = fts.id
+            WHERE clips_fts MATCH ?
+            ORDER BY c.created_at ASC, c.id ASC
+            LIMIT ?
+            "#,
+            prefixed_clip_columns("c")
+        ))
+        .bind(format!("content : {}", fts_phrase(phrase)))
+        .bind(TIMELINE_LIMIT)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let clips = self.rows_to_clips(rows).await?;
+        let mut timeline: Vec<TimelineEntry> = Vec::with_capacity(clips.len());
+        for clip in clips {
+            let previous = timeline.last().map_or("", |entry| entry.clip.content.as_str());
+            let diff = line_diff(previous, &clip.content);
+            timeline.push(TimelineEntry { clip, diff });
+        }
+
+        Ok(timeline)
+    }
+
+    /// Newest captures first.
+    pub async fn get_recent_clips(&self, limit: i32) -> Result<Vec<ClipItem>> {
+        self.get_recent_clips_by(limit, SortField::CreatedAt).await
+    }
+
+    /// The `n_tags` most used tags, most used first, each with its
+    /// `clips_per_tag` newest clips. A clip appears under every one of its
+    /// tags that made the cut.
+    pub async fn get_clips_grouped_by_top_tags(&self, n_tags: u32, clips_per_tag: u32) -> Result<Vec<TaggedClipGroup>> {
+        // One connection for all the queries rather than a checkout per tag
+        let mut conn = self.pool.acquire().await?;
+        let tags: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT j.value AS tag
+            FROM clips c, json_each(c.tags) j
+            GROUP BY tag
+            ORDER BY COUNT(*) DESC, tag
+            LIMIT ?
+            "#,
+        )
+        .bind(n_tags as i64)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let sql = format!(
+            r#"
+            SELECT {} FROM clips
+            WHERE EXISTS (SELECT 1 FROM json_each(clips.tags) WHERE json_each.value = ?)
+            ORDER BY created_at DESC
+            LIMIT ?
+            "#,
+            CLIP_COLUMNS
+        );
+        let mut groups = Vec::with_capacity(tags.len());
+        for tag in tags {
+            let rows = sqlx::query(&sql)
+                .bind(&tag)
+                .bind(clips_per_tag as i64)
+                .fetch_all(&mut *conn)
+                .await?;
+            groups.push(TaggedClipGroup {
+                tag,
+                clips: self.rows_to_clips(rows).await?,
+            });
+        }
+
+        Ok(groups)
+    }
+
+    pub async fn get_recent_clips_by(&self, limit: i32, sort: SortField) -> Result<Vec<ClipItem>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM clips ORDER BY {} DESC LIMIT ?",
+            CLIP_COLUMNS,
+            sort.column()
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.rows_to_clips(rows).await
+    }
+
+    /// One page of all clips for the data-table view, ties broken by id so
+    /// pages never overlap.
+    pub async fn list_clips(&self, sort_by: ClipTableSort, descending: bool, limit: i32, offset: i32) -> Result<ClipTablePage> {
+        let direction = if descending { "DESC" } else { "ASC" };
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {}, (SELECT COUNT(*) FROM clip_accesses a WHERE a.clip_id = c.id) AS use_count
+            FROM clips c
+            ORDER BY {} {dir}, c.id {dir}
+            LIMIT ? OFFSET ?
+            "#,
+            prefixed_clip_columns("c"),
+            sort_by.expression(),
+            dir = direction
+        ))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clips")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(ClipTablePage {
+            rows: rows
+                .iter()
+                .map(|row| {
+                    Ok(ClipTableRow {
+                        clip: ClipPreview::new(&row_to_clip(row)?, self.config.preview_chars),
+                        use_count: row.get::<i64, _>("use_count") as u64,
+                    })
+                })
+                .collect::<Result<_>>()?,
+            total: total as u64,
+        })
+    }
+
+    /// A page of recent clips grouped by local day. `tz_offset_minutes` is
+    /// the client's current UTC offset (minutes east of UTC) and is applied
+    /// to every clip, so days across a DST change shift by the difference.
+    pub async fn get_recent_clips_grouped(
+        &self,
+        limit: i32,
+        tz_offset_minutes: i32,
+        cursor: Option<&ClipCursor>,
+    ) -> Result<GroupedClips> {
+        let rows = match cursor {
+            Some(cursor) => {
+                let created_at = cursor.created_at.to_rfc3339();
+                sqlx::query(&format!(
+                    r#"
+                    SELECT {} FROM clips
+                    WHERE created_at < ? OR (created_at = ? AND id < ?)
+                    ORDER BY created_at DESC, id DESC
+                    LIMIT ?
+                    "#,
+                    CLIP_COLUMNS
+                ))
+                .bind(&created_at)
+                .bind(&created_at)
+                .bind(&cursor.id)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?
+            }
+            None => {
+                sqlx::query(&format!(
+                    "SELECT {} FROM clips ORDER BY created_at DESC, id DESC LIMIT ?",
+                    CLIP_COLUMNS
+                ))
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?
+            }
+        };
+
+        let clips = self.rows_to_clips(rows).await?;
+        let next_cursor = match clips.last() {
+            Some(last) if clips.len() as i32 == limit => Some(ClipCursor {
+                created_at: last.created_at,
+                id: last.id.clone(),
+            }),
+            _ => None,
+        };
+
+        Ok(GroupedClips {
+            groups: group_by_local_day(&clips, Utc::now(), tz_offset_minutes, self.config.preview_chars),
+            next_cursor,
+        })
+    }
+
+    pub async fn get_embed_truncated_clips(&self, limit: i32) -> Result<Vec<ClipItem>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM clips WHERE embed_truncated = 1 ORDER BY created_at DESC LIMIT ?",
+            CLIP_COLUMNS
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.rows_to_clips(rows).await
+    }
+
+    /// Embedded clips whose vector didn't come from the current embedding
+    /// model (or whose model is unknown), newest first. `reembed_clip` or
+    /// `re_embed_all` brings them back into semantic search.
+    pub async fn get_clips_needing_reindex(&self, limit: i32) -> Result<Vec<ClipItem>> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {} FROM clips
+            WHERE embedding IS NOT NULL AND embed_model IS NOT ?
+            ORDER BY created_at DESC
+            LIMIT ?
+            "#,
+            CLIP_COLUMNS
+        ))
+        .bind(self.ollama.model())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.rows_to_clips(rows).await
+    }
+
+    /// Regenerates the embedding of every clip `get_clips_needing_reindex`
+    /// would list, `EMBED_BATCH_SIZE` clips per request to Ollama, then
+    /// their chunk embeddings. Returns the number of clips re-embedded.
+    pub async fn re_embed_all(&self, progress: impl Fn(TransferProgress)) -> Result<u64> {
+        let ids: Vec<String> = sqlx::query_scalar(
+            "SELECT id FROM clips WHERE embedding IS NOT NULL AND embed_model IS NOT ? ORDER BY created_at DESC",
+        )
+        .bind(self.ollama.model())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let embedded = self.embed_clips_in_batches(&ids, &progress).await?;
+        let chunked: Vec<String> = sqlx::query_scalar("SELECT DISTINCT clip_id FROM clip_chunks WHERE embed_model IS NOT ?")
+            .bind(self.ollama.model())
+            .fetch_all(&self.pool)
+            .await?;
+        for id in &chunked {
+            self.refresh_chunks(id).await?;
+        }
+        Ok(embedded)
+    }
+
+    /// Embeds clips stored without an embedding, e.g. captured while Ollama
+    /// was down, in batches like `re_embed_all`. Hash-only clips and clips
+    /// the oversized-embedding setting skipped are left alone. Returns the
+    /// number of clips embedded.
+    pub async fn backfill_embeddings(&self, progress: impl Fn(TransferProgress)) -> Result<u64> {
+        let ids: Vec<String> = sqlx::query_scalar(&format!(
+            "SELECT id FROM clips WHERE embedding IS NULL AND embed_truncated = 0 AND {} ORDER BY created_at DESC",
+            fts_indexed("")
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.embed_clips_in_batches(&ids, &progress).await
+    }
+
+    // Embeds `ids` with the current model, one Ollama request per
+    // EMBED_BATCH_SIZE clips, committing each batch as it completes
+    async fn embed_clips_in_batches(&self, ids: &[String], progress: &impl Fn(TransferProgress)) -> Result<u64> {
+        let total = ids.len() as u64;
+        let mut embedded = 0;
+        progress(TransferProgress::new("embeddings", 0, total));
+
+        for batch in ids.chunks(EMBED_BATCH_SIZE) {
+            let clips = self.get_clips_by_ids(batch).await?;
+            let inputs: Vec<(Option<&str>, bool)> =
+                clips.iter().map(|clip| self.embedding_input(&clip.content)).collect();
+            let texts: Vec<&str> = inputs.iter().filter_map(|(text, _)| *text).collect();
+            let mut embeddings = self.embed_texts(&texts).await?.into_iter();
+
+            let mut tx = self.pool.begin().await?;
+            for (clip, (text, truncated)) in clips.iter().zip(&inputs) {
+                let embedding = text.and_then(|_| embeddings.next());
+                sqlx::query("UPDATE clips SET embedding = ?, embed_truncated = ?, embed_model = ?, embed_dim = ? WHERE id = ?")
+                    .bind(embedding.as_deref().map(encode_embedding))
+                    .bind(*truncated)
+                    .bind(embedding.as_ref().map(|_| self.ollama.model()))
+                    .bind(embedding.as_ref().map(|embedding| embedding.len() as i64))
+                    .bind(&clip.id)
+                    .execute(&mut *tx)
+                    .await?;
+                if embedding.is_some() {
+                    embedded += 1;
+                }
+            }
+            tx.commit().await?;
+
+            progress(TransferProgress::new("embeddings", embedded, total));
+        }
+
+        Ok(embedded)
+    }
+
+    /// Regenerates a clip's embedding with the current model, and its chunk
+    /// embeddings if it has any.
+    pub async fn reembed_clip(&self, id: &str) -> Result<()> {
+        let mut clip = self
+            .get_clip(id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", id))?;
+
+        clip.embedding = None;
+        clip.embed_truncated = false;
+        let (embedding, embed_truncated) = self.clip_embedding(&clip).await?;
+
+        sqlx::query("UPDATE clips SET embedding = ?, embed_truncated = ?, embed_model = ?, embed_dim = ? WHERE id = ?")
+            .bind(embedding.as_deref().map(encode_embedding))
+            .bind(embed_truncated)
+            .bind(embedding.as_ref().map(|_| self.ollama.model()))
+            .bind(embedding.as_ref().map(|embedding| embedding.len() as i64))
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        let has_chunks: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM clip_chunks WHERE clip_id = ?)")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        if has_chunks {
+            self.refresh_chunks(id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the given clips, returned in the same order as `ids`.
+    pub async fn get_clips_by_ids(&self, ids: &[String]) -> Result<Vec<ClipItem>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let sql = format!("SELECT {} FROM clips WHERE id IN ({})", CLIP_COLUMNS, placeholders);
+
+        let mut query = sqlx::query(&sql);
+        for id in ids {
+            query = query.bind(id);
+        }
+
+        let rows = query.fetch_all(&self.pool).await?;
+        let mut clips = self.rows_to_clips(rows).await?;
+        clips.sort_by_key(|clip| ids.iter().position(|id| id == &clip.id));
+
+        Ok(clips)
+    }
+
+    /// Streams every exportable clip (i.e. not hash-only), newest first,
+    /// without materializing the whole table.
+    pub fn stream_clips(&self) -> impl Stream<Item = Result<ClipItem>> + '_ {
+        sqlx::query("SELECT * FROM clips WHERE content_type != 'redacted' ORDER BY created_at DESC")
+            .fetch(&self.pool)
+            .map(|row| row_to_clip(&row?))
+    }
+
+    /// Writes all clips to `path` as a JSON array, serializing one clip at a time.
+    pub async fn export_clips_json(&self, path: &Path) -> Result<usize> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        write_json_array(&mut writer, self.stream_clips()).await
+    }
+
+    /// Writes clip embeddings to `path` as a NumPy `.npy` file holding one
+    /// little-endian `f32` row per clip, plus `<name>_ids.json` next to it
+    /// listing the clip id of each row. Only the most common embedding size
+    /// is exported, since rows must be the same length; masked clips are
+    /// left out. Returns the array's shape and the ids file's path.
+    pub async fn export_embeddings_to_npy(&self, path: &Path) -> Result<(usize, usize, PathBuf)> {
+        let dim: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT embed_dim FROM clips
+            WHERE embedding IS NOT NULL AND masked = 0
+            GROUP BY embed_dim
+            ORDER BY COUNT(*) DESC, embed_dim DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let dim = dim.ok_or_else(|| anyhow!("No embeddings to export"))?;
+
+        let rows: Vec<(String, Vec<u8>)> = sqlx::query_as(
+            "SELECT id, embedding FROM clips WHERE embedding IS NOT NULL AND masked = 0 AND embed_dim = ? ORDER BY created_at",
+        )
+        .bind(dim)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writer.write_all(&npy_header(rows.len(), dim as usize))?;
+        for (_, embedding) in &rows {
+            // Stored the way .npy wants them: packed little-endian f32
+            writer.write_all(embedding)?;
+        }
+        writer.flush()?;
+
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("embeddings");
+        let ids_path = path.with_file_name(format!("{}_ids.json", stem));
+        let ids: Vec<&str> = rows.iter().map(|(id, _)| id.as_str()).collect();
+        std::fs::write(&ids_path, serde_json::to_vec(&ids)?)?;
+
+        Ok((rows.len(), dim as usize, ids_path))
+    }
+
+    /// Collects every clip (with versions), collection and the settings.
+    pub async fn full_export(&self, progress: impl Fn(TransferProgress)) -> Result<FullExport> {
+        let mut versions_by_clip: std::collections::HashMap<String, Vec<ClipVersion>> =
+            std::collections::HashMap::new();
+        let mut version_rows = sqlx::query(
+            "SELECT id, clip_id, content, summary, created_at FROM clip_versions ORDER BY created_at",
+        )
+        .fetch(&self.pool);
+        while let Some(row) = version_rows.try_next().await? {
+            let created_at: String = row.get("created_at");
+            let version = ClipVersion {
+                id: row.get("id"),
+                clip_id: row.get("clip_id"),
+                content: row.get("content"),
+                summary: row.get("summary"),
+                created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+            };
+            versions_by_clip.entry(version.clip_id.clone()).or_default().push(version);
+        }
+        drop(version_rows);
+
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clips WHERE content_type != 'redacted'")
+            .fetch_one(&self.pool)
+            .await?;
+        let total = total as u64;
+
+        let mut clips = Vec::with_capacity(total as usize);
+        let mut stream = self.stream_clips();
+        while let Some(clip) = stream.try_next().await? {
+            let versions = versions_by_clip.remove(&clip.id).unwrap_or_default();
+            clips.push(ClipItemWithVersions { clip, versions });
+            if clips.len() as u64 % TRANSFER_PROGRESS_INTERVAL == 0 {
+                progress(TransferProgress::new("clips", clips.len() as u64, total));
+            }
+        }
+        drop(stream);
+        progress(TransferProgress::new("clips", clips.len() as u64, total));
+
+        let mut collections = Vec::new();
+        for collection in self.list_collections().await? {
+            let clip_ids = self.get_collection_clip_ids(&collection.id).await?;
+            collections.push(CollectionExport { collection, clip_ids });
+        }
+
+        Ok(FullExport {
+            export_version: FULL_EXPORT_VERSION,
+            exported_at: Utc::now(),
+            clips,
+            collections,
+            settings: self.config.clone(),
+        })
+    }
+
+    pub async fn full_export_to(&self, path: &Path, progress: impl Fn(TransferProgress)) -> Result<usize> {
+        let export = self.full_export(progress).await?;
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer(&mut writer, &export)?;
+        writer.flush()?;
+
+        Ok(export.clips.len())
+    }
+
+    /// Imports a `full_export` file in one transaction. Clips are matched by
+    /// content hash so re-importing is harmless; a clip whose id is taken by
+    /// different content gets a new id. Collections always get new ids.
+    /// With `dedupe_after`, `merge_duplicates` then runs over the whole
+    /// history.
+    pub async fn full_import(
+        &mut self,
+        path: &Path,
+        import_settings: bool,
+        dedupe_after: bool,
+        progress: impl Fn(TransferProgress),
+    ) -> Result<FullImportReport> {
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let export: FullExport = serde_json::from_reader(reader)?;
+        if export.export_version > FULL_EXPORT_VERSION {
+            return Err(anyhow!(
+                "Export version {} is newer than this app supports ({})",
+                export.export_version,
+                FULL_EXPORT_VERSION
+            ));
+        }
+
+        let mut existing_by_hash = std::collections::HashMap::new();
+        let mut rows = sqlx::query("SELECT id, content FROM clips").fetch(&self.pool);
+        while let Some(row) = rows.try_next().await? {
+            let content: String = row.get("content");
+            existing_by_hash.insert(sha256_hex(&content), row.get::<String, _>("id"));
+        }
+        drop(rows);
+
+        let mut report = FullImportReport::default();
+        // Exported clip id -> id in this database
+        let mut id_map = std::collections::HashMap::new();
+        let total = export.clips.len() as u64;
+
+        let mut tx = self.pool.begin().await?;
+        for (index, ClipItemWithVersions { mut clip, versions }) in export.clips.into_iter().enumerate() {
+            let exported_id = clip.id.clone();
+            let hash = sha256_hex(&clip.content);
+
+            let clip_id = match existing_by_hash.get(&hash) {
+                Some(existing_id) => {
+                    report.duplicate_clips += 1;
+                    existing_id.clone()
+                }
+                None => {
+                    let id_taken: Option<i64> = sqlx::query_scalar("SELECT 1 FROM clips WHERE id = ?")
+                        .bind(&clip.id)
+                        .fetch_optional(&mut *tx)
+                        .await?;
+                    if id_taken.is_some() {
+                        clip.id = Uuid::new_v4().to_string();
+                    }
+
+                    insert_clip_row(&mut *tx, &clip, clip.embedding.as_deref(), clip.embed_truncated, None).await?;
+                    existing_by_hash.insert(hash, clip.id.clone());
+                    report.clips_imported += 1;
+                    clip.id
+                }
+            };
+
+            for version in versions {
+                report.versions_imported += sqlx::query(
+                    r#"
+                    INSERT OR IGNORE INTO clip_versions (id, clip_id, content, summary, created_at)
+                    VALUES (?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&version.id)
+                .bind(&clip_id)
+                .bind(&version.content)
+                .bind(&version.summary)
+                .bind(version.created_at.to_rfc3339())
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            }
+
+            id_map.insert(exported_id, clip_id);
+            let processed = index as u64 + 1;
+            if processed % TRANSFER_PROGRESS_INTERVAL == 0 {
+                progress(TransferProgress::new("clips", processed, total));
+            }
+        }
+        progress(TransferProgress::new("clips", total, total));
+
+        for CollectionExport { collection, clip_ids } in export.collections {
+            let collection_id = Uuid::new_v4().to_string();
+            sqlx::query("INSERT INTO collections (id, name, created_at) VALUES (?, ?, ?)")
+                .bind(&collection_id)
+                .bind(&collection.name)
+                .bind(collection.created_at.to_rfc3339())
+                .execute(&mut *tx)
+                .await?;
+
+            let now = Utc::now().to_rfc3339();
+            for clip_id in clip_ids.iter().filter_map(|id| id_map.get(id)) {
+                sqlx::query(
+                    "INSERT OR IGNORE INTO clip_collections (clip_id, collection_id, added_at) VALUES (?, ?, ?)",
+                )
+                .bind(clip_id)
+                .bind(&collection_id)
+                .bind(&now)
+                .execute(&mut *tx)
+                .await?;
+            }
+            report.collections_imported += 1;
+        }
+
+        tx.commit().await?;
+
+        if import_settings {
+            self.set_config(export.settings).await?;
+            report.settings_imported = true;
+        }
+
+        if dedupe_after {
+            report.duplicates_merged = self.merge_duplicates().await?;
+        }
+
+        Ok(report)
+    }
+
+    /// Collapses clips with identical content into the earliest copy, which
+    /// keeps its timestamp and gains the others' versions, shares, accesses,
+    /// slots, pins, reminders, auto-filings and collection memberships. Each
+    /// removed copy gets a `delete` audit entry. Tags are reconciled per
+    /// `duplicate_tag_policy`, and a missing source is taken from the first
+    /// duplicate that has one. Returns how many clips were removed.
+    pub async fn merge_duplicates(&self) -> Result<u64> {
+        // Content hash -> (kept clip id, its merged tags, its merged source)
+        let mut keepers: std::collections::HashMap<String, (String, Vec<String>, Option<String>)> =
+            std::collections::HashMap::new();
+        // (duplicate id, kept id)
+        let mut duplicates: Vec<(String, String)> = Vec::new();
+
+        let mut rows = sqlx::query("SELECT id, content, tags, source, content_hash FROM clips ORDER BY created_at, id")
+            .fetch(&self.pool);
+        while let Some(row) = rows.try_next().await? {
+            let id: String = row.get("id");
+            let tags: Vec<String> = serde_json::from_str(&row.get::<String, _>("tags")).unwrap_or_default();
+            let source: Option<String> = row.get("source");
+            // Hash-only rows only merge with each other, never into a full clip
+            let key = match row.get::<Option<String>, _>("content_hash") {
+                Some(hash) => format!("redacted:{}", hash),
+                None => sha256_hex(&row.get::<String, _>("content")),
+            };
+            match keepers.entry(key) {
+                std::collections::hash_map::Entry::Occupied(mut entry) => {
+                    self.absorb_duplicate(entry.get_mut(), tags, source);
+                    duplicates.push((id, entry.get().0.clone()));
+                }
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert((id, tags, source));
+                }
+            }
+        }
+        drop(rows);
+
+        self.fold_duplicates(&duplicates, keepers.values()).await
+    }
+
+    /// Collapses groups of near-identical clips (embedding cosine similarity
+    /// of at least `threshold` among the clips `find_near_duplicates`
+    /// compares) into the earliest clip of each group, the way
+    /// `merge_duplicates` does for identical content. Masked clips are left
+    /// alone. Returns how many clips were removed.
+    pub async fn deduplicate_similar(&self, threshold: f32) -> Result<u64> {
+        let pairs = self.find_near_duplicates(threshold).await?;
+        if pairs.is_empty() {
+            return Ok(0);
+        }
+
+        // Chains of similar clips form one group, joined under a common root
+        let mut parents: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+        for (a, b, _) in &pairs {
+            let root_a = group_root(&mut parents, a);
+            let root_b = group_root(&mut parents, b);
+            if root_a != root_b {
+                parents.insert(root_a, root_b);
+            }
+        }
+
+        let ids: Vec<String> = parents.keys().cloned().collect();
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let sql = format!(
+            "SELECT id, tags, source FROM clips WHERE masked = 0 AND id IN ({}) ORDER BY created_at, id",
+            placeholders
+        );
+        let mut query = sqlx::query(&sql);
+        for id in &ids {
+            query = query.bind(id);
+        }
+        let rows = query.fetch_all(&self.pool).await?;
+
+        // Group root -> (kept clip id, its merged tags, its merged source)
+        let mut keepers: std::collections::HashMap<String, (String, Vec<String>, Option<String>)> =
+            std::collections::HashMap::new();
+        let mut duplicates: Vec<(String, String)> = Vec::new();
+        for row in &rows {
+            let id: String = row.get("id");
+            let tags: Vec<String> = serde_json::from_str(&row.get::<String, _>("tags")).unwrap_or_default();
+            let source: Option<String> = row.get("source");
+            match keepers.entry(group_root(&mut parents, &id)) {
+                std::collections::hash_map::Entry::Occupied(mut entry) => {
+                    self.absorb_duplicate(entry.get_mut(), tags, source);
+                    duplicates.push((id, entry.get().0.clone()));
+                }
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert((id, tags, source));
+                }
+            }
+        }
+
+        self.fold_duplicates(&duplicates, keepers.values()).await
+    }
+
+    // Reconciles a duplicate's tags and source into its keeper's. Duplicates
+    // arrive oldest first.
+    fn absorb_duplicate(&self, keeper: &mut (String, Vec<String>, Option<String>), tags: Vec<String>, source: Option<String>) {
+        let (_, keeper_tags, keeper_source) = keeper;
+        match self.config.duplicate_tag_policy {
+            DuplicateTagPolicy::Union => {
+                for tag in tags {
+                    if !keeper_tags.contains(&tag) {
+                        keeper_tags.push(tag);
+                    }
+                }
+            }
+            // Rows come oldest first, so the last one seen wins
+            DuplicateTagPolicy::LastWins => *keeper_tags = tags,
+        }
+        if keeper_source.is_none() {
+            *keeper_source = source;
+        }
+    }
+
+    // Moves each duplicate's versions, shares, accesses and collection
+    // memberships to its keeper and deletes it, then stores the keepers'
+    // reconciled tags and source. `duplicates` holds (duplicate id, kept id).
+    async fn fold_duplicates<'a>(
+        &self,
+        duplicates: &[(String, String)],
+        keepers: impl Iterator<Item = &'a (String, Vec<String>, Option<String>)>,
+    ) -> Result<u64> {
+        if duplicates.is_empty() {
+            return Ok(0);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for (duplicate_id, keeper_id) in duplicates {
+            for table in ["clip_versions", "clip_shares", "clip_accesses", "slots", "reminders"] {
+                sqlx::query(&format!("UPDATE {} SET clip_id = ? WHERE clip_id = ?", table))
+                    .bind(keeper_id)
+                    .bind(duplicate_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            // A keeper that is already pinned or filed there keeps its own
+            // row; the duplicate's goes with it below
+            for table in ["global_pins", "auto_filings"] {
+                sqlx::query(&format!("UPDATE OR IGNORE {} SET clip_id = ? WHERE clip_id = ?", table))
+                    .bind(keeper_id)
+                    .bind(duplicate_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO clip_collections (clip_id, collection_id, added_at)
+                SELECT ?, collection_id, added_at FROM clip_collections WHERE clip_id = ?
+                "#,
+            )
+            .bind(keeper_id)
+            .bind(duplicate_id)
+            .execute(&mut *tx)
+            .await?;
+
+            for sql in [
+                "DELETE FROM clip_collections WHERE clip_id = ?",
+                "DELETE FROM entities WHERE clip_id = ?",
+                "DELETE FROM clips WHERE id = ?",
+            ] {
+                sqlx::query(sql).bind(duplicate_id).execute(&mut *tx).await?;
+            }
+            record_audit(
+                &mut *tx,
+                "delete",
+                "clip",
+                duplicate_id,
+                Some(serde_json::json!({ "merged_into": keeper_id })),
+            )
+            .await?;
+        }
+
+        let mut merged_keepers: Vec<&String> = duplicates.iter().map(|(_, keeper_id)| keeper_id).collect();
+        merged_keepers.sort_unstable();
+        merged_keepers.dedup();
+        for (keeper_id, tags, source) in keepers.filter(|(id, _, _)| merged_keepers.contains(&id)) {
+            sqlx::query("UPDATE clips SET tags = ?, source = ? WHERE id = ?")
+                .bind(serde_json::to_string(tags)?)
+                .bind(source)
+                .bind(keeper_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(duplicates.len() as u64)
+    }
+
+    /// Serializes only the clips matching `filter` as a JSON array.
+    pub async fn export_filtered(&self, filter: &ExportFilter) -> Result<String> {
+        let (where_clause, binds) = filter.where_clause();
+        let sql = format!(
+            "SELECT {} FROM clips WHERE {} ORDER BY created_at DESC",
+            CLIP_COLUMNS, where_clause
+        );
+
+        let mut query = sqlx::query(&sql);
+        for value in &binds {
+            query = query.bind(value);
+        }
+
+        let mut rows = query.fetch(&self.pool);
+        let mut clips = Vec::new();
+        while let Some(row) = rows.try_next().await? {
+            clips.push(row_to_clip(&row)?);
+        }
+
+        Ok(serde_json::to_string(&clips)?)
+    }
+
+    pub async fn semantic_search(&self, query_embedding: &[f32], limit: i32) -> Result<Vec<ClipItem>> {
+        Ok(self
+            .semantic_search_matches(query_embedding, limit)
+            .await?
+            .into_iter()
+            .map(|hit| hit.clip)
+            .collect())
+    }
+
+    /// Semantic search with a caller-supplied vector, e.g. from an external
+    /// model. The vector must have the dimension of the stored embeddings.
+    pub async fn semantic_search_vector(&self, vector: &[f32], limit: i32) -> Result<Vec<ClipItem>> {
+        if vector.is_empty() {
+            return Err(anyhow!("Query vector is empty"));
+        }
+
+        let dimension = self
+            .embedding_dimension()
+            .await?
+            .ok_or_else(|| anyhow!("No clips have embeddings to search"))?;
+        if vector.len() != dimension {
+            return Err(anyhow!(
+                "Query vector has {} dimensions but stored embeddings have {}",
+                vector.len(),
+                dimension
+            ));
+        }
+
+        self.semantic_search(vector, limit).await
+    }
+
+    /// Dimension shared by most stored embeddings, or None if there are none.
+    pub async fn embedding_dimension(&self) -> Result<Option<usize>> {
+        let bytes: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT LENGTH(embedding) AS bytes FROM clips
+            WHERE embedding IS NOT NULL
+            GROUP BY bytes
+            ORDER BY COUNT(*) DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(bytes.map(|bytes| bytes as usize / std::mem::size_of::<f32>()))
+    }
+
+    /// Like `semantic_search`, also reporting each clip's score and, for
+    /// chunked clips, the chunk that matched best.
+    pub async fn semantic_search_matches(&self, query_embedding: &[f32], limit: i32) -> Result<Vec<SemanticMatch>> {
+        let scored: Vec<(f32, String, Option<ChunkSpan>)> = self
+            .score_clips_with_chunks(query_embedding)
+            .await?
+            .into_iter()
+            .take(limit as usize)
+            .collect();
+
+        let ids: Vec<String> = scored.iter().map(|(_, id, _)| id.clone()).collect();
+        let mut clips: std::collections::HashMap<String, ClipItem> = self
+            .get_clips_by_ids(&ids)
+            .await?
+            .into_iter()
+            .map(|clip| (clip.id.clone(), clip))
+            .collect();
+
+        let matches: Vec<SemanticMatch> = scored
+            .into_iter()
+            .filter_map(|(score, id, chunk)| clips.remove(&id).map(|clip| SemanticMatch { clip, score, chunk }))
+            .collect();
+
+        Ok(matches)
+    }
+
+    /// Answers `question` from the most relevant clips, citing them. Masked
+    /// clips are never sent to the model, and `sources` only ever holds
+    /// clips that were retrieved and actually cited.
+    pub async fn ask_clips(&self, question: &str, min_similarity: f32) -> Result<CitedAnswer> {
+        let query_embedding = self.embed_text(question).await?;
+        let retrieved: Vec<(SemanticMatch, usize, usize)> = self
+            .semantic_search_matches(&query_embedding, ASK_SOURCE_LIMIT * 2)
+            .await?
+            .into_iter()
+            .filter(|hit| !hit.clip.masked && hit.score >= min_similarity)
+            .take(ASK_SOURCE_LIMIT as usize)
+            .map(|hit| {
+                let (start, end) = match hit.chunk {
+                    Some(chunk) => (chunk.start, chunk.end),
+                    None => (0, hit.clip.content.chars().count().min(ASK_SOURCE_CHARS)),
+                };
+                (hit, start, end)
+            })
+            .collect();
+
+        if retrieved.is_empty() {
+            return Err(anyhow!("No clips are relevant to this question"));
+        }
+
+        let texts: Vec<String> = retrieved
+            .iter()
+            .map(|(hit, start, end)| hit.clip.content.chars().skip(*start).take(end - start).collect())
+            .collect();
+        let text_refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+
+        let response = self.ollama.answer_with_citations(question, &text_refs).await?;
+        let (answer, cited) = renumber_citations(&response, retrieved.len());
+
+        let sources = cited
+            .into_iter()
+            .map(|index| {
+                let (hit, start, end) = &retrieved[index];
+                CitedSource {
+                    clip_id: hit.clip.id.clone(),
+                    summary: hit.clip.summary.clone(),
+                    score: hit.score,
+                    start: *start,
+                    end: *end,
+                    chunk_index: hit.chunk.map(|chunk| chunk.chunk_index),
+                }
+            })
+            .collect();
+
+        Ok(CitedAnswer { answer, sources })
+    }
+
+    /// Tags that similar clips carry but this one doesn't, best first.
+    /// Without an embedding the capture-time tag rules are re-run instead.
+    pub async fn suggest_tags(&self, id: &str, limit: usize) -> Result<Vec<TagSuggestion>> {
+        let clip = self
+            .get_clip(id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", id))?;
+        let is_new = |tag: &str| !clip.tags.iter().any(|existing| existing == tag) && !tag.starts_with("split-from:");
+
+        let Some(embedding) = clip.embedding.as_deref() else {
+            return Ok(detect_tags(&clip.content, &clip.urls)
+                .into_iter()
+                .filter(|tag| is_new(tag))
+                .take(limit)
+                .map(|tag| TagSuggestion { tag, score: 1.0 })
+                .collect());
+        };
+
+        let neighbors: Vec<(f32, String)> = self
+            .score_clips(embedding)
+            .await?
+            .into_iter()
+            .filter(|(score, neighbor_id)| *score > 0.0 && neighbor_id != id)
+            .take(TAG_SUGGESTION_NEIGHBORS)
+            .collect();
+        let neighbor_ids: Vec<String> = neighbors.iter().map(|(_, id)| id.clone()).collect();
+        let neighbor_clips = self.get_clips_by_ids(&neighbor_ids).await?;
+
+        let mut scores: std::collections::HashMap<String, f32> = std::collections::HashMap::new();
+        for neighbor in &neighbor_clips {
+            let weight = neighbors
+                .iter()
+                .find(|(_, id)| *id == neighbor.id)
+                .map_or(0.0, |(score, _)| *score);
+            for tag in neighbor.tags.iter().filter(|tag| is_new(tag)) {
+                *scores.entry(tag.clone()).or_default() += weight;
+            }
+        }
+
+        let mut suggestions: Vec<TagSuggestion> = scores
+            .into_iter()
+            .map(|(tag, score)| TagSuggestion { tag, score })
+            .collect();
+        suggestions.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.tag.cmp(&b.tag))
+        });
+        suggestions.truncate(limit);
+
+        Ok(suggestions)
+    }
+
+    /// Stored clips semantically similar to `text`, excluding clips whose
+    /// content is exactly `text`. Reuses a stored embedding for identical
+    /// content before asking Ollama for a new one.
+    pub async fn find_related_to_text(&self, text: &str, limit: usize, min_similarity: f32) -> Result<Vec<ClipItem>> {
+        let cached: Option<Vec<u8>> = sqlx::query_scalar(
+            "SELECT embedding FROM clips WHERE content = ? AND embedding IS NOT NULL ORDER BY last_modified DESC LIMIT 1",
+        )
+        .bind(text)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let query_embedding = match cached {
+            Some(bytes) => decode_embedding(&bytes),
+            None => {
+                let end = text
+                    .char_indices()
+                    .nth(self.config.embedding_max_chars)
+                    .map_or(text.len(), |(cutoff, _)| cutoff);
+                self.embed_text(&text[..end]).await?
+            }
+        };
+
+        let exact_ids: Vec<String> = sqlx::query_scalar("SELECT id FROM clips WHERE content = ?")
+            .bind(text)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let ids: Vec<String> = self
+            .score_clips(&query_embedding)
+            .await?
+            .into_iter()
+            .filter(|(score, id)| *score >= min_similarity && !exact_ids.contains(id))
+            .take(limit)
+            .map(|(_, id)| id)
+            .collect();
+
+        self.get_clips_by_ids(&ids).await
+    }
+
+    /// Full-text search limited to clips in any of `collection_ids`.
+    pub async fn search_in_collections(&self, query: &str, collection_ids: &[&str], limit: i32) -> Result<Vec<ClipItem>> {
+        if collection_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; collection_ids.len()].join(", ");
+        let sql = format!(
+            r#"
+            SELECT {}
+            FROM clips c
+            JOIN clips_fts fts ON c.id = fts.id
+            WHERE clips_fts MATCH ?
+              AND c.id IN (SELECT clip_id FROM clip_collections WHERE collection_id IN ({}))
+            ORDER BY rank
+            LIMIT ?
+            "#,
+            prefixed_clip_columns("c"),
+            placeholders
+        );
+
+        let mut query = sqlx::query(&sql).bind(query);
+        for id in collection_ids {
+            query = query.bind(*id);
+        }
+        let rows = query.bind(limit).fetch_all(&self.pool).await?;
+
+        self.rows_to_clips(rows).await
+    }
+
+    /// Semantic search limited to clips in any of `collection_ids` scoring
+    /// at least `min_similarity`.
+    pub async fn semantic_search_in_collections(
+        &self,
+        query_embedding: &[f32],
+        collection_ids: &[&str],
+        min_similarity: f32,
+        limit: i32,
+    ) -> Result<Vec<ClipItem>> {
+        if collection_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; collection_ids.len()].join(", ");
+        let sql = format!(
+            "SELECT DISTINCT clip_id FROM clip_collections WHERE collection_id IN ({})",
+            placeholders
+        );
+        let mut query = sqlx::query_scalar::<_, String>(&sql);
+        for id in collection_ids {
+            query = query.bind(*id);
+        }
+        let members: std::collections::HashSet<String> = query.fetch_all(&self.pool).await?.into_iter().collect();
+
+        let ids: Vec<String> = self
+            .score_clips(query_embedding)
+            .await?
+            .into_iter()
+            .filter(|(score, id)| *score >= min_similarity && members.contains(id))
+            .take(limit as usize)
+            .map(|(_, id)| id)
+            .collect();
+
+        self.get_clips_by_ids(&ids).await
+    }
+
+    /// Runs text, semantic and hybrid search for `query` side by side and
+    /// reports where their results agree. Debug builds only.
+    #[cfg(debug_assertions)]
+    pub async fn compare_search_methods(&self, query: &str, limit: i32) -> Result<SearchComparison> {
+        let query_embedding = self.embed_text(query).await?;
+        let (text, semantic, hybrid) = tokio::join!(
+            self.text_search(query, limit, 0),
+            self.semantic_search(&query_embedding, limit),
+            self.search_clips(query, limit),
+        );
+        let (text_results, semantic_results, hybrid_results) = (text?.items, semantic?, hybrid?);
+
+        let ids = |clips: &[ClipItem]| clips.iter().map(|clip| clip.id.clone()).collect::<Vec<_>>();
+        let (text_ids, semantic_ids, hybrid_ids) = (ids(&text_results), ids(&semantic_results), ids(&hybrid_results));
+
+        Ok(SearchComparison {
+            overlap_text_semantic: text_ids.iter().filter(|id| semantic_ids.contains(id)).cloned().collect(),
+            exclusive_to_text: text_ids.iter().filter(|id| !semantic_ids.contains(id)).cloned().collect(),
+            exclusive_to_semantic: semantic_ids.iter().filter(|id| !text_ids.contains(id)).cloned().collect(),
+            overlap_all_three: text_ids
+                .iter()
+                .filter(|id| semantic_ids.contains(id) && hybrid_ids.contains(id))
+                .cloned()
+                .collect(),
+            text_results,
+            semantic_results,
+            hybrid_results,
+        })
+    }
+
+    pub async fn text_search_with_total(&self, query: &str, limit: i32) -> Result<SearchResults> {
+        let page = self.text_search(query, limit, 0).await?;
+        Ok(SearchResults { results: page.items, total: page.total_count, diagnostics: None })
+    }
+
+    /// Semantic search reporting how many candidates scored at least `min_similarity`.
+    /// The total requires scoring every candidate, not just the returned page.
+    pub async fn semantic_search_with_total(
+        &self,
+        query_embedding: &[f32],
+        limit: i32,
+        min_similarity: f32,
+    ) -> Result<SearchResults> {
+        let matching: Vec<String> = self
+            .score_clips(query_embedding)
+            .await?
+            .into_iter()
+            .filter(|(similarity, _)| *similarity >= min_similarity)
+            .map(|(_, id)| id)
+            .collect();
+
+        let total = matching.len() as u64;
+        let page: Vec<String> = matching.into_iter().take(limit as usize).collect();
+        let results = self.get_clips_by_ids(&page).await?;
+
+        Ok(SearchResults { results, total, diagnostics: None })
+    }
+
+    pub fn ollama(&self) -> &OllamaClient {
+        &self.ollama
+    }
+
+    /// Pairs of clips whose embeddings have cosine similarity of at least
+    /// `threshold`, most similar first. Only the most recent
+    /// `NEAR_DUPLICATE_CANDIDATE_LIMIT` embedded clips are compared, keeping the
+    /// pairwise scan bounded.
+    pub async fn find_near_duplicates(&self, threshold: f32) -> Result<Vec<(String, String, f32)>> {
+        let mut rows = sqlx::query(
+            "SELECT id, embedding FROM clips WHERE embedding IS NOT NULL ORDER BY created_at DESC LIMIT ?",
+        )
+        .bind(NEAR_DUPLICATE_CANDIDATE_LIMIT)
+        .fetch(&self.pool);
+
+        let mut candidates: Vec<(String, Vec<f32>)> = Vec::new();
+        while let Some(row) = rows.try_next().await? {
+            let embedding_bytes: Vec<u8> = row.get("embedding");
+            candidates.push((row.get("id"), decode_embedding(&embedding_bytes)));
+        }
+
+        let mut pairs = Vec::new();
+        for (i, (id_a, embedding_a)) in candidates.iter().enumerate() {
+            for (id_b, embedding_b) in &candidates[i + 1..] {
+                let similarity = cosine_similarity(embedding_a, embedding_b);
+                if similarity >= threshold {
+                    pairs.push((id_a.clone(), id_b.clone(), similarity));
+                }
+            }
+        }
+
+        pairs.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+
+        Ok(pairs)
+    }
+
+    /// Pairwise cosine similarity between the given clips, in the order given.
+    /// Fails if more than `SIMILARITY_MATRIX_LIMIT` ids are requested or any
+    /// clip is missing or has no embedding.
+    pub async fn get_embedding_similarity_matrix(&self, clip_ids: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if clip_ids.len() > SIMILARITY_MATRIX_LIMIT {
+            return Err(anyhow!(
+                "Too many clips for a similarity matrix ({} > {})",
+                clip_ids.len(),
+                SIMILARITY_MATRIX_LIMIT
+            ));
+        }
+        if clip_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; clip_ids.len()].join(", ");
+        let sql = format!("SELECT id, embedding FROM clips WHERE id IN ({})", placeholders);
+        let mut query = sqlx::query(&sql);
+        for id in clip_ids {
+            query = query.bind(*id);
+        }
+
+        let mut embeddings: std::collections::HashMap<String, Option<Vec<u8>>> = query
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| (row.get("id"), row.get("embedding")))
+            .collect();
+
+        let vectors = clip_ids
+            .iter()
+            .map(|id| match embeddings.remove(*id) {
+                Some(Some(bytes)) => Ok(decode_embedding(&bytes)),
+                Some(None) => Err(anyhow!("Clip has no embedding: {}", id)),
+                None => Err(anyhow!("Clip not found: {}", id)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let n = vectors.len();
+        let mut matrix = vec![vec![0.0f32; n]; n];
+        for (i, vector_i) in vectors.iter().enumerate() {
+            matrix[i][i] = 1.0;
+            for (j, vector_j) in vectors.iter().enumerate().skip(i + 1) {
+                let similarity = cosine_similarity(vector_i, vector_j);
+                matrix[i][j] = similarity;
+                matrix[j][i] = similarity;
+            }
+        }
+
+        Ok(matrix)
+    }
+
+    pub async fn create_collection(&self, name: &str) -> Result<Collection> {
+        let collection = Collection {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            created_at: Utc::now(),
+            auto_file: false,
+            description: None,
+            last_described_at: None,
+        };
+
+        sqlx::query("INSERT INTO collections (id, name, created_at) VALUES (?, ?, ?)")
+            .bind(&collection.id)
+            .bind(&collection.name)
+            .bind(collection.created_at.to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(collection)
+    }
+
+    pub async fn list_collections(&self) -> Result<Vec<Collection>> {
+        let rows = sqlx::query("SELECT id, name, created_at, auto_file, description, last_described_at FROM collections ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(row_to_collection).collect()
+    }
+
+    pub async fn add_clips_to_collection(&self, collection_id: &str, clip_ids: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let added_at = Utc::now().to_rfc3339();
+
+        for clip_id in clip_ids {
+            sqlx::query("INSERT OR IGNORE INTO clip_collections (clip_id, collection_id, added_at) VALUES (?, ?, ?)")
+                .bind(clip_id)
+                .bind(collection_id)
+                .bind(&added_at)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Files `clip_ids` under `collection_id`, taking them out of every other
+    /// collection first unless `keep_existing` is set. All or nothing.
+    pub async fn move_clips_to_collection(&self, clip_ids: &[String], collection_id: &str, keep_existing: bool) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let exists: Option<String> = sqlx::query_scalar("SELECT id FROM collections WHERE id = ?")
+            .bind(collection_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if exists.is_none() {
+            return Err(anyhow!("Collection not found: {}", collection_id));
+        }
+
+        let added_at = Utc::now().to_rfc3339();
+        for clip_id in clip_ids {
+            if !keep_existing {
+                sqlx::query("DELETE FROM clip_collections WHERE clip_id = ? AND collection_id != ?")
+                    .bind(clip_id)
+                    .bind(collection_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            sqlx::query("INSERT OR IGNORE INTO clip_collections (clip_id, collection_id, added_at) VALUES (?, ?, ?)")
+                .bind(clip_id)
+                .bind(collection_id)
+                .bind(&added_at)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Asks the model to describe the collection from its most recent clips'
+    /// summaries and stores the result.
+    pub async fn describe_collection(&self, collection_id: &str) -> Result<String> {
+        let exists: Option<String> = sqlx::query_scalar("SELECT id FROM collections WHERE id = ?")
+            .bind(collection_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if exists.is_none() {
+            return Err(anyhow!("Collection not found: {}", collection_id));
+        }
+
+        let summaries: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT c.summary FROM clips c
+            JOIN clip_collections cc ON cc.clip_id = c.id
+            WHERE cc.collection_id = ? AND c.masked = 0
+            ORDER BY cc.added_at DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(collection_id)
+        .bind(COLLECTION_DESCRIPTION_CLIPS)
+        .fetch_all(&self.pool)
+        .await?;
+        if summaries.is_empty() {
+            return Err(anyhow!("Collection has no clips to describe"));
+        }
+
+        let summaries: Vec<&str> = summaries.iter().map(String::as_str).collect();
+        let description = self.ollama.summarize_collection(&summaries).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE collections
+            SET description = ?, last_described_at = ?,
+                described_clip_count = (SELECT COUNT(*) FROM clip_collections WHERE collection_id = ?)
+            WHERE id = ?
+            "#,
+        )
+        .bind(&description)
+        .bind(Utc::now().to_rfc3339())
+        .bind(collection_id)
+        .bind(collection_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(description)
+    }
+
+    /// Described collections that gained or lost more than
+    /// `DESCRIPTION_STALE_CLIPS` clips since their description was written.
+    pub async fn collections_needing_description(&self) -> Result<Vec<String>> {
+        Ok(sqlx::query_scalar(
+            r#"
+            SELECT col.id FROM collections col
+            WHERE col.description IS NOT NULL
+              AND ABS((SELECT COUNT(*) FROM clip_collections cc WHERE cc.collection_id = col.id)
+                      - COALESCE(col.described_clip_count, 0)) > ?
+            "#,
+        )
+        .bind(DESCRIPTION_STALE_CLIPS)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Opts a collection in or out of auto-filing.
+    pub async fn set_collection_auto_file(&self, collection_id: &str, enabled: bool) -> Result<()> {
+        let result = sqlx::query("UPDATE collections SET auto_file = ? WHERE id = ?")
+            .bind(enabled)
+            .bind(collection_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow!("Collection not found: {}", collection_id));
+        }
+
+        Ok(())
+    }
+
+    /// Asks the model which opted-in collection `clip_id` belongs in and
+    /// files it there, tagged `auto-filed`, when the model is confident
+    /// enough. Does nothing when auto-filing is off, no collection has opted
+    /// in, or the clip is masked.
+    pub async fn auto_file_clip(&self, clip_id: &str) -> Result<Option<AutoFiling>> {
+        if !self.config.auto_file_enabled {
+            return Ok(None);
+        }
+
+        let candidates: Vec<(String, String)> =
+            sqlx::query_as("SELECT id, name FROM collections WHERE auto_file = 1 ORDER BY name")
+                .fetch_all(&self.pool)
+                .await?;
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+
+        let clip = self
+            .get_clip(clip_id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", clip_id))?;
+        if clip.masked {
+            return Ok(None);
+        }
+
+        let names: Vec<&str> = candidates.iter().map(|(_, name)| name.as_str()).collect();
+        let guess = self.ollama.classify_collection(&clip.summary, &names).await?;
+        if guess.confidence < self.config.auto_file_min_confidence {
+            return Ok(None);
+        }
+        let Some((collection_id, collection_name)) = guess.collection.and_then(|picked| {
+            candidates
+                .iter()
+                .find(|(_, name)| name.trim().eq_ignore_ascii_case(picked.trim()))
+                .cloned()
+        }) else {
+            return Ok(None);
+        };
+
+        let filed_at = Utc::now();
+        let mut tags = clip.tags.clone();
+        if !tags.iter().any(|tag| tag == AUTO_FILED_TAG) {
+            tags.push(AUTO_FILED_TAG.to_string());
+        }
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT OR IGNORE INTO clip_collections (clip_id, collection_id, added_at) VALUES (?, ?, ?)")
+            .bind(clip_id)
+            .bind(&collection_id)
+            .bind(filed_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("INSERT OR REPLACE INTO auto_filings (clip_id, collection_id, confidence, filed_at) VALUES (?, ?, ?, ?)")
+            .bind(clip_id)
+            .bind(&collection_id)
+            .bind(guess.confidence)
+            .bind(filed_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE clips SET tags = ? WHERE id = ?")
+            .bind(serde_json::to_string(&tags)?)
+            .bind(clip_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(Some(AutoFiling {
+            clip_id: clip.id,
+            summary: clip.summary,
+            collection_id,
+            collection_name,
+            confidence: guess.confidence,
+            filed_at,
+        }))
+    }
+
+    /// Most recent auto-filings, newest first, for review.
+    pub async fn get_auto_filings(&self, limit: u32) -> Result<Vec<AutoFiling>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT f.clip_id, c.summary, f.collection_id, col.name, f.confidence, f.filed_at
+            FROM auto_filings f
+            JOIN clips c ON c.id = f.clip_id
+            JOIN collections col ON col.id = f.collection_id
+            ORDER BY f.filed_at DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let filed_at: String = row.get("filed_at");
+                Ok(AutoFiling {
+                    clip_id: row.get("clip_id"),
+                    summary: row.get("summary"),
+                    collection_id: row.get("collection_id"),
+                    collection_name: row.get("name"),
+                    confidence: row.get("confidence"),
+                    filed_at: DateTime::parse_from_rfc3339(&filed_at)?.with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    /// Reverts every auto-filing of `clip_id` and logs each as a correction.
+    /// With `correct_collection_id` the clip is filed there instead.
+    pub async fn undo_auto_file(&self, clip_id: &str, correct_collection_id: Option<&str>) -> Result<()> {
+        let clip = self
+            .get_clip(clip_id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", clip_id))?;
+
+        let mut tx = self.pool.begin().await?;
+        let filed: Vec<String> = sqlx::query_scalar("SELECT collection_id FROM auto_filings WHERE clip_id = ?")
+            .bind(clip_id)
+            .fetch_all(&mut *tx)
+            .await?;
+        if filed.is_empty() {
+            return Err(anyhow!("Clip was not auto-filed: {}", clip_id));
+        }
+
+        let now = Utc::now().to_rfc3339();
+        for collection_id in &filed {
+            sqlx::query("DELETE FROM clip_collections WHERE clip_id = ? AND collection_id = ?")
+                .bind(clip_id)
+                .bind(collection_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(
+                r#"
+                INSERT INTO auto_file_corrections (clip_id, summary, wrong_collection_id, correct_collection_id, corrected_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(clip_id)
+            .bind(&clip.summary)
+            .bind(collection_id)
+            .bind(correct_collection_id)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query("DELETE FROM auto_filings WHERE clip_id = ?")
+            .bind(clip_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if let Some(collection_id) = correct_collection_id {
+            sqlx::query("INSERT OR IGNORE INTO clip_collections (clip_id, collection_id, added_at) VALUES (?, ?, ?)")
+                .bind(clip_id)
+                .bind(collection_id)
+                .bind(&now)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let tags: Vec<&String> = clip.tags.iter().filter(|tag| *tag != AUTO_FILED_TAG).collect();
+        sqlx::query("UPDATE clips SET tags = ? WHERE id = ?")
+            .bind(serde_json::to_string(&tags)?)
+            .bind(clip_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Replaces each of `sources` with `target` on every clip, dropping the
+    /// repeat when a clip already has `target`, and repoints retention rules
+    /// and tag collection rules at `target`. Runs in one transaction and
+    /// returns the number of clips changed.
+    pub async fn merge_tags(&self, sources: &[&str], target: &str) -> Result<u64> {
+        let target = target.trim();
+        if target.is_empty() {
+            return Err(anyhow!("Tag must not be empty"));
+        }
+        let sources: Vec<&str> = sources.iter().map(|tag| tag.trim()).filter(|tag| *tag != target).collect();
+        if sources.is_empty() {
+            return Ok(0);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let sql = format!(
+            "SELECT COUNT(*) FROM clips WHERE EXISTS (SELECT 1 FROM json_each(clips.tags) WHERE json_each.value IN ({}))",
+            vec!["?"; sources.len()].join(", ")
+        );
+        let mut query = sqlx::query_scalar::<_, i64>(&sql);
+        for source in &sources {
+            query = query.bind(*source);
+        }
+        let clips_changed = query.fetch_one(&mut *tx).await?;
+
+        for source in &sources {
+            // Clips that already have `target` just lose the source...
+            sqlx::query(
+                r#"
+                UPDATE clips
+                SET tags = json_remove(tags, (SELECT fullkey FROM json_each(clips.tags) WHERE value = ?1 LIMIT 1))
+                WHERE EXISTS (SELECT 1 FROM json_each(clips.tags) WHERE value = ?1)
+                  AND EXISTS (SELECT 1 FROM json_each(clips.tags) WHERE value = ?2)
+                "#,
+            )
+            .bind(source)
+            .bind(target)
+            .execute(&mut *tx)
+            .await?;
+            // ...the rest get `target` in its place, keeping the tag order
+            sqlx::query(
+                r#"
+                UPDATE clips
+                SET tags = json_replace(tags, (SELECT fullkey FROM json_each(clips.tags) WHERE value = ?1 LIMIT 1), ?2)
+                WHERE EXISTS (SELECT 1 FROM json_each(clips.tags) WHERE value = ?1)
+                "#,
+            )
+            .bind(source)
+            .bind(target)
+            .execute(&mut *tx)
+            .await?;
+
+            // A rule already on `target` wins over the source's
+            sqlx::query("UPDATE OR IGNORE retention_rules SET tag = ? WHERE tag = ?")
+                .bind(target)
+                .bind(source)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM retention_rules WHERE tag = ?")
+                .bind(source)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE collection_rules SET rule_value = ? WHERE rule_type = ? AND rule_value = ? COLLATE NOCASE")
+                .bind(target)
+                .bind(CollectionRuleType::TagContains.as_str())
+                .bind(source)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        record_audit(
+            &mut *tx,
+            "merge_tags",
+            "tag",
+            target,
+            Some(serde_json::json!({ "sources": sources, "clips": clips_changed })),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(clips_changed as u64)
+    }
+
+    pub async fn remove_clip_from_collection(&self, collection_id: &str, clip_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM clip_collections WHERE clip_id = ? AND collection_id = ?")
+            .bind(clip_id)
+            .bind(collection_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_collection_clip_ids(&self, collection_id: &str) -> Result<Vec<String>> {
+        Ok(sqlx::query_scalar(
+            "SELECT clip_id FROM clip_collections WHERE collection_id = ? ORDER BY added_at",
+        )
+        .bind(collection_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    pub async fn collection_overlap(&self, collection_id_a: &str, collection_id_b: &str) -> Result<CollectionOverlap> {
+        let shared_clip_ids: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT clip_id FROM clip_collections WHERE collection_id = ?
+            INTERSECT
+            SELECT clip_id FROM clip_collections WHERE collection_id = ?
+            "#,
+        )
+        .bind(collection_id_a)
+        .bind(collection_id_b)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let exclusive_count = |first: &str, second: &str| {
+            let (first, second) = (first.to_string(), second.to_string());
+            async move {
+                let count: i64 = sqlx::query_scalar(
+                    r#"
+                    SELECT COUNT(*) FROM (
+                        SELECT clip_id FROM clip_collections WHERE collection_id = ?
+                        EXCEPT
+                        SELECT clip_id FROM clip_collections WHERE collection_id = ?
+                    )
+                    "#,
+                )
+                .bind(first)
+                .bind(second)
+                .fetch_one(&self.pool)
+                .await?;
+                Ok::<_, anyhow::Error>(count as u64)
+            }
+        };
+
+        let only_in_a = exclusive_count(collection_id_a, collection_id_b).await?;
+        let only_in_b = exclusive_count(collection_id_b, collection_id_a).await?;
+        let shared_count = shared_clip_ids.len() as u64;
+        let union = shared_count + only_in_a + only_in_b;
+
+        Ok(CollectionOverlap {
+            shared_clip_ids,
+            jaccard_similarity: if union > 0 { shared_count as f32 / union as f32 } else { 0.0 },
+            only_in_a,
+            only_in_b,
+            shared_count,
+        })
+    }
+
+    /// Creates `new_name` holding the union of both collections. The source
+    /// collections are left as they are.
+    pub async fn merge_collections(&self, collection_id_a: &str, collection_id_b: &str, new_name: &str) -> Result<Collection> {
+        let collection = Collection {
+            id: Uuid::new_v4().to_string(),
+            name: new_name.to_string(),
+            created_at: Utc::now(),
+            auto_file: false,
+            description: None,
+            last_described_at: None,
+        };
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("INSERT INTO collections (id, name, created_at) VALUES (?, ?, ?)")
+            .bind(&collection.id)
+            .bind(&collection.name)
+            .bind(collection.created_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO clip_collections (clip_id, collection_id, added_at)
+            SELECT clip_id, ?, MIN(added_at) FROM clip_collections
+            WHERE collection_id IN (?, ?)
+            GROUP BY clip_id
+            "#,
+        )
+        .bind(&collection.id)
+        .bind(collection_id_a)
+        .bind(collection_id_b)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(collection)
+    }
+
+    /// Creates a collection for every tag used by at least `min_clips` clips,
+    /// unless a collection of that name already exists, and fills it with
+    /// the tagged clips. With `dry_run` nothing is written and the returned
+    /// collections are only what would be created.
+    pub async fn auto_create_collections_from_tags(&self, min_clips: u32, dry_run: bool) -> Result<Vec<Collection>> {
+        let tags: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT j.value AS tag
+            FROM clips c, json_each(c.tags) j
+            WHERE j.value NOT IN (SELECT name FROM collections)
+            GROUP BY tag
+            HAVING COUNT(DISTINCT c.id) >= ?
+            ORDER BY tag
+            "#,
+        )
+        .bind(min_clips as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let now = Utc::now();
+        let collections: Vec<Collection> = tags
+            .into_iter()
+            .map(|tag| Collection {
+                id: Uuid::new_v4().to_string(),
+                name: tag,
+                created_at: now,
+                auto_file: false,
+                description: None,
+                last_described_at: None,
+            })
+            .collect();
+
+        if dry_run || collections.is_empty() {
+            return Ok(collections);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        for collection in &collections {
+            sqlx::query("INSERT INTO collections (id, name, created_at) VALUES (?, ?, ?)")
+                .bind(&collection.id)
+                .bind(&collection.name)
+                .bind(collection.created_at.to_rfc3339())
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO clip_collections (clip_id, collection_id, added_at)
+                SELECT id, ?, ? FROM clips
+                WHERE EXISTS (SELECT 1 FROM json_each(clips.tags) WHERE json_each.value = ?)
+                "#,
+            )
+            .bind(&collection.id)
+            .bind(collection.created_at.to_rfc3339())
+            .bind(&collection.name)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(collections)
+    }
+
+    /// Embeds `text` with the configured model. Once that model has been
+    /// calibrated, a vector of any other size is rejected.
+    pub async fn embed_text(&self, text: &str) -> Result<Vec<f32>> {
+        let embedding = self
+            .metrics
+            .time(Operation::Embedding, self.embedder.get_embedding(text))
+            .await?;
+
+        self.check_embedding_dim(&embedding)?;
+        Ok(embedding)
+    }
+
+    /// `embed_text` for several texts in one request.
+    pub async fn embed_texts(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let embeddings = self
+            .metrics
+            .time(Operation::Embedding, self.embedder.batch_embed(texts))
+            .await?;
+
+        for embedding in &embeddings {
+            self.check_embedding_dim(embedding)?;
+        }
+        Ok(embeddings)
+    }
+
+    fn check_embedding_dim(&self, embedding: &[f32]) -> Result<()> {
+        if let Some(calibration) = self.calibration.as_ref().filter(|c| c.model == self.ollama.model()) {
+            if embedding.len() != calibration.expected_dim {
+                return Err(anyhow!(
+                    "{} returned a {}-dimensional embedding, expected {}; recalibrate if the model changed",
+                    calibration.model,
+                    embedding.len(),
+                    calibration.expected_dim
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Probes the configured model for its embedding size and records it.
+    /// Returns `None` without changing anything when Ollama can't be
+    /// reached, so calibration can be retried later.
+    pub async fn calibrate_embeddings(&mut self) -> Result<Option<EmbeddingCalibration>> {
+        if let Err(e) = self.ollama.check_health().await {
+            eprintln!("Deferring embedding calibration: {}", e);
+            return Ok(None);
+        }
+
+        let expected_dim = self.embedder.get_embedding(CALIBRATION_TEXT).await?.len();
+        if expected_dim == 0 {
+            return Err(anyhow!("{} returned an empty embedding", self.ollama.model()));
+        }
+        let calibration = EmbeddingCalibration {
+            model: self.ollama.model().to_string(),
+            expected_dim,
+            calibrated_at: Utc::now(),
+        };
+
+        sqlx::query("INSERT OR REPLACE INTO settings (key, value) VALUES ('embedding_calibration', ?)")
+            .bind(serde_json::to_string(&calibration)?)
+            .execute(&self.pool)
+            .await?;
+        self.calibration = Some(calibration.clone());
+        Ok(Some(calibration))
+    }
+
+    /// The current model's calibration, if it has one.
+    pub fn embedding_calibration(&self) -> Option<&EmbeddingCalibration> {
+        self.calibration.as_ref().filter(|c| c.model == self.ollama.model())
+    }
+
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    async fn score_clips(&self, query_embedding: &[f32]) -> Result<Vec<(f32, String)>> {
+        Ok(self
+            .score_clips_with_chunks(query_embedding)
+            .await?
+            .into_iter()
+            .map(|(score, id, _)| (score, id))
+            .collect())
+    }
+
+    // Scores every embedded clip against the query, most similar first (higher
+    // scores are always better, whatever the configured metric). A chunked
+    // clip scores as its best chunk when that beats the whole-clip vector.
+    // Only ids and embeddings are streamed so clip contents never pile up in memory.
+    async fn score_clips_with_chunks(&self, query_embedding: &[f32]) -> Result<Vec<(f32, String, Option<ChunkSpan>)>> {
+        let metric = self.config.similarity_metric;
+        let mut best: std::collections::HashMap<String, (f32, Option<ChunkSpan>)> = std::collections::HashMap::new();
+
+        let model = self.ollama.model();
+        let dim = query_embedding.len() as i64;
+
+        let stale: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM clips WHERE embedding IS NOT NULL AND NOT ({})",
+            CURRENT_MODEL_EMBEDDING
+        ))
+        .bind(model)
+        .bind(dim)
+        .fetch_one(&self.pool)
+        .await?;
+        if stale > 0 {
+            eprintln!(
+                "Semantic search skipped {} clips embedded with a model other than {}; reindex them to include them",
+                stale, model
+            );
+        }
+
+        let mut rows = sqlx::query(&format!(
+            "SELECT id, embedding FROM clips WHERE embedding IS NOT NULL AND {}",
+            CURRENT_MODEL_EMBEDDING
+        ))
+        .bind(model)
+        .bind(dim)
+        .fetch(&self.pool);
+        while let Some(row) = rows.try_next().await? {
+            let embedding_bytes: Vec<u8> = row.get("embedding");
+            let score = similarity(metric, query_embedding, &decode_embedding(&embedding_bytes));
+            best.insert(row.get("id"), (score, None));
+        }
+        drop(rows);
+
+        let mut rows = sqlx::query(
+            r#"
+            SELECT clip_id, chunk_index, start_char, end_char, embedding FROM clip_chunks
+            WHERE embed_model = ? OR (embed_model IS NULL AND LENGTH(embedding) = ? * 4)
+            "#,
+        )
+        .bind(model)
+        .bind(dim)
+        .fetch(&self.pool);
+        while let Some(row) = rows.try_next().await? {
+            let embedding_bytes: Vec<u8> = row.get("embedding");
+            let score = similarity(metric, query_embedding, &decode_embedding(&embedding_bytes));
+            let span = ChunkSpan {
+                chunk_index: row.get::<i64, _>("chunk_index") as u32,
+                start: row.get::<i64, _>("start_char") as usize,
+                end: row.get::<i64, _>("end_char") as usize,
+            };
+            let entry = best.entry(row.get("clip_id")).or_insert((f32::NEG_INFINITY, None));
+            if score > entry.0 {
+                *entry = (score, Some(span));
+            }
+        }
+        drop(rows);
+
+        let mut scored_clips: Vec<(f32, String, Option<ChunkSpan>)> =
+            best.into_iter().map(|(id, (score, span))| (score, id, span)).collect();
+        scored_clips.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        Ok(scored_clips)
+    }
+
+    /// Ids of long clips that have no chunk embeddings yet, newest first.
+    pub async fn clips_needing_chunks(&self, limit: i32) -> Result<Vec<String>> {
+        Ok(sqlx::query_scalar(
+            r#"
+            SELECT id FROM clips
+            WHERE LENGTH(content) > ?
+              AND NOT EXISTS (SELECT 1 FROM clip_chunks WHERE clip_id = clips.id)
+            ORDER BY created_at DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(CHUNK_MIN_CHARS as i64)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Splits a long clip into overlapping chunks, embeds them in one batch
+    /// and replaces its stored chunks. Returns the number of chunks; short
+    /// clips get none.
+    pub async fn refresh_chunks(&self, clip_id: &str) -> Result<usize> {
+        let clip = self
+            .get_clip(clip_id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", clip_id))?;
+
+        let spans = chunk_spans(&clip.content);
+        let texts: Vec<&str> = spans.iter().map(|(_, _, text)| *text).collect();
+        let embeddings = self.embed_texts(&texts).await?;
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM clip_chunks WHERE clip_id = ?")
+            .bind(clip_id)
+            .execute(&mut *tx)
+            .await?;
+        for (index, ((start, end, _), embedding)) in spans.iter().zip(&embeddings).enumerate() {
+            sqlx::query(
+                "INSERT INTO clip_chunks (clip_id, chunk_index, start_char, end_char, embedding, embed_model) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(clip_id)
+            .bind(index as i64)
+            .bind(*start as i64)
+            .bind(*end as i64)
+            .bind(encode_embedding(embedding))
+            .bind(self.ollama.model())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(spans.len())
+    }
+
+    /// Sentences of `clip_id`, split on first use and cached until its
+    /// content changes; read-only libraries split them without caching.
+    /// Masked clips are refused.
+    pub async fn get_clip_sentences(&self, clip_id: &str) -> Result<Vec<SentenceSpan>> {
+        let rows = sqlx::query(
+            "SELECT start_char, end_char, text FROM clip_sentences WHERE clip_id = ? ORDER BY sentence_index",
+        )
+        .bind(clip_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let clip = self
+            .get_clip(clip_id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", clip_id))?;
+        if clip.masked {
+            return Err(anyhow!("Clip is masked: {}", clip_id));
+        }
+
+        if !rows.is_empty() {
+            return Ok(rows
+                .iter()
+                .map(|row| SentenceSpan {
+                    start_char: row.get::<i64, _>("start_char") as u32,
+                    end_char: row.get::<i64, _>("end_char") as u32,
+                    text: row.get("text"),
+                })
+                .collect());
+        }
+
+        let sentences = sentence_spans(&clip.content);
+        if self.read_only {
+            return Ok(sentences);
+        }
+        let mut tx = self.pool.begin().await?;
+        for (index, sentence) in sentences.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO clip_sentences (clip_id, sentence_index, start_char, end_char, text)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(clip_id)
+            .bind(index as i64)
+            .bind(sentence.start_char as i64)
+            .bind(sentence.end_char as i64)
+            .bind(&sentence.text)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(sentences)
+    }
+
+    /// Embedding of a single sentence of `clip_id`, for fine-grained search.
+    pub async fn get_sentence_embedding(&self, clip_id: &str, sentence_index: u32) -> Result<Vec<f32>> {
+        let sentences = self.get_clip_sentences(clip_id).await?;
+        let sentence = sentences
+            .get(sentence_index as usize)
+            .ok_or_else(|| anyhow!("Clip {} has no sentence {}", clip_id, sentence_index))?;
+
+        self.embed_text(&sentence.text).await
+    }
+
+    pub async fn record_access(&self, clip_id: &str) -> Result<()> {
+        sqlx::query("INSERT INTO clip_accesses (clip_id, accessed_at) VALUES (?, ?)")
+            .bind(clip_id)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Keeps only the `keep_per_clip` most recent access rows for each clip.
+    pub async fn prune_access_log(&self, keep_per_clip: u32) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            WITH ranked AS (
+                SELECT id, ROW_NUMBER() OVER (PARTITION BY clip_id ORDER BY accessed_at DESC) AS row_num
+                FROM clip_accesses
+            )
+            DELETE FROM clip_accesses
+            WHERE id IN (SELECT id FROM ranked WHERE row_num > ?)
+            "#,
+        )
+        .bind(keep_per_clip as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Removes access rows older than `older_than_days`, regardless of clip.
+    pub async fn prune_old_accesses(&self, older_than_days: u64) -> Result<u64> {
+        let cutoff = Utc::now() - chrono::Duration::days(older_than_days as i64);
+
+        let result = sqlx::query("DELETE FROM clip_accesses WHERE accessed_at < ?")
+            .bind(cutoff.to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Runs NER on a clip and replaces its stored entities with the result.
+    pub async fn extract_entities_for_clip(&self, clip_id: &str) -> Result<Vec<Entity>> {
+        let clip = self
+            .get_clip(clip_id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", clip_id))?;
+
+        let entities = self.ollama.extract_entities(&clip.content).await?;
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM entities WHERE clip_id = ?")
+            .bind(clip_id)
+            .execute(&mut *tx)
+            .await?;
+        for entity in &entities {
+            sqlx::query("INSERT INTO entities (clip_id, entity_type, entity_value) VALUES (?, ?, ?)")
+                .bind(clip_id)
+                .bind(&entity.entity_type)
+                .bind(&entity.entity_value)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(entities)
+    }
+
+    pub async fn find_clips_by_entity(&self, entity_type: &str, entity_value: &str, limit: i32) -> Result<Vec<ClipItem>> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {} FROM clips
+            WHERE id IN (
+                SELECT clip_id FROM entities
+                WHERE entity_type = ? AND entity_value = ?
+            )
+            ORDER BY created_at DESC
+            LIMIT ?
+            "#,
+            CLIP_COLUMNS
+        ))
+        .bind(entity_type)
+        .bind(entity_value)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.rows_to_clips(rows).await
+    }
+
+    pub async fn create_share(&self, clip_id: &str, config: ShareConfig) -> Result<ClipShare> {
+        let exists: Option<String> = sqlx::query_scalar("SELECT id FROM clips WHERE id = ?")
+            .bind(clip_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if exists.is_none() {
+            return Err(anyhow!("Clip not found: {}", clip_id));
+        }
+
+        let created_at = Utc::now();
+        let expires_at = config
+            .expires_in_hours
+            .map(|hours| created_at + chrono::Duration::hours(hours as i64));
+        let password_hash = config.password.as_deref().map(sha256_hex);
+
+        let share = ClipShare {
+            share_id: Uuid::new_v4().simple().to_string(),
+            clip_id: clip_id.to_string(),
+            created_at,
+            expires_at,
+            view_count: 0,
+            password_protected: password_hash.is_some(),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO clip_shares (share_id, clip_id, created_at, expires_at, view_count, password_hash)
+            VALUES (?, ?, ?, ?, 0, ?)
+            "#,
+        )
+        .bind(&share.share_id)
+        .bind(&share.clip_id)
+        .bind(share.created_at.to_rfc3339())
+        .bind(share.expires_at.map(|t| t.to_rfc3339()))
+        .bind(password_hash)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(share)
+    }
+
+    pub async fn revoke_share(&self, share_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM clip_shares WHERE share_id = ?")
+            .bind(share_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_shares_for_clip(&self, clip_id: &str) -> Result<Vec<ClipShare>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT share_id, clip_id, created_at, expires_at, view_count, password_hash
+            FROM clip_shares
+            WHERE clip_id = ?
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(clip_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let created_at: String = row.get("created_at");
+                let expires_at: Option<String> = row.get("expires_at");
+                let password_hash: Option<String> = row.get("password_hash");
+
+                Ok(ClipShare {
+                    share_id: row.get("share_id"),
+                    clip_id: row.get("clip_id"),
+                    created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+                    expires_at: expires_at
+                        .map(|t| DateTime::parse_from_rfc3339(&t).map(|t| t.with_timezone(&Utc)))
+                        .transpose()?,
+                    view_count: row.get("view_count"),
+                    password_protected: password_hash.is_some(),
+                })
+            })
+            .collect()
+    }
+
+    /// Per-application capture statistics for the last `days` local days.
+    /// Clips without a source are grouped under "unknown".
+    pub async fn get_source_stats(&self, days: u32, tz_offset_minutes: i32) -> Result<Vec<SourceStats>> {
+        let since = local_window_start(days, tz_offset_minutes).to_rfc3339();
+
+        let rows = sqlx::query(
+            r#"
+            SELECT COALESCE(source, 'unknown') AS app,
+                   COUNT(*) AS clip_count,
+                   COALESCE(SUM(LENGTH(CAST(content AS BLOB))), 0) AS total_bytes,
+                   MAX(created_at) AS last_capture
+            FROM clips
+            WHERE created_at >= ?
+            GROUP BY app
+            ORDER BY clip_count DESC
+            "#,
+        )
+        .bind(&since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let tag_rows = sqlx::query(
+            r#"
+            SELECT COALESCE(c.source, 'unknown') AS app, j.value AS tag, COUNT(*) AS uses
+            FROM clips c, json_each(c.tags) j
+            WHERE c.created_at >= ?
+            GROUP BY app, tag
+            ORDER BY app, uses DESC, tag
+            "#,
+        )
+        .bind(&since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut tags_by_app: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
+        for row in &tag_rows {
+            let tags = tags_by_app.entry(row.get("app")).or_default();
+            if tags.len() < 3 {
+                tags.push(row.get("tag"));
+            }
+        }
+
+        let total: i64 = rows.iter().map(|row| row.get::<i64, _>("clip_count")).sum();
+
+        rows.iter()
+            .map(|row| {
+                let app: String = row.get("app");
+                let clip_count: i64 = row.get("clip_count");
+                let last_capture: String = row.get("last_capture");
+
+                Ok(SourceStats {
+                    top_tags: tags_by_app.remove(&app).unwrap_or_default(),
+                    source: app,
+                    clip_count,
+                    total_bytes: row.get("total_bytes"),
+                    share: if total > 0 { clip_count as f64 / total as f64 } else { 0.0 },
+                    last_capture: DateTime::parse_from_rfc3339(&last_capture)?.with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    /// The most frequent words across stored clips, ignoring the configured
+    /// stopwords. Masked and hash-only clips aren't read.
+    pub async fn top_terms(&self, limit: usize) -> Result<Vec<TermCount>> {
+        let mut counter = TermCounter::new(&self.config.stopwords);
+        let mut rows = sqlx::query_scalar::<_, String>("SELECT content FROM clips WHERE masked = 0").fetch(&self.pool);
+        while let Some(content) = rows.try_next().await? {
+            counter.add(&content);
+        }
+
+        Ok(counter.top(limit))
+    }
+
+    /// Clip counts per source for the last `days` local days, busiest first.
+    /// A lighter version of `get_source_stats` for the activity summary.
+    pub async fn get_source_activity(&self, days: u32, tz_offset_minutes: i32) -> Result<Vec<SourceActivity>> {
+        let since = local_window_start(days, tz_offset_minutes).to_rfc3339();
+
+        let rows = sqlx::query(
+            r#"
+            SELECT COALESCE(source, 'unknown') AS app, COUNT(*) AS clip_count
+            FROM clips
+            WHERE created_at >= ?
+            GROUP BY app
+            ORDER BY clip_count DESC, app
+            "#,
+        )
+        .bind(&since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| SourceActivity {
+                source: row.get("app"),
+                clip_count: row.get("clip_count"),
+            })
+            .collect())
+    }
+
+    /// Clip counts per hour of day and day of week for clips carrying `tag`.
+    pub async fn get_tag_time_heatmap(&self, tag: &str) -> Result<Vec<TagHeatmapPoint>> {
+        self.time_heatmap(Some(tag)).await
+    }
+
+    pub async fn get_all_clips_heatmap(&self) -> Result<Vec<TagHeatmapPoint>> {
+        self.time_heatmap(None).await
+    }
+
+    // Always returns all 168 cells (day-major) so the frontend can render the
+    // grid directly; empty cells have a count of zero.
+    async fn time_heatmap(&self, tag: Option<&str>) -> Result<Vec<TagHeatmapPoint>> {
+        let filter = if tag.is_some() {
+            "WHERE EXISTS (SELECT 1 FROM json_each(clips.tags) WHERE json_each.value = ?)"
+        } else {
+            ""
+        };
+
+        let mut query = sqlx::query(&format!(
+            r#"
+            SELECT CAST(strftime('%H', created_at) AS INTEGER) AS hour_of_day,
+                   CAST(strftime('%w', created_at) AS INTEGER) AS day_of_week,
+                   COUNT(*) AS count
+            FROM clips
+            {}
+            GROUP BY hour_of_day, day_of_week
+            "#,
+            filter
+        ));
+        if let Some(tag) = tag {
+            query = query.bind(tag);
+        }
+        let rows = query.fetch_all(&self.pool).await?;
+
+        let mut counts = [[0u64; 24]; 7];
+        for row in &rows {
+            let hour: Option<i64> = row.get("hour_of_day");
+            let day: Option<i64> = row.get("day_of_week");
+            // Unparseable timestamps give NULL and are left out
+            if let (Some(hour @ 0..=23), Some(day @ 0..=6)) = (hour, day) {
+                counts[day as usize][hour as usize] = row.get::<i64, _>("count") as u64;
+            }
+        }
+
+        Ok(counts
+            .iter()
+            .enumerate()
+            .flat_map(|(day, hours)| {
+                hours.iter().enumerate().map(move |(hour, &count)| TagHeatmapPoint {
+                    hour_of_day: hour as u8,
+                    day_of_week: day as u8,
+                    count,
+                })
+            })
+            .collect())
+    }
+
+    /// Clip counts for each of `year`'s 12 months, zero where nothing was
+    /// captured.
+    pub async fn get_monthly_clip_counts(&self, year: i32) -> Result<Vec<MonthlyCount>> {
+        let rows: Vec<(Option<i64>, i64)> = sqlx::query_as(
+            r#"
+            SELECT CAST(strftime('%m', created_at) AS INTEGER) AS month, COUNT(*)
+            FROM clips
+            WHERE strftime('%Y', created_at) = ?
+            GROUP BY month
+            "#,
+        )
+        .bind(format!("{:04}", year))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut counts = [0u64; 12];
+        for (month, count) in rows {
+            if let Some(month @ 1..=12) = month {
+                counts[month as usize - 1] = count as u64;
+            }
+        }
+
+        Ok((1..=12)
+            .map(|month| MonthlyCount {
+                year,
+                month,
+                count: counts[month as usize - 1],
+            })
+            .collect())
+    }
+
+    /// Clip counts for every day of `month` (1-12) in `year`, zero where
+    /// nothing was captured.
+    pub async fn get_daily_clip_counts(&self, year: i32, month: u32) -> Result<Vec<DailyCount>> {
+        let first = chrono::NaiveDate::from_ymd_opt(year, month, 1)
+            .ok_or_else(|| anyhow!("Invalid month: {}-{}", year, month))?;
+        let days_in_month = first
+            .checked_add_months(chrono::Months::new(1))
+            .map_or(31, |next| (next - first).num_days() as u32);
+
+        let rows: Vec<(Option<i64>, i64)> = sqlx::query_as(
+            r#"
+            SELECT CAST(strftime('%d', created_at) AS INTEGER) AS day, COUNT(*)
+            FROM clips
+            WHERE strftime('%Y', created_at) = ? AND strftime('%m', created_at) = ?
+            GROUP BY day
+            "#,
+        )
+        .bind(format!("{:04}", year))
+        .bind(format!("{:02}", month))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut counts = vec![0u64; days_in_month as usize];
+        for (day, count) in rows {
+            if let Some(day) = day.filter(|day| (1..=days_in_month as i64).contains(day)) {
+                counts[day as usize - 1] = count as u64;
+            }
+        }
+
+        Ok(counts
+            .into_iter()
+            .enumerate()
+            .map(|(index, count)| DailyCount {
+                year,
+                month,
+                day: index as u32 + 1,
+                count,
+            })
+            .collect())
+    }
+
+    /// Copies every readable clip from a (possibly damaged) database file into
+    /// this one. Rows are read in rowid batches; a batch that fails is retried
+    /// row by row so one bad page doesn't cost the rest of the table.
+    pub async fn salvage_clips_from(&self, path: &Path) -> Result<SalvageReport> {
+        // ATTACH is per-connection, so keep the whole salvage on one connection
+        let mut conn = self.pool.acquire().await?;
+
+        sqlx::query("ATTACH DATABASE ? AS damaged")
+            .bind(path.display().to_string())
+            .execute(&mut *conn)
+            .await?;
+
+        let result = async {
+            let damaged_columns: Vec<String> = sqlx::query("PRAGMA damaged.table_info(clips)")
+                .fetch_all(&mut *conn)
+                .await?
+                .iter()
+                .map(|row| row.get::<String, _>("name"))
+                .collect();
+            // Files from before the created_at/last_modified split only
+            // have `timestamp`, which seeds both
+            let has_column = |name: &str| damaged_columns.iter().any(|c| c == name);
+            let (columns, sources): (Vec<&str>, Vec<&str>) = CLIP_COLUMNS
+                .split(", ")
+                .filter_map(|column| match column {
+                    // Renumbered below so they can't collide with existing clips
+                    "seq" => None,
+                    _ if has_column(column) => Some((column, column)),
+                    "created_at" | "last_modified" if has_column("timestamp") => Some((column, "timestamp")),
+                    _ => None,
+                })
+                .unzip();
+            if columns.is_empty() {
+                return Err(anyhow!("No readable clips table in {}", path.display()));
+            }
+
+            let max_rowid: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(rowid), 0) FROM damaged.clips")
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap_or(0);
+
+            let copy_range = format!(
+                "INSERT OR IGNORE INTO main.clips ({cols}) SELECT {sources} FROM damaged.clips WHERE rowid BETWEEN ? AND ?",
+                cols = columns.join(", "),
+                sources = sources.join(", ")
+            );
+
+            let mut report = SalvageReport::default();
+            let mut start = 1;
+            while start <= max_rowid {
+                let end = start + SALVAGE_BATCH_SIZE - 1;
+                match sqlx::query(&copy_range).bind(start).bind(end).execute(&mut *conn).await {
+                    Ok(result) => report.recovered_clips += result.rows_affected(),
+                    Err(_) => {
+                        for rowid in start..=end {
+                            match sqlx::query(&copy_range).bind(rowid).bind(rowid).execute(&mut *conn).await {
+                                Ok(result) => report.recovered_clips += result.rows_affected(),
+                                Err(_) => report.unreadable_rows += 1,
+                            }
+                        }
+                    }
+                }
+                start = end + 1;
+            }
+            number_unnumbered_clips(&mut *conn).await?;
+
+            Ok::<_, anyhow::Error>(report)
+        }
+        .await;
+
+        let _ = sqlx::query("DETACH DATABASE damaged").execute(&mut *conn).await;
+        result
+    }
+
+    /// Moves clips older than `older_than_days` into the archive database,
+    /// removing them (and their FTS entries) from the hot tables.
+    pub async fn archive_clips(&self, older_than_days: u64) -> Result<u64> {
+        let cutoff = (Utc::now() - chrono::Duration::days(older_than_days as i64)).to_rfc3339();
+        self.move_to_archive("last_modified < ?", &cutoff).await
+    }
+
+    /// Deletes clips from `source` created more than `older_than_hours` ago,
+    /// keeping any that `prune_protected` covers.
+    pub async fn prune_clips_from_source(&self, source: &str, older_than_hours: u64) -> Result<u64> {
+        let cutoff = (Utc::now() - chrono::Duration::hours(older_than_hours as i64)).to_rfc3339();
+        let (protected, binds) = self.prune_protected();
+        let sql = format!(
+            "SELECT id FROM clips WHERE source = ? AND created_at < ? AND NOT ({})",
+            protected
+        );
+        let mut query = sqlx::query_scalar::<_, String>(&sql).bind(source).bind(cutoff);
+        for value in binds {
+            query = query.bind(value);
+        }
+        let ids = query.fetch_all(&self.pool).await?;
+
+        for id in &ids {
+            self.delete_clip(id).await?;
+        }
+        Ok(ids.len() as u64)
+    }
+
+    /// Binds `clip_id` to quick-access `slot`, replacing whatever was there.
+    pub async fn assign_slot(&self, slot: u8, clip_id: &str) -> Result<()> {
+        if !(1..=SLOT_COUNT).contains(&slot) {
+            return Err(anyhow!("Slot must be between 1 and {}", SLOT_COUNT));
+        }
+        if self.get_clip(clip_id).await?.is_none() {
+            return Err(anyhow!("Clip not found: {}", clip_id));
+        }
+
+        sqlx::query("INSERT OR REPLACE INTO slots (slot, clip_id, assigned_at) VALUES (?, ?, ?)")
+            .bind(slot as i64)
+            .bind(clip_id)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn clear_slot(&self, slot: u8) -> Result<()> {
+        sqlx::query("DELETE FROM slots WHERE slot = ?")
+            .bind(slot as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_slots(&self) -> Result<Vec<SlotAssignment>> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT s.slot, s.assigned_at, {}
+            FROM slots s
+            JOIN clips c ON c.id = s.clip_id
+            ORDER BY s.slot
+            "#,
+            prefixed_clip_columns("c")
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let assigned_at: String = row.get("assigned_at");
+                Ok(SlotAssignment {
+                    slot: row.get::<i64, _>("slot") as u8,
+                    clip: ClipPreview::new(&row_to_clip(row)?, self.config.preview_chars),
+                    assigned_at: DateTime::parse_from_rfc3339(&assigned_at)?.with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    pub async fn get_slot_clip(&self, slot: u8) -> Result<Option<ClipItem>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM clips WHERE id = (SELECT clip_id FROM slots WHERE slot = ?)",
+            CLIP_COLUMNS
+        ))
+        .bind(slot as i64)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(row_to_clip).transpose()
+    }
+
+    /// Pins a clip to the first free global position. Pinning an already
+    /// pinned clip does nothing.
+    pub async fn add_global_pin(&self, clip_id: &str) -> Result<()> {
+        if self.get_clip(clip_id).await?.is_none() {
+            return Err(anyhow!("Clip not found: {}", clip_id));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let pinned: Vec<(String, i64)> = sqlx::query_as("SELECT clip_id, position FROM global_pins")
+            .fetch_all(&mut *tx)
+            .await?;
+        if pinned.iter().any(|(id, _)| id == clip_id) {
+            return Ok(());
+        }
+
+        let position = (0..GLOBAL_PIN_COUNT as i64)
+            .find(|position| !pinned.iter().any(|(_, used)| used == position))
+            .ok_or_else(|| anyhow!("All {} pins are in use", GLOBAL_PIN_COUNT))?;
+
+        sqlx::query("INSERT INTO global_pins (clip_id, position, pinned_at) VALUES (?, ?, ?)")
+            .bind(clip_id)
+            .bind(position)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn remove_global_pin(&self, clip_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM global_pins WHERE clip_id = ?")
+            .bind(clip_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Reassigns positions 0.. in the order given. `ordered_ids` must list
+    /// exactly the currently pinned clips.
+    pub async fn reorder_global_pins(&self, ordered_ids: &[&str]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let pinned: Vec<(String, String)> = sqlx::query_as("SELECT clip_id, pinned_at FROM global_pins")
+            .fetch_all(&mut *tx)
+            .await?;
+        let mut requested: Vec<&str> = ordered_ids.to_vec();
+        requested.sort_unstable();
+        requested.dedup();
+        let mut current: Vec<&str> = pinned.iter().map(|(id, _)| id.as_str()).collect();
+        current.sort_unstable();
+        if requested.len() != ordered_ids.len() || requested != current {
+            return Err(anyhow!("Reorder must list each pinned clip exactly once"));
+        }
+
+        // Positions are unique, so clear them all before writing the new order
+        sqlx::query("DELETE FROM global_pins").execute(&mut *tx).await?;
+        for (position, clip_id) in ordered_ids.iter().enumerate() {
+            let pinned_at = pinned
+                .iter()
+                .find(|(id, _)| id == clip_id)
+                .map(|(_, pinned_at)| pinned_at.as_str());
+            sqlx::query("INSERT INTO global_pins (clip_id, position, pinned_at) VALUES (?, ?, ?)")
+                .bind(*clip_id)
+                .bind(position as i64)
+                .bind(pinned_at)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Globally pinned clips in position order.
+    pub async fn get_global_pins(&self) -> Result<Vec<ClipItem>> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM global_pins p
+            JOIN clips c ON c.id = p.clip_id
+            ORDER BY p.position
+            "#,
+            prefixed_clip_columns("c")
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.rows_to_clips(rows).await
+    }
+
+    /// Permanently deletes a clip and its versions, collection memberships
+    /// and shares. Returns the slots it occupied, which are now empty.
+    pub async fn delete_clip(&self, id: &str) -> Result<Vec<u8>> {
+        let mut tx = self.pool.begin().await?;
+
+        let slots: Vec<i64> = sqlx::query_scalar("SELECT slot FROM slots WHERE clip_id = ?")
+            .bind(id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let deleted = sqlx::query("DELETE FROM clips WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if deleted == 0 {
+            return Err(anyhow!("Clip not found: {}", id));
+        }
+
+        for table in ["clip_versions", "clip_collections", "clip_shares", "clip_accesses", "entities"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE clip_id = ?", table))
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        record_audit(&mut *tx, "delete", "clip", id, None).await?;
+
+        tx.commit().await?;
+        Ok(slots.into_iter().map(|slot| slot as u8).collect())
+    }
+
+    /// Audit entries, newest first, optionally only those for `entity_id`.
+    pub async fn get_audit_log(&self, entity_id: Option<&str>, limit: i32, offset: i32) -> Result<Vec<AuditEntry>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, operation, entity_type, entity_id, performed_at, details_json
+            FROM audit_log
+            WHERE ?1 IS NULL OR entity_id = ?1
+            ORDER BY performed_at DESC, id
+            LIMIT ?2 OFFSET ?3
+            "#,
+        )
+        .bind(entity_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let performed_at: String = row.get("performed_at");
+                let details: Option<String> = row.get("details_json");
+                Ok(AuditEntry {
+                    id: row.get("id"),
+                    operation: row.get("operation"),
+                    entity_type: row.get("entity_type"),
+                    entity_id: row.get("entity_id"),
+                    performed_at: DateTime::parse_from_rfc3339(&performed_at)?.with_timezone(&Utc),
+                    details: details.as_deref().map(serde_json::from_str).transpose()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Deletes audit entries older than `days`; returns how many went.
+    pub async fn clear_audit_log_older_than(&self, days: u64) -> Result<u64> {
+        let cutoff = (Utc::now() - chrono::Duration::days(days as i64)).to_rfc3339();
+        let result = sqlx::query("DELETE FROM audit_log WHERE performed_at < ?")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes every clip in `ids` (unknown ids are ignored) along with the
+    /// same dependent rows as `delete_clip`, in one transaction. Returns how
+    /// many clips were removed.
+    pub async fn delete_clips(&self, ids: &[String]) -> Result<usize> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let mut deleted = 0;
+
+        for chunk in ids.chunks(DELETE_CHUNK_SIZE) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+
+            let existing_sql = format!("SELECT id FROM clips WHERE id IN ({})", placeholders);
+            let mut existing = sqlx::query_scalar::<_, String>(&existing_sql);
+            for id in chunk {
+                existing = existing.bind(id);
+            }
+            for id in existing.fetch_all(&mut *tx).await? {
+                record_audit(&mut *tx, "delete", "clip", &id, None).await?;
+            }
+
+            let statements = std::iter::once(format!("DELETE FROM clips WHERE id IN ({})", placeholders)).chain(
+                ["clip_versions", "clip_collections", "clip_shares", "clip_accesses", "entities"]
+                    .iter()
+                    .map(|table| format!("DELETE FROM {} WHERE clip_id IN ({})", table, placeholders)),
+            );
+
+            for (index, sql) in statements.enumerate() {
+                let mut query = sqlx::query(&sql);
+                for id in chunk {
+                    query = query.bind(id);
+                }
+                let affected = query.execute(&mut *tx).await?.rows_affected();
+                if index == 0 {
+                    deleted += affected as usize;
+                }
+            }
+        }
+
+        tx.commit().await?;
+        Ok(deleted)
+    }
+
+    pub async fn archive_clip(&self, id: &str) -> Result<()> {
+        match self.move_to_archive("id = ?", id).await? {
+            0 => Err(anyhow!("Clip not found: {}", id)),
+            _ => Ok(()),
+        }
+    }
+
+    // Moves the clips matching `condition` (with one bound value) in a single
+    // transaction; the delete trigger drops their hot FTS entries.
+    async fn move_to_archive(&self, condition: &str, value: &str) -> Result<u64> {
+        let mut conn = self.pool.acquire().await?;
+        self.attach_archive(&mut conn).await?;
+
+        let result = async {
+            let mut tx = conn.begin().await?;
+
+            let archived: Vec<String> = sqlx::query_scalar(&format!("SELECT id FROM main.clips WHERE {}", condition))
+                .bind(value)
+                .fetch_all(&mut *tx)
+                .await?;
+            for id in &archived {
+                record_audit(&mut *tx, "archive", "clip", id, None).await?;
+            }
+
+            let moved = sqlx::query(&format!(
+                "INSERT OR REPLACE INTO archive.clips ({cols}) SELECT {cols} FROM main.clips WHERE {condition}",
+                cols = CLIP_COLUMNS,
+                condition = condition
+            ))
+            .bind(value)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+            sqlx::query(&format!(
+                "INSERT INTO archive.clips_fts ({cols}) SELECT {values} FROM main.clips WHERE {condition}",
+                cols = FTS_COLUMNS.join(", "),
+                values = fts_values(""),
+                condition = condition
+            ))
+            .bind(value)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(&format!("DELETE FROM main.clips WHERE {}", condition))
+                .bind(value)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok::<_, anyhow::Error>(moved)
+        }
+        .await;
+
+        let _ = sqlx::query("DETACH DATABASE archive").execute(&mut *conn).await;
+        result
+    }
+
+    pub async fn search_archive(&self, query: &str, limit: i32) -> Result<Vec<ClipItem>> {
+        let mut conn = self.pool.acquire().await?;
+        self.attach_archive(&mut conn).await?;
+
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM archive.clips a
+            JOIN archive.clips_fts ON a.id = clips_fts.id
+            WHERE clips_fts MATCH ?
+            ORDER BY rank
+            LIMIT ?
+            "#,
+            prefixed_clip_columns("a")
+        ))
+        .bind(query)
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await;
+
+        let _ = sqlx::query("DETACH DATABASE archive").execute(&mut *conn).await;
+        self.rows_to_clips(rows?).await
+    }
+
+    /// Moves an archived clip back into the hot set; the insert trigger
+    /// re-indexes it in FTS.
+    pub async fn unarchive_clip(&self, id: &str) -> Result<ClipItem> {
+        let mut conn = self.pool.acquire().await?;
+        self.attach_archive(&mut conn).await?;
+
+        let result = async {
+            let mut tx = conn.begin().await?;
+
+            let restored = sqlx::query(&format!(
+                "INSERT INTO main.clips ({cols}) SELECT {cols} FROM archive.clips WHERE id = ?",
+                cols = CLIP_COLUMNS
+            ))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if restored == 0 {
+                return Err(anyhow!("Archived clip not found: {}", id));
+            }
+
+            sqlx::query("DELETE FROM archive.clips_fts WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM archive.clips WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(())
+        }
+        .await;
+
+        let _ = sqlx::query("DETACH DATABASE archive").execute(&mut *conn).await;
+        result?;
+
+        self.get_clip(id)
+            .await?
+            .ok_or_else(|| anyhow!("Clip not found: {}", id))
+    }
+
+    // Attaches the archive file as `archive` on this connection, creating its
+    // tables and adding any columns the hot `clips` table has gained since.
+    async fn attach_archive(&self, conn: &mut SqliteConnection) -> Result<()> {
+        let path = self
+            .archive_path
+            .as_ref()
+            .ok_or_else(|| anyhow!("Archive location is not configured"))?;
+
+        sqlx::query("ATTACH DATABASE ? AS archive")
+            .bind(path.display().to_string())
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query("CREATE TABLE IF NOT EXISTS archive.clips (id TEXT PRIMARY KEY)")
+            .execute(&mut *conn)
+            .await?;
+
+        let mut archived: Vec<String> = sqlx::query("PRAGMA archive.table_info(clips)")
+            .fetch_all(&mut *conn)
+            .await?
+            .iter()
+            .map(|row| row.get("name"))
+            .collect();
+
+        // Same `timestamp` split as the hot table
+        let legacy_timestamp = archived.iter().any(|name| name == "timestamp")
+            && !archived.iter().any(|name| name == "last_modified");
+        if legacy_timestamp {
+            sqlx::query("ALTER TABLE archive.clips RENAME COLUMN timestamp TO last_modified")
+                .execute(&mut *conn)
+                .await?;
+            archived.retain(|name| name != "timestamp");
+            archived.push("last_modified".to_string());
+        }
+
+        for column in sqlx::query("PRAGMA main.table_info(clips)").fetch_all(&mut *conn).await? {
+            let name: String = column.get("name");
+            if archived.contains(&name) {
+                continue;
+            }
+
+            let column_type: String = column.get("type");
+            let not_null: bool = column.get("notnull");
+            let default: Option<String> = column.get("dflt_value");
+            let definition = match (not_null, default) {
+                (true, Some(default)) => format!("{} NOT NULL DEFAULT {}", column_type, default),
+                (_, Some(default)) => format!("{} DEFAULT {}", column_type, default),
+                (_, None) => column_type,
+            };
+
+            sqlx::query(&format!("ALTER TABLE archive.clips ADD COLUMN {} {}", name, definition))
+                .execute(&mut *conn)
+                .await?;
+        }
+
+        if legacy_timestamp {
+            sqlx::query("UPDATE archive.clips SET created_at = last_modified WHERE created_at IS NULL OR created_at = ''")
+                .execute(&mut *conn)
+                .await?;
+        }
+
+        // An index from before a change to FTS_COLUMNS is rebuilt from the
+        // archived rows
+        let fts_columns: Vec<String> = sqlx::query("PRAGMA archive.table_info(clips_fts)")
+            .fetch_all(&mut *conn)
+            .await?
+            .iter()
+            .map(|row| row.get("name"))
+            .collect();
+        let stale_fts = !fts_columns.is_empty() && !fts_columns.iter().map(String::as_str).eq(FTS_COLUMNS.iter().copied());
+        if stale_fts {
+            sqlx::query("DROP TABLE archive.clips_fts").execute(&mut *conn).await?;
+        }
+
+        sqlx::query(&format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS archive.clips_fts USING fts5(id UNINDEXED, {}, tokenize='{}')",
+            FTS_COLUMNS[1..].join(", "),
+            self.config.fts_tokenize()
+        ))
+        .execute(&mut *conn)
+        .await?;
+
+        if stale_fts {
+            sqlx::query(&format!(
+                "INSERT INTO archive.clips_fts ({}) SELECT {} FROM archive.clips WHERE {}",
+                FTS_COLUMNS.join(", "),
+                fts_values(""),
+                fts_indexed("")
+            ))
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Counts what the search index holds against the clips that should be
+    /// in it. Entries are read through `clips_fts_instances`, since reading
+    /// `clips_fts` itself goes through to `clips`. Clips with no searchable
+    /// words show up as missing.
+    pub async fn check_index(&self, progress: impl Fn(IndexProgress)) -> Result<IndexReport> {
+        progress(IndexProgress::new("counting", 0, 3));
+        let clip_count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM clips WHERE {}", fts_indexed("")))
+            .fetch_one(&self.pool)
+            .await?;
+        let fts_count: i64 = sqlx::query_scalar("SELECT COUNT(DISTINCT doc) FROM clips_fts_instances")
+            .fetch_one(&self.pool)
+            .await?;
+
+        progress(IndexProgress::new("missing", 1, 3));
+        let missing = self.clips_missing_from_fts().await?;
+
+        progress(IndexProgress::new("orphaned", 2, 3));
+        let orphaned = self.orphaned_fts_entries().await?;
+
+        progress(IndexProgress::new("done", 3, 3));
+        Ok(IndexReport {
+            clip_count,
+            fts_count,
+            missing_count: missing.len() as u64,
+            orphaned_count: orphaned.len() as u64,
+            missing_sample: missing.into_iter().take(INDEX_SAMPLE_LIMIT).collect(),
+            orphaned_sample: orphaned.into_iter().take(INDEX_SAMPLE_LIMIT).collect(),
+        })
+    }
+
+    /// Compares the search index with `clips`. Like `check_index`, and also
+    /// catches stale summaries. Clips whose text has no searchable words at
+    /// all show up as missing.
+    pub async fn verify_fts_sync(&self) -> Result<FtsSyncReport> {
+        let missing_from_fts = self.clips_missing_from_fts().await?;
+        let extra_in_fts = self.orphaned_fts_entries().await?;
+
+        // Only summaries with a word in them can be looked up as a phrase
+        let content_mismatches: Vec<String> = sqlx::query_scalar(&format!(
+            r#"
+            SELECT c.id FROM clips c
+            WHERE {} AND NOT c.masked AND c.summary GLOB '*[A-Za-z0-9]*'
+              AND c.rowid IN (SELECT doc FROM clips_fts_instances)
+              AND NOT EXISTS (
+                  SELECT 1 FROM clips_fts
+                  WHERE clips_fts MATCH 'summary : "' || REPLACE(c.summary, '"', '""') || '"'
+                    AND rowid = c.rowid
+              )
+            "#,
+            fts_indexed("c.")
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let report = FtsSyncReport {
+            missing_from_fts,
+            extra_in_fts,
+            content_mismatches,
+        };
+        if report.needs_repair() {
+            eprintln!(
+                "Search index out of sync: {} clips missing, {} with stale summaries; run repair_index to rebuild it",
+                report.missing_from_fts.len(),
+                report.content_mismatches.len()
+            );
+        }
+        Ok(report)
+    }
+
+    /// Rebuilds the search index from `clips`, reporting how many missing
+    /// and orphaned entries that fixed. Orphans can't be deleted one by one:
+    /// the 'delete' command needs the values they were indexed with, which
+    /// are gone with their clips. The rebuild also fixes stale entries.
+    pub async fn repair_index(&self, progress: impl Fn(IndexProgress)) -> Result<IndexRepairReport> {
+        progress(IndexProgress::new("checking", 0, 2));
+        let reinserted = self.clips_missing_from_fts().await?.len() as u64;
+        let removed = self.orphaned_fts_entries().await?.len() as u64;
+
+        progress(IndexProgress::new("rebuilding", 1, 2));
+        // One transaction, so a crash mid-repair leaves the index as it was
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO clips_fts(clips_fts) VALUES ('delete-all')")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!(
+            "INSERT INTO clips_fts(rowid, {}) SELECT rowid, {} FROM clips WHERE {}",
+            FTS_COLUMNS.join(", "),
+            fts_values(""),
+            fts_indexed("")
+        ))
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        progress(IndexProgress::new("done", 2, 2));
+        Ok(IndexRepairReport { reinserted, removed })
+    }
+
+    // Clips that should be searchable but have no entry in the index
+    async fn clips_missing_from_fts(&self) -> Result<Vec<String>> {
+        Ok(sqlx::query_scalar(&format!(
+            r#"
+            SELECT id FROM clips
+            WHERE {} AND rowid NOT IN (SELECT DISTINCT doc FROM clips_fts_instances)
+            "#,
+            fts_indexed("")
+        ))
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    // Index entries with no clip, or for a clip that shouldn't be indexed.
+    // Entries with no clip are identified by rowid.
+    async fn orphaned_fts_entries(&self) -> Result<Vec<String>> {
+        Ok(sqlx::query_scalar(&format!(
+            r#"
+            SELECT COALESCE(c.id, 'rowid:' || i.doc)
+            FROM (SELECT DISTINCT doc FROM clips_fts_instances) i
+            LEFT JOIN clips c ON c.rowid = i.doc
+            WHERE c.rowid IS NULL OR NOT ({})
+            "#,
+            fts_indexed("c.")
+        ))
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn rows_to_clips(&self, rows: Vec<SqliteRow>) -> Result<Vec<ClipItem>> {
+        rows.iter().map(row_to_clip).collect()
+    }
+}
+
+fn row_to_reminder(row: &SqliteRow) -> Result<Reminder> {
+    let remind_at: String = row.get("remind_at");
+    let created_at: String = row.get("created_at");
+    Ok(Reminder {
+        id: row.get("id"),
+        clip_id: row.get("clip_id"),
+        remind_at: DateTime::parse_from_rfc3339(&remind_at)?.with_timezone(&Utc),
+        created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+    })
+}
+
+fn row_to_clip(row: &SqliteRow) -> Result<ClipItem> {
+    let id: String = row.get("id");
+    let content: String = row.get("content");
+    let summary: String = row.get("summary");
+    let tags_json: String = row.get("tags");
+    let created_at: String = row.get("created_at");
+    let last_modified: String = row.get("last_modified");
+    let source: Option<String> = row.get("source");
+    let embedding_bytes: Option<Vec<u8>> = row.get("embedding");
+    let urls_json: String = row.get("urls");
+    let embed_truncated: bool = row.get("embed_truncated");
+    let content_type: String = row.get("content_type");
+    let source_url: Option<String> = row.get("source_url");
+    let rewrite_history_json: String = row.get("rewrite_history");
+    let masked: bool = row.get("masked");
+    let keywords_json: String = row.get("keywords");
+    let metadata_json: String = row.get("metadata");
+    let seq: Option<i64> = row.get("seq");
+    let code_language: Option<String> = row.get("code_language");
+
+    let tags: Vec<String> = serde_json::from_str(&tags_json)?;
+    let urls: Vec<String> = serde_json::from_str(&urls_json)?;
+    let rewrite_history: Vec<RewriteRecord> = serde_json::from_str(&rewrite_history_json)?;
+    let keywords: Vec<String> = serde_json::from_str(&keywords_json)?;
+    let metadata: ClipMetadata = serde_json::from_str(&metadata_json)?;
+    let created_at = DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc);
+    let last_modified = DateTime::parse_from_rfc3339(&last_modified)?.with_timezone(&Utc);
+    let embedding = embedding_bytes.as_deref().map(decode_embedding);
+
+    Ok(ClipItem {
+        id,
+        content,
+        summary,
+        tags,
+        created_at,
+        last_modified,
+        source,
+        embedding,
+        urls,
+        embed_truncated,
+        content_type: ContentType::parse(&content_type),
+        source_url,
+        rewrite_history,
+        masked,
+        keywords,
+        metadata,
+        seq,
+        code_language,
+    })
+}
+
+/// Start of the window covering today and the previous `days - 1` days,
+/// measured from local midnight for a client at `tz_offset_minutes` from UTC.
+fn local_window_start(days: u32, tz_offset_minutes: i32) -> DateTime<Utc> {
+    let offset = chrono::Duration::minutes(tz_offset_minutes as i64);
+    let local_today = (Utc::now() + offset).date_naive();
+    let first_day = local_today - chrono::Duration::days(days.saturating_sub(1) as i64);
+
+    first_day.and_hms_opt(0, 0, 0).unwrap().and_utc() - offset
+}
+
+// Groups newest-first clips into consecutive local days relative to `now`.
+fn group_by_local_day(clips: &[ClipItem], now: DateTime<Utc>, tz_offset_minutes: i32, preview_chars: usize) -> Vec<ClipGroup> {
+    let offset = chrono::Duration::minutes(tz_offset_minutes as i64);
+    let today = (now + offset).date_naive();
+
+    let mut groups: Vec<ClipGroup> = Vec::new();
+    for clip in clips {
+        let day = (clip.created_at + offset).date_naive();
+        let date = day.format("%Y-%m-%d").to_string();
+
+        match groups.last_mut() {
+            Some(group) if group.date == date => group.clips.push(ClipPreview::new(clip, preview_chars)),
+            _ => {
+                let label = match (today - day).num_days() {
+                    0 => "Today".to_string(),
+                    1 => "Yesterday".to_string(),
+                    2..=6 => day.format("%A").to_string(),
+                    _ => date.clone(),
+                };
+                groups.push(ClipGroup { label, date, clips: vec![ClipPreview::new(clip, preview_chars)] });
+            }
+        }
+    }
+
+    groups
+}
+
+// A clip holding one piece of `parent`. Tags are inherited except those
+// describing the whole (`url` is re-derived, `long-text` no longer applies).
+fn split_child(parent: &ClipItem, piece: &str, source: Option<String>, now: DateTime<Utc>) -> ClipItem {
+    let urls = crate::urls::extract_urls(piece);
+    let mut tags: Vec<String> = parent
+        .tags
+        .iter()
+        .filter(|tag| !matches!(tag.as_str(), "url" | "long-text") && !tag.starts_with("split-from:"))
+        .cloned()
+        .collect();
+    if !urls.is_empty() {
+        tags.push("url".to_string());
+    }
+
+    let summary = if piece.chars().count() > 50 {
+        format!("{}...", piece.chars().take(47).collect::<String>())
+    } else {
+        piece.to_string()
+    };
+
+    ClipItem {
+        id: Uuid::new_v4().to_string(),
+        content: piece.to_string(),
+        summary,
+        content_type: ContentType::detect(piece, &tags),
+        tags,
+        created_at: now,
+        last_modified: now,
+        source,
+        embedding: None,
+        urls,
+        embed_truncated: false,
+        source_url: parent.source_url.clone(),
+        rewrite_history: Vec::new(),
+        masked: parent.masked,
+        keywords: Vec::new(),
+        metadata: ClipMetadata {
+            split_from: Some(parent.id.clone()),
+            ..Default::default()
+        },
+        seq: None,
+        code_language: None,
+    }
+}
+
+// Overlapping windows of `content` as (start char, end char, text). Content
+// up to CHUNK_MIN_CHARS isn't chunked at all.
+fn chunk_spans(content: &str) -> Vec<(usize, usize, &str)> {
+    let boundaries: Vec<usize> = content
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(content.len()))
+        .collect();
+    let char_count = boundaries.len() - 1;
+    if char_count <= CHUNK_MIN_CHARS {
+        return Vec::new();
+    }
+
+    let mut spans = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + CHUNK_CHARS).min(char_count);
+        spans.push((start, end, &content[boundaries[start]..boundaries[end]]));
+        if end == char_count {
+            return spans;
+        }
+        start = end - CHUNK_OVERLAP_CHARS;
+    }
+}
+
+// Sentences of `content` per Unicode sentence boundaries, with char offsets
+fn sentence_spans(content: &str) -> Vec<SentenceSpan> {
+    let mut spans = Vec::new();
+    // Byte and char offsets of the end of the previous sentence
+    let (mut byte_pos, mut char_pos) = (0, 0);
+
+    for sentence in content.unicode_sentences() {
+        // Every item is a slice of `content`, so its address gives its offset
+        let byte_start = sentence.as_ptr() as usize - content.as_ptr() as usize;
+        let start_char = char_pos + content[byte_pos..byte_start].chars().count();
+        let text = sentence.trim_end();
+        let end_char = start_char + text.chars().count();
+
+        spans.push(SentenceSpan {
+            start_char: start_char as u32,
+            end_char: end_char as u32,
+            text: text.to_string(),
+        });
+        byte_pos = byte_start + text.len();
+        char_pos = end_char;
+    }
+
+    spans
+}
+
+// Quotes `phrase` as a single FTS5 string so operators and punctuation in it
+// are matched literally rather than parsed as query syntax.
+fn fts_phrase(phrase: &str) -> String {
+    format!("\"{}\"", phrase.replace('"', "\"\""))
+}
+
+// CLIP_COLUMNS qualified with a table alias, for queries that join
+fn prefixed_clip_columns(alias: &str) -> String {
+    CLIP_COLUMNS
+        .split(", ")
+        .map(|column| format!("{}.{}", alias, column))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+async fn record_audit<'e, E>(
+    executor: E,
+    operation: &str,
+    entity_type: &str,
+    entity_id: &str,
+    details: Option<serde_json::Value>,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (id, operation, entity_type, entity_id, performed_at, details_json)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(operation)
+    .bind(entity_type)
+    .bind(entity_id)
+    .bind(Utc::now().to_rfc3339())
+    .bind(details.map(|details| details.to_string()))
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+// Audit details for a clip write: metadata only, never the content
+fn clip_audit_details(clip: &ClipItem) -> serde_json::Value {
+    serde_json::json!({
+        "content_type": clip.content_type.as_str(),
+        "source": clip.source,
+        "length": clip.content.len(),
+    })
+}
+
+// Numbers clips that have no short number yet, oldest first: rows from
+// before numbering existed, and salvaged ones
+async fn number_unnumbered_clips(conn: &mut SqliteConnection) -> Result<u64> {
+    let mut tx = conn.begin().await?;
+    let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM clips WHERE seq IS NULL ORDER BY created_at, rowid")
+        .fetch_all(&mut *tx)
+        .await?;
+    for id in &ids {
+        sqlx::query("UPDATE clips SET seq = (SELECT last + 1 FROM clip_seq) WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE clip_seq SET last = last + 1")
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(ids.len() as u64)
+}
+
+async fn insert_clip_row<'e, E>(
+    executor: E,
+    clip: &ClipItem,
+    embedding: Option<&[f32]>,
+    embed_truncated: bool,
+    embed_model: Option<&str>,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO clips (id, content, summary, tags, created_at, last_modified, source, embedding, urls, embed_truncated, content_type, source_url, rewrite_history, masked, keywords, metadata, embed_model, embed_dim, simhash, seq)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, (SELECT last + 1 FROM clip_seq))
+        "#,
+    )
+    .bind(&clip.id)
+    .bind(&clip.content)
+    .bind(&clip.summary)
+    .bind(serde_json::to_string(&clip.tags)?)
+    .bind(clip.created_at.to_rfc3339())
+    .bind(clip.last_modified.to_rfc3339())
+    .bind(&clip.source)
+    .bind(embedding.map(encode_embedding))
+    .bind(serde_json::to_string(&clip.urls)?)
+    .bind(embed_truncated)
+    .bind(clip.content_type.as_str())
+    .bind(&clip.source_url)
+    .bind(serde_json::to_string(&clip.rewrite_history)?)
+    .bind(clip.masked)
+    .bind(serde_json::to_string(&clip.keywords)?)
+    .bind(serde_json::to_string(&clip.metadata)?)
+    .bind(embedding.and(embed_model))
+    .bind(embedding.map(|embedding| embedding.len() as i64))
+    .bind(simhash::fingerprint(&clip.content).map(|fingerprint| fingerprint as i64))
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+// Alfred (like other Core Data apps) stores dates as seconds since
+// 2001-01-01 UTC
+fn core_data_date(seconds: f64) -> Result<DateTime<Utc>> {
+    const CORE_DATA_EPOCH: i64 = 978_307_200;
+    DateTime::from_timestamp(CORE_DATA_EPOCH + seconds.trunc() as i64, (seconds.fract() * 1e9) as u32)
+        .ok_or_else(|| anyhow!("Invalid Alfred date: {}", seconds))
+}
+
+fn validate_retention_limits(max_age_days: Option<u32>, max_count: Option<u32>) -> Result<()> {
+    if max_age_days == Some(0) {
+        return Err(anyhow!("Maximum age must be at least 1 day"));
+    }
+    if max_count == Some(0) {
+        return Err(anyhow!("Maximum count must be at least 1"));
+    }
+    Ok(())
+}
+
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn row_to_collection(row: &SqliteRow) -> Result<Collection> {
+    let created_at: String = row.get("created_at");
+
+    Ok(Collection {
+        id: row.get("id"),
+        name: row.get("name"),
+        created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+        auto_file: row.get("auto_file"),
+        description: row.get("description"),
+        last_described_at: row
+            .get::<Option<String>, _>("last_described_at")
+            .map(|t| DateTime::parse_from_rfc3339(&t).map(|t| t.with_timezone(&Utc)))
+            .transpose()?,
+    })
+}
+
+fn sha256_hex(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Writes `items` as a JSON array as they arrive, so only one is held at a
+// time. Returns how many were written.
+async fn write_json_array<T: Serialize>(
+    writer: &mut impl Write,
+    items: impl Stream<Item = Result<T>>,
+) -> Result<usize> {
+    let mut items = std::pin::pin!(items);
+    let mut count = 0;
+
+    writer.write_all(b"[")?;
+    while let Some(item) = items.try_next().await? {
+        if count > 0 {
+            writer.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut *writer, &item)?;
+        count += 1;
+    }
+    writer.write_all(b"]")?;
+    writer.flush()?;
+
+    Ok(count)
+}
+
+// NPY format 1.0 header for a C-order `f32` array of `rows` x `columns`,
+// padded so the data starts on a 64-byte boundary
+fn npy_header(rows: usize, columns: usize) -> Vec<u8> {
+    let mut dict = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        rows, columns
+    );
+    // magic (6) + version (2) + header length (2) + dict + trailing newline
+    let unpadded = 10 + dict.len() + 1;
+    dict.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    dict.push('\n');
+
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header
+}
+
+// Drops the vectors the monitor used to store in place of embeddings, so
+// semantic search can't take them for another model's when their length
+// happens to match. Those clips are left unembedded.
+async fn clear_placeholder_embeddings(pool: &SqlitePool) -> Result<u64> {
+    let rows: Vec<(String, Vec<u8>)> =
+        sqlx::query_as("SELECT id, embedding FROM clips WHERE embed_model IS NULL AND embedding IS NOT NULL")
+            .fetch_all(pool)
+            .await?;
+
+    let mut tx = pool.begin().await?;
+    let mut cleared = 0;
+    for (id, bytes) in rows {
+        if is_placeholder_embedding(&decode_embedding(&bytes)) {
+            sqlx::query("UPDATE clips SET embedding = NULL, embed_dim = NULL, embed_truncated = 0 WHERE id = ?")
+                .bind(&id)
+                .execute(&mut *tx)
+                .await?;
+            cleared += 1;
+        }
+    }
+    tx.commit().await?;
+    Ok(cleared)
+}
+
+// The placeholder was each character's code point / 255, so every
+// component is a whole number of 255ths; real embeddings never are
+fn is_placeholder_embedding(embedding: &[f32]) -> bool {
+    !embedding.is_empty()
+        && embedding
+            .iter()
+            .all(|value| *value >= 0.0 && (*value * 255.0).round() / 255.0 == *value)
+}
+
+fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+// Falls back to a plain client when the connection settings are broken, so
+// a missing certificate doesn't keep the library from opening
+fn ollama_client(config: &AppConfig) -> OllamaClient {
+    OllamaClient::from_config(config).unwrap_or_else(|e| {
+        eprintln!("Ignoring Ollama connection settings: {}", e);
+        OllamaClient::new(&config.ollama_url, &config.ollama_model)
+    })
+}
+
+// Settings changed in the app. Older versions stored the whole config,
+// which simply overrides every layer below it.
+async fn load_calibration(pool: &SqlitePool) -> Result<Option<EmbeddingCalibration>> {
+    let stored: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = 'embedding_calibration'")
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(stored.map(|json| serde_json::from_str(&json)).transpose()?)
+}
+
+async fn load_settings(pool: &SqlitePool) -> Result<Overrides> {
+    let stored: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = 'app_config'")
+        .fetch_optional(pool)
+        .await?;
+
+    match stored {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(Overrides::new()),
+    }
+}
+
+// Columns of the FTS index, in order. Changing this list recreates the index.
+const FTS_COLUMNS: &[&str] = &["id", "content", "summary", "tags", "source", "source_url", "metadata"];
+
+// Metadata fields rendered into the `metadata` FTS column
+const FTS_METADATA_FIELDS: &[&str] = &["url_title"];
+
+/// Creates the FTS table and its sync triggers. If an older index with a
+/// different column set or tokenizer exists, it's dropped, recreated and
+/// repopulated from `clips`.
+async fn ensure_fts_schema(pool: &SqlitePool, tokenize: &str) -> Result<()> {
+    let existing = table_columns(pool, "clips_fts").await?;
+    let table_sql: Option<String> = sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE name = 'clips_fts'")
+        .fetch_optional(pool)
+        .await?;
+    // Triggers from before they used FTS5's 'delete' command left the index
+    // inconsistent with `clips`, so an index maintained by them is rebuilt
+    let delete_trigger_sql: Option<String> = sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE name = 'clips_ad'")
+        .fetch_optional(pool)
+        .await?;
+    let tokenize_clause = format!("tokenize='{}'", tokenize);
+    let up_to_date = existing.iter().map(String::as_str).eq(FTS_COLUMNS.iter().copied())
+        && table_sql.is_some_and(|sql| sql.contains(&tokenize_clause))
+        && delete_trigger_sql.is_some_and(|sql| sql.contains("'delete'"));
+
+    if !existing.is_empty() && !up_to_date {
+        for statement in [
+            "DROP TRIGGER IF EXISTS clips_ai",
+            "DROP TRIGGER IF EXISTS clips_ad",
+            "DROP TRIGGER IF EXISTS clips_au",
+            "DROP TABLE IF EXISTS clips_fts",
+        ] {
+            sqlx::query(statement).execute(pool).await?;
+        }
+    }
+
+    // Create FTS5 virtual table for full-text search
+    sqlx::query(&format!(
+        r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS clips_fts USING fts5(
+            id UNINDEXED,
+            content,
+            summary,
+            tags,
+            source,
+            source_url,
+            metadata,
+            content='clips',
+            content_rowid='rowid',
+            {}
+        )
+        "#,
+        tokenize_clause
+    ))
+    .execute(pool)
+    .await?;
+
+    // Triggers are recreated every start so changes to what gets indexed
+    // (see `fts_values`) apply to existing databases too
+    for statement in [
+        "DROP TRIGGER IF EXISTS clips_ai",
+        "DROP TRIGGER IF EXISTS clips_ad",
+        "DROP TRIGGER IF EXISTS clips_au",
+    ] {
+        sqlx::query(statement).execute(pool).await?;
+    }
+
+    // Create triggers to keep FTS table in sync. clips_fts is an external
+    // content table, so entries share the clip's rowid and are removed with
+    // the 'delete' command, passing exactly the values that were indexed.
+    sqlx::query(&format!(
+        r#"
+        CREATE TRIGGER clips_ai AFTER INSERT ON clips WHEN {} BEGIN
+            INSERT INTO clips_fts(rowid, {}) VALUES (new.rowid, {});
+        END
+        "#,
+        fts_indexed("new."),
+        FTS_COLUMNS.join(", "),
+        fts_values("new.")
+    ))
+    .execute(pool)
+    .await?;
+
+    sqlx::query(&format!(
+        r#"
+        CREATE TRIGGER clips_ad AFTER DELETE ON clips WHEN {} BEGIN
+            INSERT INTO clips_fts(clips_fts, rowid, {}) VALUES ('delete', old.rowid, {});
+        END
+        "#,
+        fts_indexed("old."),
+        FTS_COLUMNS.join(", "),
+        fts_values("old.")
+    ))
+    .execute(pool)
+    .await?;
+
+    sqlx::query(&format!(
+        r#"
+        CREATE TRIGGER clips_au AFTER UPDATE ON clips BEGIN
+            INSERT INTO clips_fts(clips_fts, rowid, {cols}) SELECT 'delete', old.rowid, {old} WHERE {old_indexed};
+            INSERT INTO clips_fts(rowid, {cols}) SELECT new.rowid, {new} WHERE {new_indexed};
+        END
+        "#,
+        cols = FTS_COLUMNS.join(", "),
+        old = fts_values("old."),
+        old_indexed = fts_indexed("old."),
+        new = fts_values("new."),
+        new_indexed = fts_indexed("new.")
+    ))
+    .execute(pool)
+    .await?;
+
+    if !up_to_date {
+        sqlx::query(&format!(
+            "INSERT INTO clips_fts(rowid, {}) SELECT rowid, {} FROM clips WHERE {}",
+            FTS_COLUMNS.join(", "),
+            fts_values(""),
+            fts_indexed("")
+        ))
+        .execute(pool)
+        .await?;
+    }
+
+    // Reading clips_fts itself goes through to `clips` (it's an external
+    // content table); this exposes what the index actually holds
+    sqlx::query("CREATE VIRTUAL TABLE IF NOT EXISTS clips_fts_instances USING fts5vocab(clips_fts, instance)")
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Keyword counterpart of `ensure_fts_schema`: a separate index over the
+/// `keywords` column, rebuilt from `clips` when missing or its tokenizer
+/// changed. Masked clips index no keywords.
+async fn ensure_keywords_fts_schema(pool: &SqlitePool, tokenize: &str) -> Result<()> {
+    let table_sql: Option<String> =
+        sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE name = 'clip_keywords_fts'")
+            .fetch_optional(pool)
+            .await?;
+    let tokenize_clause = format!("tokenize='{}'", tokenize);
+    let up_to_date = table_sql.as_ref().is_some_and(|sql| sql.contains(&tokenize_clause));
+
+    if table_sql.is_some() && !up_to_date {
+        sqlx::query("DROP TABLE IF EXISTS clip_keywords_fts").execute(pool).await?;
+    }
+
+    sqlx::query(&format!(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS clip_keywords_fts USING fts5(id UNINDEXED, keywords, {})",
+        tokenize_clause
+    ))
+    .execute(pool)
+    .await?;
+
+    for statement in [
+        "DROP TRIGGER IF EXISTS clips_kw_ai",
+        "DROP TRIGGER IF EXISTS clips_kw_ad",
+        "DROP TRIGGER IF EXISTS clips_kw_au",
+        r#"
+        CREATE TRIGGER clips_kw_ai AFTER INSERT ON clips BEGIN
+            INSERT INTO clip_keywords_fts(id, keywords)
+            VALUES (new.id, CASE WHEN new.masked THEN '' ELSE new.keywords END);
+        END
+        "#,
+        r#"
+        CREATE TRIGGER clips_kw_ad AFTER DELETE ON clips BEGIN
+            DELETE FROM clip_keywords_fts WHERE id = old.id;
+        END
+        "#,
+        r#"
+        CREATE TRIGGER clips_kw_au AFTER UPDATE ON clips BEGIN
+            DELETE FROM clip_keywords_fts WHERE id = old.id;
+            INSERT INTO clip_keywords_fts(id, keywords)
+            VALUES (new.id, CASE WHEN new.masked THEN '' ELSE new.keywords END);
+        END
+        "#,
+    ] {
+        sqlx::query(statement).execute(pool).await?;
+    }
+
+    if !up_to_date {
+        sqlx::query(
+            "INSERT INTO clip_keywords_fts(id, keywords) SELECT id, CASE WHEN masked THEN '' ELSE keywords END FROM clips",
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+// Values indexed for a clips row (`row` is a prefix such as "new."), in
+// FTS_COLUMNS order. Masked clips only expose their tags and source.
+fn fts_values(row: &str) -> String {
+    format!(
+        "{r}id, \
+         CASE WHEN {r}masked THEN '' ELSE {r}content END, \
+         CASE WHEN {r}masked THEN '' ELSE {r}summary END, \
+         {r}tags, {r}source, \
+         CASE WHEN {r}masked THEN '' ELSE {r}source_url END, \
+         CASE WHEN {r}masked THEN '' ELSE {m} END",
+        r = row,
+        m = fts_metadata_text(row)
+    )
+}
+
+// Selected metadata fields as one space-separated string, for FTS
+fn fts_metadata_text(row: &str) -> String {
+    FTS_METADATA_FIELDS
+        .iter()
+        .map(|field| format!("COALESCE(json_extract({}metadata, '$.{}'), '')", row, field))
+        .collect::<Vec<_>>()
+        .join(" || ' ' || ")
+}
+
+// Condition a clips row must meet to be in the FTS index: hash-only rows
+// never are
+fn fts_indexed(row: &str) -> String {
+    format!("{}content_type != 'redacted'", row)
+}
+
+async fn table_columns(pool: &SqlitePool, table: &str) -> Result<Vec<String>> {
+    let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(pool)
+        .await?;
+
+    Ok(columns.iter().map(|row| row.get::<String, _>("name")).collect())
+}
+
+// Renames `from` to `to` unless `to` already exists. Returns true if it renamed.
+async fn rename_column_if_exists(pool: &SqlitePool, table: &str, from: &str, to: &str) -> Result<bool> {
+    let columns = table_columns(pool, table).await?;
+    if !columns.iter().any(|name| name == from) || columns.iter().any(|name| name == to) {
+        return Ok(false);
+    }
+
+    sqlx::query(&format!("ALTER TABLE {} RENAME COLUMN {} TO {}", table, from, to))
+        .execute(pool)
+        .await?;
+    Ok(true)
+}
+
+async fn add_column_if_missing(pool: &SqlitePool, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists = table_columns(pool, table)
+        .await?
+        .iter()
+        .any(|name| name == column);
+
+    if !exists {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Scores `a` against `b` so that higher always means more similar. Euclidean
+/// distance is negated to keep a single descending sort order.
+fn similarity(metric: SimilarityMetric, a: &[f32], b: &[f32]) -> f32 {
+    match metric {
+        SimilarityMetric::Cosine => cosine_similarity(a, b),
+        SimilarityMetric::DotProduct => dot_product(a, b),
+        SimilarityMetric::Euclidean => -euclidean_distance(a, b),
+    }
+}
+
+fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return f32::NEG_INFINITY;
+    }
+
+    simd_dot(a, b)
+}
+
+fn euclidean_distance(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return f32::INFINITY;
+    }
+
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum::<f32>().sqrt()
+}
+
+// Root of `id`'s group in a union-find forest, halving paths on the way
+fn group_root(parents: &mut std::collections::HashMap<String, String>, id: &str) -> String {
+    let mut current = id.to_string();
+    while let Some(parent) = parents.get(&current).cloned() {
+        if parent == current {
+            break;
+        }
+        if let Some(grandparent) = parents.get(&parent).cloned() {
+            parents.insert(current.clone(), grandparent);
+        }
+        current = parent;
+    }
+    parents.entry(current.clone()).or_insert_with(|| current.clone());
+    current
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    let dot_product = simd_dot(a, b);
+    let norm_a = simd_dot(a, a).sqrt();
+    let norm_b = simd_dot(b, b).sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot_product / (norm_a * norm_b)
+    }
+}
+
+// Dot product over eight independent accumulators. Splitting the sum lets the
+// compiler vectorize the loop, which a single running f32 sum prevents.
+fn simd_dot(a: &[f32], b: &[f32]) -> f32 {
+    const LANES: usize = 8;
+    let mut sums = [0.0f32; LANES];
+
+    let chunks_a = a.chunks_exact(LANES);
+    let chunks_b = b.chunks_exact(LANES);
+    let tail: f32 = chunks_a
+        .remainder()
+        .iter()
+        .zip(chunks_b.remainder())
+        .map(|(x, y)| x * y)
+        .sum();
+
+    for (chunk_a, chunk_b) in chunks_a.zip(chunks_b) {
+        for ((sum, x), y) in sums.iter_mut().zip(chunk_a).zip(chunk_b) {
+            *sum += x * y;
+        }
+    }
+
+    sums.iter().sum::<f32>() + tail
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use futures::future::BoxFuture;
+    use tempfile::TempDir;
+
+    // Embeds text as letter frequencies, so tests need no Ollama. The 0.1
+    // floor keeps the vectors from looking like old placeholders.
+    #[derive(Debug)]
+    struct StubEmbedder;
+
+    impl EmbeddingProvider for StubEmbedder {
+        fn get_embedding<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Vec<f32>>> {
+            Box::pin(async move { Ok(letter_counts(text)) })
+        }
+
+        fn batch_embed<'a>(&'a self, texts: &'a [&'a str]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+            Box::pin(async move { Ok(texts.iter().map(|text| letter_counts(text)).collect()) })
+        }
+    }
+
+    fn letter_counts(text: &str) -> Vec<f32> {
+        let mut counts = vec![0.1; 26];
+        for c in text.chars().filter(char::is_ascii_alphabetic) {
+            counts[(c.to_ascii_lowercase() as u8 - b'a') as usize] += 1.0;
+        }
+        counts
+    }
+
+    /// A fresh library in its own directory, removed when the `TempDir`
+    /// is dropped.
+    pub(crate) async fn test_db() -> (Database, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let mut db = Database::new(&test_db_url(&dir), &DatabaseConfig::default()).await.unwrap();
+        db.embedder = Arc::new(StubEmbedder);
+        (db, dir)
+    }
+
+    pub(crate) fn test_db_url(dir: &TempDir) -> String {
+        format!("sqlite://{}?mode=rwc", dir.path().join("clipsage.db").display())
+    }
+
+    pub(crate) fn test_clip(content: &str) -> ClipItem {
+        let now = Utc::now();
+        ClipItem {
+            id: Uuid::new_v4().to_string(),
+            content: content.to_string(),
+            summary: String::new(),
+            tags: Vec::new(),
+            created_at: now,
+            last_modified: now,
+            source: None,
+            embedding: None,
+            urls: Vec::new(),
+            embed_truncated: false,
+            content_type: ContentType::Text,
+            source_url: None,
+            rewrite_history: Vec::new(),
+            masked: false,
+            keywords: Vec::new(),
+            metadata: ClipMetadata::default(),
+            seq: None,
+            code_language: None,
+        }
+    }
+
+    fn ids_of(clips: &[ClipItem]) -> Vec<&str> {
+        clips.iter().map(|clip| clip.id.as_str()).collect()
+    }
+
+    async fn assert_index_in_sync(db: &Database) {
+        let report = db.verify_fts_sync().await.unwrap();
+        assert!(report.missing_from_fts.is_empty(), "{:?}", report);
+        assert!(report.extra_in_fts.is_empty(), "{:?}", report);
+        assert!(report.content_mismatches.is_empty(), "{:?}", report);
+    }
+
+    #[tokio::test]
+    async fn new_creates_a_library_that_reopens_cleanly() {
+        let (db, dir) = test_db().await;
+        assert!(db.get_recent_clips(10).await.unwrap().is_empty());
+        db.insert_clip(&test_clip("kept across restarts")).await.unwrap();
+        db.pool.close().await;
+
+        let reopened = Database::new(&test_db_url(&dir), &DatabaseConfig::default()).await.unwrap();
+        let clips = reopened.get_recent_clips(10).await.unwrap();
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].content, "kept across restarts");
+        assert_index_in_sync(&reopened).await;
+    }
+
+    #[tokio::test]
+    async fn inserted_clip_round_trips_through_get_recent_clips() {
+        let (db, _dir) = test_db().await;
+        let mut clip = test_clip("fn main() {}");
+        clip.summary = "Empty Rust program".to_string();
+        clip.tags = vec!["code".to_string(), "rust".to_string()];
+        clip.source = Some("editor".to_string());
+        db.insert_clip(&clip).await.unwrap();
+
+        let clips = db.get_recent_clips(10).await.unwrap();
+        assert_eq!(clips.len(), 1);
+        let stored = &clips[0];
+        assert_eq!(stored.id, clip.id);
+        assert_eq!(stored.content, clip.content);
+        assert_eq!(stored.summary, clip.summary);
+        assert_eq!(stored.tags, clip.tags);
+        assert_eq!(stored.source, clip.source);
+        assert_eq!(stored.created_at.timestamp(), clip.created_at.timestamp());
+        assert_eq!(stored.embedding, Some(letter_counts(&clip.content)));
+        assert_eq!(stored.seq, Some(1));
+    }
+
+    #[tokio::test]
+    async fn get_recent_clips_pages_newest_first() {
+        let (db, _dir) = test_db().await;
+        let now = Utc::now();
+        let mut inserted = Vec::new();
+        for age in 0..5 {
+            let mut clip = test_clip(&format!("clip number {}", age));
+            clip.created_at = now - chrono::Duration::minutes(age);
+            db.insert_clip(&clip).await.unwrap();
+            inserted.push(clip.id);
+        }
+
+        let recent = db.get_recent_clips(3).await.unwrap();
+        assert_eq!(ids_of(&recent), [inserted[0].as_str(), inserted[1].as_str(), inserted[2].as_str()]);
+
+        let page = db.list_clips(ClipTableSort::Timestamp, true, 2, 2).await.unwrap();
+        assert_eq!(page.total, 5);
+        let page_ids: Vec<&str> = page.rows.iter().map(|row| row.clip.id.as_str()).collect();
+        assert_eq!(page_ids, [inserted[2].as_str(), inserted[3].as_str()]);
+    }
+
+    #[tokio::test]
+    async fn search_clips_ranks_full_text_matches_first() {
+        let (db, _dir) = test_db().await;
+        let fox = test_clip("the quick brown fox jumps over the lazy dog");
+        let lorem = test_clip("lorem ipsum dolor sit amet");
+        db.insert_clip(&fox).await.unwrap();
+        db.insert_clip(&lorem).await.unwrap();
+
+        let results = db.search_clips("fox", 10).await.unwrap();
+        assert_eq!(results.first().map(|clip| clip.id.as_str()), Some(fox.id.as_str()));
+        assert!(db.text_search_items("fox", 10, 0).await.unwrap().iter().all(|clip| clip.id == fox.id));
+    }
+
+    // Inserts one clip per embedding, returning their ids in order
+    async fn insert_embedded(db: &Database, embeddings: &[&[f32]]) -> Vec<String> {
+        let mut ids = Vec::new();
+        for (i, embedding) in embeddings.iter().enumerate() {
+            let mut clip = test_clip(&format!("embedded clip {}", i));
+            clip.embedding = Some(embedding.to_vec());
+            db.insert_clip(&clip).await.unwrap();
+            ids.push(clip.id);
+        }
+        ids
+    }
+
+    #[tokio::test]
+    async fn text_search_total_counts_every_match() {
+        let (db, _dir) = test_db().await;
+        for content in ["kiwi jam", "kiwi tart", "sliced kiwi", "kiwi kiwi kiwi", "banana bread"] {
+            db.insert_clip(&test_clip(content)).await.unwrap();
+        }
+
+        let found = db.text_search_with_total("kiwi", 2).await.unwrap();
+        assert_eq!(found.results.len(), 2);
+        assert_eq!(found.total, 4);
+        assert_eq!(db.text_search_with_total("mango", 2).await.unwrap().total, 0);
+    }
+
+    #[tokio::test]
+    async fn semantic_search_total_counts_clips_above_the_threshold() {
+        let (db, _dir) = test_db().await;
+        let ids = insert_embedded(&db, &[&[1.0, 0.0], &[0.9, 0.1], &[0.7, 0.7], &[0.0, 1.0]]).await;
+
+        let found = db.semantic_search_with_total(&[1.0, 0.0], 1, 0.8).await.unwrap();
+        assert_eq!(found.total, 2);
+        assert_eq!(ids_of(&found.results), [ids[0].as_str()]);
+        assert_eq!(db.semantic_search_with_total(&[1.0, 0.0], 10, -1.0).await.unwrap().total, 4);
+    }
+
+    // A clip that counts how many of its kind are alive, and the most at
+    // any one time
+    struct Held<'a> {
+        clip: ClipItem,
+        live: &'a std::cell::Cell<(usize, usize)>,
+    }
+
+    impl<'a> Held<'a> {
+        fn new(clip: ClipItem, live: &'a std::cell::Cell<(usize, usize)>) -> Self {
+            let (now, peak) = live.get();
+            live.set((now + 1, peak.max(now + 1)));
+            Held { clip, live }
+        }
+    }
+
+    impl Serialize for Held<'_> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            self.clip.serialize(serializer)
+        }
+    }
+
+    impl Drop for Held<'_> {
+        fn drop(&mut self) {
+            let (now, peak) = self.live.get();
+            self.live.set((now - 1, peak));
+        }
+    }
+
+    #[tokio::test]
+    async fn export_streams_instead_of_loading_every_clip() {
+        const CLIPS: usize = 500;
+        const CLIP_BYTES: usize = 1024;
+
+        let (db, dir) = test_db().await;
+        let now = Utc::now();
+        let clips: Vec<ClipItem> = (0..CLIPS)
+            .map(|i| {
+                let mut clip = test_clip(&format!("{:05}{}", i, "x".repeat(CLIP_BYTES)));
+                clip.created_at = now - chrono::Duration::seconds(i as i64);
+                // Masked clips skip full-text indexing, which keeps this fast
+                clip.masked = true;
+                clip.embedding = Some(vec![0.5; 4]);
+                clip
+            })
+            .collect();
+        for batch in clips.chunks(250) {
+            db.batch_insert_clips(batch).await.unwrap();
+        }
+        drop(clips);
+
+        let live = std::cell::Cell::new((0, 0));
+        let held = db.stream_clips().map_ok(|clip| Held::new(clip, &live));
+        let mut written = Vec::new();
+        assert_eq!(write_json_array(&mut written, held).await.unwrap(), CLIPS);
+        assert_eq!(live.get(), (0, 1));
+        assert_eq!(serde_json::from_slice::<Vec<ClipItem>>(&written).unwrap().len(), CLIPS);
+
+        let path = dir.path().join("export.json");
+        assert_eq!(db.export_clips_json(&path).await.unwrap(), CLIPS);
+        assert!(std::fs::metadata(&path).unwrap().len() > (CLIPS * CLIP_BYTES) as u64);
+
+        let mut streamed = 0;
+        let mut stream = db.stream_clips();
+        while let Some(clip) = stream.try_next().await.unwrap() {
+            assert!(clip.content.starts_with(&format!("{:05}", streamed)));
+            streamed += 1;
+        }
+        assert_eq!(streamed, CLIPS);
+    }
+
+    async fn exported_ids(db: &Database, filter: &ExportFilter) -> Vec<String> {
+        let json = db.export_filtered(filter).await.unwrap();
+        let clips: Vec<ClipItem> = serde_json::from_str(&json).unwrap();
+        clips.into_iter().map(|clip| clip.id).collect()
+    }
+
+    #[tokio::test]
+    async fn export_filtered_combines_filters() {
+        let (db, _dir) = test_db().await;
+        let now = Utc::now();
+        let mut ids = Vec::new();
+        for (tag, days_ago, content_type, source) in [
+            ("rust", 1, ContentType::Code, "editor"),
+            ("rust", 10, ContentType::Code, "editor"),
+            ("python", 1, ContentType::Code, "terminal"),
+            ("rust", 2, ContentType::Text, "browser"),
+        ] {
+            let mut clip = test_clip("exported snippet");
+            clip.tags = vec![tag.to_string()];
+            clip.created_at = now - chrono::Duration::days(days_ago);
+            clip.content_type = content_type;
+            clip.source = Some(source.to_string());
+            db.insert_clip(&clip).await.unwrap();
+            ids.push(clip.id);
+        }
+
+        let recent_rust = ExportFilter {
+            tag: Some("rust".to_string()),
+            since: Some(now - chrono::Duration::days(3)),
+            ..ExportFilter::default()
+        };
+        assert_eq!(exported_ids(&db, &recent_rust).await, [ids[0].clone(), ids[3].clone()]);
+
+        let editor_code = ExportFilter {
+            content_type: Some(ContentType::Code),
+            source: Some("editor".to_string()),
+            ..ExportFilter::default()
+        };
+        assert_eq!(exported_ids(&db, &editor_code).await, [ids[0].clone(), ids[1].clone()]);
+
+        let nothing = ExportFilter {
+            tag: Some("python".to_string()),
+            until: Some(now - chrono::Duration::days(5)),
+            ..ExportFilter::default()
+        };
+        assert!(exported_ids(&db, &nothing).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn semantic_search_orders_by_stored_embeddings() {
+        let (db, _dir) = test_db().await;
+        let mut clips = Vec::new();
+        for (content, embedding) in [
+            ("north", vec![1.0, 0.0, 0.0]),
+            ("east", vec![0.0, 1.0, 0.0]),
+            ("north by east", vec![0.8, 0.2, 0.0]),
+        ] {
+            let mut clip = test_clip(content);
+            clip.embedding = Some(embedding);
+            db.insert_clip(&clip).await.unwrap();
+            clips.push(clip.id);
+        }
+
+        let results = db.semantic_search(&[1.0, 0.0, 0.0], 2).await.unwrap();
+        assert_eq!(ids_of(&results), [clips[0].as_str(), clips[2].as_str()]);
+    }
+
+    #[tokio::test]
+    async fn over_long_clips_are_searchable_to_the_end() {
+        let (mut db, _dir) = test_db().await;
+        let mut config = db.config().clone();
+        config.embedding_max_chars = 100;
+        db.set_config(config).await.unwrap();
+
+        let content = format!("{} needle", "hay ".repeat(500));
+        let clip = test_clip(&content);
+        db.insert_clip(&clip).await.unwrap();
+
+        assert_eq!(ids_of(&db.text_search_items("needle", 10, 0).await.unwrap()), [clip.id.as_str()]);
+        let stored = db.get_clip(&clip.id).await.unwrap().unwrap();
+        assert!(stored.embed_truncated);
+        assert_eq!(stored.embedding, Some(letter_counts(&content[..100])));
+    }
+
+    #[tokio::test]
+    async fn over_long_clips_can_skip_embedding() {
+        let (mut db, _dir) = test_db().await;
+        let mut config = db.config().clone();
+        config.embedding_max_chars = 100;
+        config.oversized_embedding = OversizedEmbedding::Skip;
+        db.set_config(config).await.unwrap();
+
+        let clip = test_clip(&format!("{} needle", "hay ".repeat(500)));
+        db.insert_clip(&clip).await.unwrap();
+
+        assert_eq!(ids_of(&db.text_search_items("needle", 10, 0).await.unwrap()), [clip.id.as_str()]);
+        let stored = db.get_clip(&clip.id).await.unwrap().unwrap();
+        assert!(stored.embed_truncated);
+        assert_eq!(stored.embedding, None);
+    }
+
+    #[tokio::test]
+    async fn each_similarity_metric_ranks_by_its_own_measure() {
+        let (mut db, _dir) = test_db().await;
+        // Long and off-axis, short and on-axis, close to the query
+        let ids = insert_embedded(&db, &[&[10.0, 10.0], &[0.2, 0.0], &[1.0, 0.5]]).await;
+        let (long, short, close) = (ids[0].as_str(), ids[1].as_str(), ids[2].as_str());
+
+        for (metric, expected) in [
+            (SimilarityMetric::Cosine, [short, close, long]),
+            (SimilarityMetric::DotProduct, [long, close, short]),
+            (SimilarityMetric::Euclidean, [close, short, long]),
+        ] {
+            let mut config = db.config().clone();
+            config.similarity_metric = metric;
+            db.set_config(config).await.unwrap();
+
+            let results = db.semantic_search(&[1.0, 0.0], 3).await.unwrap();
+            assert_eq!(ids_of(&results), expected, "{:?}", metric);
+        }
+    }
+
+    #[tokio::test]
+    async fn near_duplicates_pair_only_similar_clips() {
+        let (db, _dir) = test_db().await;
+        let ids = insert_embedded(&db, &[&[1.0, 0.0, 0.0], &[0.99, 0.05, 0.0], &[0.0, 0.0, 1.0]]).await;
+
+        let pairs = db.find_near_duplicates(0.95).await.unwrap();
+        assert_eq!(pairs.len(), 1);
+        let (a, b, similarity) = &pairs[0];
+        let mut pair = [a.as_str(), b.as_str()];
+        pair.sort();
+        let mut expected = [ids[0].as_str(), ids[1].as_str()];
+        expected.sort();
+        assert_eq!(pair, expected);
+        assert!(*similarity > 0.99);
+
+        assert!(db.find_near_duplicates(0.9999).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn delete_clip_removes_it_from_the_index() {
+        let (db, _dir) = test_db().await;
+        let doomed = test_clip("ephemeral note about zeppelins");
+        let survivor = test_clip("permanent note about zeppelins");
+        db.insert_clip(&doomed).await.unwrap();
+        db.insert_clip(&survivor).await.unwrap();
+
+        db.delete_clip(&doomed.id).await.unwrap();
+
+        assert!(db.get_clip(&doomed.id).await.unwrap().is_none());
+        assert_eq!(ids_of(&db.text_search_items("zeppelins", 10, 0).await.unwrap()), [survivor.id.as_str()]);
+        assert!(db.text_search_items("ephemeral", 10, 0).await.unwrap().is_empty());
+        assert_index_in_sync(&db).await;
+    }
+
+    #[tokio::test]
+    async fn update_clip_reindexes_the_new_content() {
+        let (db, _dir) = test_db().await;
+        let clip = test_clip("original wording with aardvark");
+        db.insert_clip(&clip).await.unwrap();
+
+        let mut edited = db.get_clip(&clip.id).await.unwrap().unwrap();
+        edited.content = "rewritten wording with okapi".to_string();
+        edited.summary = "About an okapi".to_string();
+        db.update_clip(&edited).await.unwrap();
+
+        assert!(db.text_search_items("aardvark", 10, 0).await.unwrap().is_empty());
+        assert_eq!(ids_of(&db.text_search_items("okapi", 10, 0).await.unwrap()), [clip.id.as_str()]);
+        assert_index_in_sync(&db).await;
+
+        // A second edit must remove what the first one indexed
+        edited.content = "final wording with quokka".to_string();
+        db.update_clip(&edited).await.unwrap();
+        assert!(db.text_search_items("rewritten", 10, 0).await.unwrap().is_empty());
+        assert_index_in_sync(&db).await;
+    }
+
+    #[tokio::test]
+    async fn identical_content_is_recognized_by_hash() {
+        let (db, _dir) = test_db().await;
+        db.insert_hash_only_clip("hunter2", None).await.unwrap();
+        assert!(db.is_latest_clip("hunter2").await.unwrap());
+        assert!(!db.is_latest_clip("hunter3").await.unwrap());
+
+        let first = test_clip("copied twice");
+        let mut second = test_clip("copied twice");
+        second.created_at = first.created_at + chrono::Duration::seconds(1);
+        db.insert_clip(&first).await.unwrap();
+        db.insert_clip(&second).await.unwrap();
+        db.insert_hash_only_clip("hunter2", None).await.unwrap();
+
+        // The two full copies merge, as do the two hash-only rows
+        assert_eq!(db.merge_duplicates().await.unwrap(), 2);
+        assert!(db.get_clip(&first.id).await.unwrap().is_some());
+        assert!(db.get_clip(&second.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn merge_tags_replaces_every_spelling() {
+        let (db, _dir) = test_db().await;
+        let mut clips = Vec::new();
+        for tags in [&["javascript", "web"][..], &["JavaScript"][..], &["js", "javascript"][..], &["python"][..]] {
+            let mut clip = test_clip("a snippet from the frontend");
+            clip.tags = tags.iter().map(|tag| tag.to_string()).collect();
+            db.insert_clip(&clip).await.unwrap();
+            clips.push(clip.id);
+        }
+        assert_eq!(db.text_search_items("javascript", 10, 0).await.unwrap().len(), 3);
+
+        assert_eq!(db.merge_tags(&["javascript", "JavaScript", "js"], "JS").await.unwrap(), 3);
+
+        let mut tags = Vec::new();
+        for id in &clips {
+            tags.push(db.get_clip(id).await.unwrap().unwrap().tags);
+        }
+        assert_eq!(tags, [vec!["JS", "web"], vec!["JS"], vec!["JS"], vec!["python"]]);
+        assert!(db.text_search_items("javascript", 10, 0).await.unwrap().is_empty());
+        assert_index_in_sync(&db).await;
+    }
+
+    #[tokio::test]
+    async fn pruning_spares_exempt_tags_and_collections() {
+        let (mut db, _dir) = test_db().await;
+        let mut config = db.config().clone();
+        config.retention_exempt_tags = vec!["keep".to_string()];
+        config.retention_exempt_collections = vec!["Saved".to_string()];
+        db.set_config(config).await.unwrap();
+
+        let two_days_ago = Utc::now() - chrono::Duration::days(2);
+        let mut clips = Vec::new();
+        for tags in [vec!["Keep"], vec![], vec!["other"]] {
+            let mut clip = test_clip("output from an old terminal session");
+            clip.source = Some("terminal".to_string());
+            clip.created_at = two_days_ago;
+            clip.tags = tags.into_iter().map(String::from).collect();
+            db.insert_clip(&clip).await.unwrap();
+            clips.push(clip.id);
+        }
+        let saved = db.create_collection("saved").await.unwrap();
+        db.add_clips_to_collection(&saved.id, &clips[1..2]).await.unwrap();
+
+        assert_eq!(db.prune_clips_from_source("terminal", 24).await.unwrap(), 1);
+        assert!(db.get_clip(&clips[0]).await.unwrap().is_some());
+        assert!(db.get_clip(&clips[1]).await.unwrap().is_some());
+        assert!(db.get_clip(&clips[2]).await.unwrap().is_none());
+    }
+
+    fn clip_ref_error(result: Result<String>) -> ClipRefError {
+        result.unwrap_err().downcast::<ClipRefError>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn resolve_clip_id_accepts_ids_and_short_numbers() {
+        let (db, _dir) = test_db().await;
+        let first = test_clip("first clip");
+        let second = test_clip("second clip");
+        db.insert_clip(&first).await.unwrap();
+        db.insert_clip(&second).await.unwrap();
+
+        assert_eq!(db.resolve_clip_id(&first.id).await.unwrap(), first.id);
+        assert_eq!(db.resolve_clip_id("#2").await.unwrap(), second.id);
+        assert_eq!(db.resolve_clip_id(" 1 ").await.unwrap(), first.id);
+        let resolved = db.resolve_clip_ids(&["2".to_string(), second.id.clone()]).await.unwrap();
+        assert_eq!(resolved, [second.id.clone(), second.id.clone()]);
+
+        assert_eq!(clip_ref_error(db.resolve_clip_id("#3").await), ClipRefError::NotFound("#3".to_string()));
+        assert_eq!(clip_ref_error(db.resolve_clip_id("3").await), ClipRefError::NotFound("3".to_string()));
+    }
+
+    #[tokio::test]
+    async fn resolve_clip_id_reports_numeric_ids_that_collide() {
+        let (db, _dir) = test_db().await;
+        let numbered = test_clip("gets short number 1");
+        let mut imported = test_clip("imported with a numeric id");
+        imported.id = "1".to_string();
+        db.insert_clip(&numbered).await.unwrap();
+        db.insert_clip(&imported).await.unwrap();
+
+        assert_eq!(clip_ref_error(db.resolve_clip_id("1").await), ClipRefError::Ambiguous("1".to_string()));
+        assert_eq!(db.resolve_clip_id("#1").await.unwrap(), numbered.id);
+        // Short number 2 belongs to the imported clip, so no ambiguity
+        assert_eq!(db.resolve_clip_id("2").await.unwrap(), "1");
+    }
+
+    #[tokio::test]
+    async fn short_numbers_are_not_reused_after_a_wipe() {
+        let (mut db, _dir) = test_db().await;
+        db.insert_clip(&test_clip("before the wipe")).await.unwrap();
+        db.insert_clip(&test_clip("also before the wipe")).await.unwrap();
+        db.wipe_all_data(true).await.unwrap();
+
+        let after = test_clip("after the wipe");
+        db.insert_clip(&after).await.unwrap();
+
+        assert_eq!(db.get_clip(&after.id).await.unwrap().unwrap().seq, Some(3));
+        assert_eq!(clip_ref_error(db.resolve_clip_id("#1").await), ClipRefError::NotFound("#1".to_string()));
+        assert_eq!(db.resolve_clip_id("#3").await.unwrap(), after.id);
+    }
+
+    #[tokio::test]
+    async fn read_only_libraries_still_split_sentences() {
+        let (db, dir) = test_db().await;
+        let clip = test_clip("First sentence. Second one.");
+        db.insert_clip(&clip).await.unwrap();
+
+        let backup = db
+            .open_read_only(&dir.path().join("clipsage.db"), &DatabaseConfig::default())
+            .await
+            .unwrap();
+        let sentences = backup.get_clip_sentences(&clip.id).await.unwrap();
+
+        assert_eq!(sentences.len(), 2);
+        let cached: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clip_sentences WHERE clip_id = ?")
+            .bind(&clip.id)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(cached, 0);
+    }
+
+    #[tokio::test]
+    async fn wiping_forgets_the_embedding_calibration() {
+        let (mut db, dir) = test_db().await;
+        let calibration = EmbeddingCalibration {
+            model: db.ollama.model().to_string(),
+            expected_dim: 5,
+            calibrated_at: Utc::now(),
+        };
+        sqlx::query("INSERT INTO settings (key, value) VALUES ('embedding_calibration', ?)")
+            .bind(serde_json::to_string(&calibration).unwrap())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        db.calibration = Some(calibration);
+
+        db.wipe_all_data(true).await.unwrap();
+
+        assert!(db.embedding_calibration().is_none());
+        let reopened = Database::new(&test_db_url(&dir), &DatabaseConfig::default()).await.unwrap();
+        assert!(reopened.embedding_calibration().is_none());
+    }
+
+    async fn access_times(db: &Database, clip_id: &str) -> Vec<String> {
+        sqlx::query_scalar("SELECT accessed_at FROM clip_accesses WHERE clip_id = ? ORDER BY accessed_at DESC")
+            .bind(clip_id)
+            .fetch_all(&db.pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn prune_access_log_keeps_the_newest_accesses_per_clip() {
+        let (db, _dir) = test_db().await;
+        let busy = test_clip("opened all the time");
+        let quiet = test_clip("opened now and then");
+        db.insert_clip(&busy).await.unwrap();
+        db.insert_clip(&quiet).await.unwrap();
+
+        let now = Utc::now();
+        let mut busy_times = Vec::new();
+        for minutes_ago in 0..50 {
+            let accessed_at = (now - chrono::Duration::minutes(minutes_ago)).to_rfc3339();
+            sqlx::query("INSERT INTO clip_accesses (clip_id, accessed_at) VALUES (?, ?)")
+                .bind(&busy.id)
+                .bind(&accessed_at)
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            busy_times.push(accessed_at);
+        }
+        for _ in 0..5 {
+            db.record_access(&quiet.id).await.unwrap();
+        }
+
+        assert_eq!(db.prune_access_log(10).await.unwrap(), 40);
+        assert_eq!(access_times(&db, &busy.id).await, busy_times[..10]);
+        assert_eq!(access_times(&db, &quiet.id).await.len(), 5);
+        assert_eq!(db.prune_access_log(10).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn split_clip_by_lines_makes_one_clip_per_line() {
+        let (db, _dir) = test_db().await;
+        let parent = test_clip("https://a.com\nhttps://b.org\n\nok\nhttps://c.net\n  https://d.io  \nhttps://e.dev");
+        db.insert_clip(&parent).await.unwrap();
+
+        let children = db.split_clip_by_lines(&parent.id, 4).await.unwrap();
+
+        let contents: Vec<&str> = children.iter().map(|clip| clip.content.as_str()).collect();
+        assert_eq!(contents, ["https://a.com", "https://b.org", "https://c.net", "https://d.io", "https://e.dev"]);
+        let source = format!("split:{}", parent.id);
+        for child in &children {
+            assert_eq!(child.source.as_deref(), Some(source.as_str()));
+            assert!(db.get_clip(&child.id).await.unwrap().is_some());
+        }
+        assert!(db.get_clip(&parent.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn merge_clips_into_new_joins_in_the_given_order() {
+        let (db, _dir) = test_db().await;
+        let first = test_clip("first part");
+        let second = test_clip("second part");
+        let third = test_clip("third part");
+        for clip in [&first, &second, &third] {
+            db.insert_clip(clip).await.unwrap();
+        }
+
+        let ids = vec![third.id.clone(), first.id.clone(), second.id.clone()];
+        let merged = db.merge_clips_into_new(&ids, "\n---\n", false).await.unwrap();
+
+        assert_eq!(merged.content, "third part\n---\nfirst part\n---\nsecond part");
+        assert!(merged.tags.iter().any(|tag| tag == MERGED_TAG));
+        assert_eq!(merged.metadata.extra.get("merged_from"), Some(&serde_json::json!(ids)));
+        assert_eq!(merged.embedding, Some(letter_counts(&merged.content)));
+        for id in &ids {
+            assert!(db.get_clip(id).await.unwrap().is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn merge_clips_into_new_can_delete_the_sources() {
+        let (db, _dir) = test_db().await;
+        let first = test_clip("keep this line");
+        let second = test_clip("and this one");
+        db.insert_clip(&first).await.unwrap();
+        db.insert_clip(&second).await.unwrap();
+
+        let ids = vec![first.id.clone(), second.id.clone()];
+        let merged = db.merge_clips_into_new(&ids, " ", true).await.unwrap();
+
+        assert_eq!(merged.content, "keep this line and this one");
+        assert!(db.get_clip(&first.id).await.unwrap().is_none());
+        assert!(db.get_clip(&second.id).await.unwrap().is_none());
+        let remaining = db.get_recent_clips(10).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, merged.id);
+    }
+
+    fn clip_at(content: &str, created_at: &str) -> ClipItem {
+        let mut clip = test_clip(content);
+        clip.created_at = created_at.parse().unwrap();
+        clip.last_modified = clip.created_at;
+        clip
+    }
+
+    fn labels_and_dates(groups: &[ClipGroup]) -> Vec<(&str, &str, usize)> {
+        groups
+            .iter()
+            .map(|group| (group.label.as_str(), group.date.as_str(), group.clips.len()))
+            .collect()
+    }
+
+    #[test]
+    fn local_midnight_splits_days_by_the_client_offset() {
+        let now: DateTime<Utc> = "2026-10-16T12:00:00Z".parse().unwrap();
+        let clips = [
+            clip_at("after midnight", "2026-10-15T22:00:00Z"),
+            clip_at("before midnight", "2026-10-15T21:59:59Z"),
+            clip_at("last week", "2026-10-12T09:00:00Z"),
+            clip_at("long ago", "2026-10-01T09:00:00Z"),
+        ];
+
+        // UTC+2: 22:00 UTC is local midnight on the 16th
+        let groups = group_by_local_day(&clips, now, 120, 100);
+        assert_eq!(
+            labels_and_dates(&groups),
+            [
+                ("Today", "2026-10-16", 1),
+                ("Yesterday", "2026-10-15", 1),
+                ("Monday", "2026-10-12", 1),
+                ("2026-10-01", "2026-10-01", 1),
+            ]
+        );
+
+        // In UTC both late clips fall on the 15th
+        let groups = group_by_local_day(&clips, now, 0, 100);
+        assert_eq!(groups[0].label, "Yesterday");
+        assert_eq!(groups[0].clips.len(), 2);
+    }
+
+    #[test]
+    fn grouping_across_a_dst_change_uses_the_current_offset() {
+        // US clocks sprang forward at 2026-03-08 07:00 UTC; the client now
+        // reports EDT (UTC-4)
+        let now: DateTime<Utc> = "2026-03-09T15:00:00Z".parse().unwrap();
+        let clips = [
+            clip_at("just after midnight EDT", "2026-03-08T04:30:00Z"),
+            clip_at("just before midnight EDT", "2026-03-08T03:59:00Z"),
+        ];
+
+        let groups = group_by_local_day(&clips, now, -240, 100);
+        assert_eq!(
+            labels_and_dates(&groups),
+            [("Yesterday", "2026-03-08", 1), ("Saturday", "2026-03-07", 1)]
+        );
+
+        // The same clips under EST (UTC-5) both land on the 7th
+        let groups = group_by_local_day(&clips, now, -300, 100);
+        assert_eq!(labels_and_dates(&groups), [("Saturday", "2026-03-07", 2)]);
+    }
+
+    #[tokio::test]
+    async fn grouped_pages_continue_a_split_day() {
+        let (db, _dir) = test_db().await;
+        for clip in [
+            clip_at("first", "2020-01-01T12:00:00Z"),
+            clip_at("second", "2020-01-01T11:00:00Z"),
+            clip_at("third", "2020-01-01T10:00:00Z"),
+            clip_at("fourth", "2019-12-31T12:00:00Z"),
+        ] {
+            db.insert_clip(&clip).await.unwrap();
+        }
+
+        let first = db.get_recent_clips_grouped(2, 0, None).await.unwrap();
+        assert_eq!(labels_and_dates(&first.groups), [("2020-01-01", "2020-01-01", 2)]);
+        let cursor = first.next_cursor.expect("a full page has a cursor");
+
+        let second = db.get_recent_clips_grouped(2, 0, Some(&cursor)).await.unwrap();
+        assert_eq!(
+            labels_and_dates(&second.groups),
+            [("2020-01-01", "2020-01-01", 1), ("2019-12-31", "2019-12-31", 1)]
+        );
+        let previews: Vec<&str> = second
+            .groups
+            .iter()
+            .flat_map(|group| group.clips.iter().map(|clip| clip.preview.as_str()))
+            .collect();
+        assert_eq!(previews, ["third", "fourth"]);
+
+        let cursor = second.next_cursor.unwrap();
+        let last = db.get_recent_clips_grouped(2, 0, Some(&cursor)).await.unwrap();
+        assert!(last.groups.is_empty());
+        assert!(last.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn porter_tokenizer_stems_where_unicode61_does_not() {
+        let (mut db, _dir) = test_db().await;
+        let clip = test_clip("she was running late again");
+        db.insert_clip(&clip).await.unwrap();
+
+        assert_eq!(db.config().fts_tokenizer, crate::config::FtsTokenizer::Unicode61);
+        assert!(db.text_search_items("run", 10, 0).await.unwrap().is_empty());
+
+        let mut config = db.config().clone();
+        config.fts_tokenizer = crate::config::FtsTokenizer::Porter;
+        db.set_config(config).await.unwrap();
+
+        let found = db.text_search_items("run", 10, 0).await.unwrap();
+        assert_eq!(ids_of(&found), [clip.id.as_str()]);
+        assert_index_in_sync(&db).await;
+    }
+
+    #[tokio::test]
+    async fn clip_timeline_diffs_successive_versions() {
+        let (db, _dir) = test_db().await;
+        for clip in [
+            clip_at("fn greet() {\n    println!(\"hi\");\n}", "2026-01-01T10:00:00Z"),
+            clip_at("unrelated note", "2026-01-01T10:30:00Z"),
+            clip_at("fn greet() {\n    println!(\"hello\");\n}", "2026-01-01T11:00:00Z"),
+            clip_at("fn greet() {\n    log();\n    println!(\"hello\");\n}", "2026-01-01T12:00:00Z"),
+        ] {
+            db.insert_clip(&clip).await.unwrap();
+        }
+
+        let timeline = db.get_clip_timeline("fn greet").await.unwrap();
+        let diffs: Vec<&LineDiff> = timeline.iter().map(|entry| &entry.diff).collect();
+        assert_eq!(
+            diffs,
+            [
+                &LineDiff {
+                    added: vec!["fn greet() {".into(), "    println!(\"hi\");".into(), "}".into()],
+                    removed: vec![],
+                },
+                &LineDiff { added: vec!["    println!(\"hello\");".into()], removed: vec!["    println!(\"hi\");".into()] },
+                &LineDiff { added: vec!["    log();".into()], removed: vec![] },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn collections_are_created_only_for_frequent_tags() {
+        let (db, _dir) = test_db().await;
+        let mut rust_ids = Vec::new();
+        for (tag, count) in [("rust", 10), ("python", 2)] {
+            for n in 0..count {
+                let mut clip = test_clip(&format!("{} snippet {}", tag, n));
+                clip.tags = vec![tag.to_string()];
+                db.insert_clip(&clip).await.unwrap();
+                if tag == "rust" {
+                    rust_ids.push(clip.id);
+                }
+            }
+        }
+        let existing = db.list_collections().await.unwrap().len();
+
+        let planned = db.auto_create_collections_from_tags(5, true).await.unwrap();
+        let names: Vec<&str> = planned.iter().map(|collection| collection.name.as_str()).collect();
+        assert_eq!(names, ["rust"]);
+        assert_eq!(db.list_collections().await.unwrap().len(), existing);
+
+        let created = db.auto_create_collections_from_tags(5, false).await.unwrap();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].name, "rust");
+        assert_eq!(db.list_collections().await.unwrap().len(), existing + 1);
+
+        let mut members = db.get_collection_clip_ids(&created[0].id).await.unwrap();
+        members.sort();
+        rust_ids.sort();
+        assert_eq!(members, rust_ids);
+
+        // A collection named after the tag now exists, so nothing is redone
+        assert!(db.auto_create_collections_from_tags(5, false).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn delete_clips_removes_only_the_listed_clips() {
+        let (db, _dir) = test_db().await;
+        let clips = [test_clip("one"), test_clip("two"), test_clip("three")];
+        for clip in &clips {
+            db.insert_clip(clip).await.unwrap();
+        }
+
+        let ids = vec![clips[0].id.clone(), clips[2].id.clone(), Uuid::new_v4().to_string()];
+        assert_eq!(db.delete_clips(&ids).await.unwrap(), 2);
+
+        assert_eq!(ids_of(&db.get_recent_clips(10).await.unwrap()), [clips[1].id.as_str()]);
+        assert_index_in_sync(&db).await;
+    }
+
+    #[tokio::test]
+    async fn delete_clips_with_no_ids_does_nothing() {
+        let (db, _dir) = test_db().await;
+        db.insert_clip(&test_clip("kept")).await.unwrap();
+
+        assert_eq!(db.delete_clips(&[]).await.unwrap(), 0);
+        assert_eq!(clip_count(&db).await, 1);
+    }
+
+    #[tokio::test]
+    async fn delete_clips_chunks_lists_past_the_variable_limit() {
+        let (db, _dir) = test_db().await;
+        let clips: Vec<ClipItem> = (0..DELETE_CHUNK_SIZE * 2 + 200)
+            .map(|n| test_clip(&format!("bulk clip {}", n)))
+            .collect();
+        db.batch_insert_clips(&clips).await.unwrap();
+        let survivor = test_clip("survivor");
+        db.insert_clip(&survivor).await.unwrap();
+
+        let mut ids: Vec<String> = clips.iter().map(|clip| clip.id.clone()).collect();
+        ids.extend((0..300).map(|_| Uuid::new_v4().to_string()));
+        assert!(ids.len() > 999);
+
+        assert_eq!(db.delete_clips(&ids).await.unwrap(), clips.len());
+        assert_eq!(ids_of(&db.get_recent_clips(10).await.unwrap()), [survivor.id.as_str()]);
+        assert_index_in_sync(&db).await;
+    }
+
+    #[tokio::test]
+    async fn collection_scoped_search_skips_clips_outside_the_collections() {
+        let (db, _dir) = test_db().await;
+        let mut inside = Vec::new();
+        let mut scope = Vec::new();
+        for name in ["Work", "Research", "Personal"] {
+            let collection = db.create_collection(name).await.unwrap();
+            let clip = test_clip(&format!("quarterly report for {}", name));
+            db.insert_clip(&clip).await.unwrap();
+            db.add_clips_to_collection(&collection.id, &[clip.id.clone()]).await.unwrap();
+            if name != "Personal" {
+                inside.push(clip.id);
+                scope.push(collection.id);
+            }
+        }
+        db.insert_clip(&test_clip("quarterly report without a collection")).await.unwrap();
+        inside.sort();
+        let scope: Vec<&str> = scope.iter().map(String::as_str).collect();
+
+        let found = db.search_in_collections("report", &scope, 10).await.unwrap();
+        let mut found: Vec<String> = found.into_iter().map(|clip| clip.id).collect();
+        found.sort();
+        assert_eq!(found, inside);
+
+        let query = db.embed_text("quarterly report").await.unwrap();
+        let similar = db.semantic_search_in_collections(&query, &scope, 0.0, 10).await.unwrap();
+        let mut similar: Vec<String> = similar.into_iter().map(|clip| clip.id).collect();
+        similar.sort();
+        assert_eq!(similar, inside);
+    }
+
+    #[tokio::test]
+    async fn auto_categorize_files_urls_under_links() {
+        let (mut db, _dir) = test_db().await;
+        let mut config = db.config().clone();
+        config.auto_categorize = true;
+        db.set_config(config).await.unwrap();
+
+        let mut clip = test_clip("https://example.com/docs");
+        clip.tags = detect_tags(&clip.content, &crate::urls::extract_urls(&clip.content));
+        clip.content_type = ContentType::detect(&clip.content, &clip.tags);
+        assert_eq!(clip.content_type, ContentType::Url);
+        db.insert_clip(&clip).await.unwrap();
+        assert_eq!(collections_of(&db, &clip.id).await, ["Links"]);
+
+        // Re-detecting after an edit moves it to the matching collection
+        clip.content = "see the docs page".to_string();
+        clip.tags.clear();
+        clip.content_type = ContentType::detect(&clip.content, &clip.tags);
+        db.update_clip(&clip).await.unwrap();
+        assert_eq!(collections_of(&db, &clip.id).await, ["Notes"]);
+    }
+
+    #[tokio::test]
+    async fn semantic_search_vector_returns_the_nearest_clip() {
+        let (db, _dir) = test_db().await;
+        let ids = insert_embedded(&db, &[&[1.0, 0.0, 0.0][..], &[0.0, 1.0, 0.0][..], &[0.0, 0.0, 1.0][..]]).await;
+
+        let found = db.semantic_search_vector(&[0.1, 0.9, 0.2], 1).await.unwrap();
+        assert_eq!(ids_of(&found), [ids[1].as_str()]);
+
+        let error = db.semantic_search_vector(&[1.0, 0.0], 1).await.unwrap_err();
+        assert!(error.to_string().contains("2 dimensions but stored embeddings have 3"), "{}", error);
+    }
+
+    async fn count_with_content(db: &Database, content: &str) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM clips WHERE content = ?")
+            .bind(content)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn import_with_dedupe_collapses_overlapping_clips() {
+        let (laptop, laptop_dir) = test_db().await;
+        for content in ["shared note", "from the laptop"] {
+            laptop.insert_clip(&test_clip(content)).await.unwrap();
+        }
+        let export_path = laptop_dir.path().join("export.json");
+        laptop.full_export_to(&export_path, |_| {}).await.unwrap();
+
+        let (mut db, _dir) = test_db().await;
+        for content in ["shared note", "shared note", "only here"] {
+            db.insert_clip(&test_clip(content)).await.unwrap();
+        }
+
+        let report = db.full_import(&export_path, false, true, |_| {}).await.unwrap();
+        assert_eq!(report.clips_imported, 1);
+        assert_eq!(report.duplicate_clips, 1);
+        assert_eq!(report.duplicates_merged, 1);
+
+        assert_eq!(count_with_content(&db, "shared note").await, 1);
+        assert_eq!(clip_count(&db).await, 3);
+        assert_index_in_sync(&db).await;
+    }
+
+    // Inserts the same text twice with different tags, merges under
+    // `policy`, and returns the older copy and the clip that survived
+    async fn merge_tagged_copies(policy: DuplicateTagPolicy) -> (ClipItem, ClipItem) {
+        let (mut db, _dir) = test_db().await;
+        let mut config = db.config().clone();
+        config.duplicate_tag_policy = policy;
+        db.set_config(config).await.unwrap();
+
+        let mut older = clip_at("same words", "2026-01-01T09:00:00Z");
+        older.tags = vec!["draft".to_string(), "work".to_string()];
+        let mut newer = clip_at("same words", "2026-01-02T09:00:00Z");
+        newer.tags = vec!["work".to_string(), "final".to_string()];
+        newer.source = Some("terminal".to_string());
+        for clip in [&older, &newer] {
+            db.insert_clip(clip).await.unwrap();
+        }
+
+        assert_eq!(db.merge_duplicates().await.unwrap(), 1);
+        let remaining = db.get_recent_clips(10).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        (older, remaining.into_iter().next().unwrap())
+    }
+
+    #[tokio::test]
+    async fn merged_duplicates_take_the_union_of_tags() {
+        let (older, kept) = merge_tagged_copies(DuplicateTagPolicy::Union).await;
+        assert_eq!(kept.id, older.id);
+        assert_eq!(kept.created_at, older.created_at);
+        assert_eq!(kept.tags, ["draft", "work", "final"]);
+        assert_eq!(kept.source.as_deref(), Some("terminal"));
+    }
+
+    #[tokio::test]
+    async fn merged_duplicates_can_keep_only_the_latest_tags() {
+        let (older, kept) = merge_tagged_copies(DuplicateTagPolicy::LastWins).await;
+        assert_eq!(kept.id, older.id);
+        assert_eq!(kept.tags, ["work", "final"]);
+        assert_eq!(kept.source.as_deref(), Some("terminal"));
+    }
+
+    #[tokio::test]
+    async fn list_clips_sorts_by_each_key_and_pages_with_offset() {
+        let (db, _dir) = test_db().await;
+        let mut ids = Vec::new();
+        for (content, created_at, uses, source) in [
+            ("aaaaaaaa", "2026-01-01T09:00:00Z", 0, "zsh"),
+            ("bb", "2026-01-02T09:00:00Z", 2, "browser"),
+            ("cccc", "2026-01-03T09:00:00Z", 1, "mail"),
+        ] {
+            let mut clip = clip_at(content, created_at);
+            clip.source = Some(source.to_string());
+            db.insert_clip(&clip).await.unwrap();
+            for _ in 0..uses {
+                db.record_access(&clip.id).await.unwrap();
+            }
+            ids.push(clip.id);
+        }
+        let [a, b, c] = [ids[0].as_str(), ids[1].as_str(), ids[2].as_str()];
+
+        for (sort_by, ascending) in [
+            (ClipTableSort::Timestamp, [a, b, c]),
+            (ClipTableSort::ContentLength, [b, c, a]),
+            (ClipTableSort::UseCount, [a, c, b]),
+            (ClipTableSort::Source, [b, c, a]),
+        ] {
+            let page = db.list_clips(sort_by, false, 10, 0).await.unwrap();
+            let order: Vec<&str> = page.rows.iter().map(|row| row.clip.id.as_str()).collect();
+            assert_eq!(order, ascending, "{:?}", sort_by);
+            assert_eq!(page.total, 3);
+
+            let page = db.list_clips(sort_by, true, 2, 1).await.unwrap();
+            let order: Vec<&str> = page.rows.iter().map(|row| row.clip.id.as_str()).collect();
+            assert_eq!(order, [ascending[1], ascending[0]], "{:?} descending", sort_by);
+            assert_eq!(page.total, 3);
+        }
+
+        let uses: Vec<u64> = db
+            .list_clips(ClipTableSort::UseCount, false, 10, 0)
+            .await
+            .unwrap()
+            .rows
+            .iter()
+            .map(|row| row.use_count)
+            .collect();
+        assert_eq!(uses, [0, 1, 2]);
+    }
+
+    #[test]
+    fn unknown_sort_keys_are_rejected() {
+        assert_eq!(serde_json::from_str::<ClipTableSort>("\"use_count\"").unwrap(), ClipTableSort::UseCount);
+        assert!(serde_json::from_str::<ClipTableSort>("\"content; DROP TABLE clips\"").is_err());
+    }
+
+    #[tokio::test]
+    async fn semantic_search_scores_only_the_current_models_vectors() {
+        let (db, _dir) = test_db().await;
+        let current = test_clip("apple pie recipe");
+        let other = test_clip("apple pie recipe, again");
+        for clip in [&current, &other] {
+            db.insert_clip(clip).await.unwrap();
+        }
+        sqlx::query("UPDATE clips SET embed_model = 'some-older-model' WHERE id = ?")
+            .bind(&other.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let query = db.embed_text("apple pie").await.unwrap();
+        let found = db.semantic_search(&query, 10).await.unwrap();
+        assert_eq!(ids_of(&found), [current.id.as_str()]);
+
+        let stale = db.get_clips_needing_reindex(10).await.unwrap();
+        assert_eq!(ids_of(&stale), [other.id.as_str()]);
+    }
+
+    #[tokio::test]
+    async fn text_search_pages_report_whether_more_follow() {
+        let (db, _dir) = test_db().await;
+        for content in ["mango lassi", "mango chutney", "dried mango", "plain yogurt"] {
+            db.insert_clip(&test_clip(content)).await.unwrap();
+        }
+
+        let all = db.text_search("mango", 10, 0).await.unwrap();
+        assert_eq!((all.items.len(), all.total_count, all.has_next), (3, 3, false));
+
+        let first = db.text_search("mango", 2, 0).await.unwrap();
+        assert_eq!((first.items.len(), first.total_count, first.has_next), (2, 3, true));
+        let last = db.text_search("mango", 2, 2).await.unwrap();
+        assert_eq!((last.items.len(), last.has_next), (1, false));
+
+        // A similarity bar nothing can reach leaves only the text matches
+        let hybrid = db.search_clips_page("mango", 2, 0, 2.0).await.unwrap();
+        assert_eq!((hybrid.total_count, hybrid.has_next), (3, true));
+        let hybrid = db.search_clips_page("mango", 3, 0, 2.0).await.unwrap();
+        assert!(!hybrid.has_next);
+    }
+
+    fn activity_counts(activity: &[SourceActivity]) -> Vec<(&str, i64)> {
+        activity.iter().map(|row| (row.source.as_str(), row.clip_count)).collect()
+    }
+
+    #[tokio::test]
+    async fn source_activity_counts_each_source_within_the_window() {
+        let (db, _dir) = test_db().await;
+        let today_start = local_window_start(1, 0);
+        let yesterday = today_start - chrono::Duration::hours(12);
+        let two_days_ago = today_start - chrono::Duration::hours(36);
+
+        for (source, created_at) in [
+            ("terminal", Utc::now()),
+            ("browser", Utc::now()),
+            ("terminal", yesterday),
+            ("browser", two_days_ago),
+        ] {
+            let mut clip = test_clip(&format!("copied from {}", source));
+            clip.source = Some(source.to_string());
+            clip.created_at = created_at;
+            db.insert_clip(&clip).await.unwrap();
+        }
+
+        let two_days = db.get_source_activity(2, 0).await.unwrap();
+        assert_eq!(activity_counts(&two_days), [("terminal", 2), ("browser", 1)]);
+
+        let today = db.get_source_activity(1, 0).await.unwrap();
+        assert_eq!(activity_counts(&today), [("browser", 1), ("terminal", 1)]);
+
+        let three_days = db.get_source_activity(3, 0).await.unwrap();
+        assert_eq!(activity_counts(&three_days), [("browser", 2), ("terminal", 2)]);
+    }
+
+    async fn clip_count(db: &Database) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM clips").fetch_one(&db.pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn alfred_import_reads_text_entries_from_its_database() {
+        let (db, dir) = test_db().await;
+        db.insert_clip(&test_clip("already here")).await.unwrap();
+
+        let alfred_path = dir.path().join("clipboard.alfdb");
+        let options = SqliteConnectOptions::new().filename(&alfred_path).create_if_missing(true);
+        let mut alfred = SqliteConnection::connect_with(&options).await.unwrap();
+        sqlx::query(
+            "CREATE TABLE clipboard (item TEXT, ts DECIMAL, app TEXT, apppath TEXT, dataType INTEGER, dataHash TEXT, lastUsedDate REAL)",
+        )
+        .execute(&mut alfred)
+        .await
+        .unwrap();
+        for (item, app, data_type, last_used) in [
+            (Some("alfred snippet one"), Some("Safari"), 0, 700_000_000.5),
+            (None, Some("Preview"), 1, 700_000_100.0),
+            (Some("already here"), Some("Notes"), 0, 700_000_200.0),
+            (Some("no app recorded"), None, 0, 700_000_300.0),
+        ] {
+            sqlx::query("INSERT INTO clipboard (item, app, dataType, dataHash, lastUsedDate) VALUES (?, ?, ?, ?, ?)")
+                .bind(item)
+                .bind(app)
+                .bind(data_type)
+                .bind(item.map(sha256_hex))
+                .bind(last_used)
+                .execute(&mut alfred)
+                .await
+                .unwrap();
+        }
+        alfred.close().await.unwrap();
+
+        let report = db.import_from_alfred(&alfred_path).await.unwrap();
+        assert_eq!((report.clips_imported, report.duplicates_skipped, report.non_text_skipped), (2, 1, 1));
+
+        let imported = db.text_search_items("snippet", 10, 0).await.unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].content, "alfred snippet one");
+        assert_eq!(imported[0].source.as_deref(), Some("Safari"));
+        assert_eq!(imported[0].created_at.to_rfc3339(), "2023-03-08T20:26:40.500+00:00");
+
+        let no_app = db.text_search_items("recorded", 10, 0).await.unwrap();
+        assert_eq!(no_app[0].source.as_deref(), Some("alfred"));
+        assert_eq!(clip_count(&db).await, 3);
+    }
+
+    async fn use_ollama_at(db: &mut Database, url: &str) {
+        let mut config = db.config().clone();
+        config.ollama_url = url.to_string();
+        db.set_config(config).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn calibration_records_the_dimension_ollama_returns() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/version"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "version": "0.5.0" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/embeddings"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "embedding": [0.1, 0.2, 0.3, 0.4, 0.5] })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let (mut db, dir) = test_db().await;
+        use_ollama_at(&mut db, &server.uri()).await;
+        assert!(db.embedding_calibration().is_none());
+
+        let calibration = db.calibrate_embeddings().await.unwrap().expect("Ollama is up");
+        assert_eq!(calibration.expected_dim, 5);
+        assert_eq!(db.embedding_calibration().map(|c| c.expected_dim), Some(5));
+
+        let reopened = Database::new(&test_db_url(&dir), &DatabaseConfig::default()).await.unwrap();
+        assert_eq!(reopened.embedding_calibration().map(|c| c.expected_dim), Some(5));
+    }
+
+    #[tokio::test]
+    async fn calibration_waits_while_ollama_is_down() {
+        let (mut db, _dir) = test_db().await;
+        // Nothing listens on the discard port
+        use_ollama_at(&mut db, "http://127.0.0.1:9").await;
+
+        assert!(db.calibrate_embeddings().await.unwrap().is_none());
+        assert!(db.embedding_calibration().is_none());
+    }
+
+    async fn collections_of(db: &Database, clip_id: &str) -> Vec<String> {
+        sqlx::query_scalar(
+            r#"
+            SELECT c.name FROM collections c
+            JOIN clip_collections cc ON cc.collection_id = c.id
+            WHERE cc.clip_id = ?
+            ORDER BY c.name
+            "#,
+        )
+        .bind(clip_id)
+        .fetch_all(&db.pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn clips_can_be_added_to_or_moved_into_a_collection() {
+        let (db, _dir) = test_db().await;
+        let inbox = db.create_collection("Inbox").await.unwrap();
+        let target = db.create_collection("Projects").await.unwrap();
+        let kept = test_clip("stays in the inbox too");
+        let moved = test_clip("leaves the inbox");
+        for clip in [&kept, &moved] {
+            db.insert_clip(clip).await.unwrap();
+            db.add_clips_to_collection(&inbox.id, &[clip.id.clone()]).await.unwrap();
+        }
+
+        db.move_clips_to_collection(&[kept.id.clone()], &target.id, true).await.unwrap();
+        assert_eq!(collections_of(&db, &kept.id).await, ["Inbox", "Projects"]);
+
+        db.move_clips_to_collection(&[moved.id.clone()], &target.id, false).await.unwrap();
+        assert_eq!(collections_of(&db, &moved.id).await, ["Projects"]);
+    }
+
+    #[tokio::test]
+    async fn moving_into_a_missing_collection_changes_nothing() {
+        let (db, _dir) = test_db().await;
+        let inbox = db.create_collection("Inbox").await.unwrap();
+        let clip = test_clip("filed once");
+        db.insert_clip(&clip).await.unwrap();
+        db.add_clips_to_collection(&inbox.id, &[clip.id.clone()]).await.unwrap();
+
+        let error = db.move_clips_to_collection(&[clip.id.clone()], "no-such-collection", false).await.unwrap_err();
+        assert!(error.to_string().contains("Collection not found"), "{}", error);
+        assert_eq!(collections_of(&db, &clip.id).await, ["Inbox"]);
+    }
+
+    #[tokio::test]
+    async fn fuzzy_dedup_matches_reformatted_copies_only() {
+        let (mut db, _dir) = test_db().await;
+        let original = test_clip("Meeting moved to Thursday, 3pm.");
+        db.insert_clip(&original).await.unwrap();
+        assert_eq!(db.find_fuzzy_duplicate("meeting moved to thursday 3pm").await.unwrap(), None);
+
+        let mut config = db.config().clone();
+        config.fuzzy_dedup = true;
+        db.set_config(config).await.unwrap();
+
+        let found = db.find_fuzzy_duplicate("  MEETING moved to Thursday -- 3pm!").await.unwrap();
+        assert_eq!(found.as_deref(), Some(original.id.as_str()));
+        assert_eq!(db.find_fuzzy_duplicate("Invoice 4471 is overdue, please pay by Friday").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn merged_duplicates_hand_their_rows_to_the_keeper() {
+        let (db, _dir) = test_db().await;
+        let older = clip_at("same words", "2026-01-01T09:00:00Z");
+        let newer = clip_at("same words", "2026-01-02T09:00:00Z");
+        for clip in [&older, &newer] {
+            db.insert_clip(clip).await.unwrap();
+        }
+        let collection = db.create_collection("Filed").await.unwrap();
+        db.assign_slot(3, &newer.id).await.unwrap();
+        db.add_global_pin(&newer.id).await.unwrap();
+        let reminder = db.set_clip_reminder(&newer.id, Utc::now() + chrono::Duration::days(1)).await.unwrap();
+        sqlx::query("INSERT INTO auto_filings (clip_id, collection_id, confidence, filed_at) VALUES (?, ?, 0.9, ?)")
+            .bind(&newer.id)
+            .bind(&collection.id)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        assert_eq!(db.merge_duplicates().await.unwrap(), 1);
+
+        let slots = db.get_slots().await.unwrap();
+        assert_eq!(slots.iter().map(|slot| (slot.slot, slot.clip.id.as_str())).collect::<Vec<_>>(), [(3, older.id.as_str())]);
+        assert_eq!(ids_of(&db.get_global_pins().await.unwrap()), [older.id.as_str()]);
+        let reminders = db.list_reminders().await.unwrap();
+        assert_eq!(reminders.len(), 1);
+        assert_eq!((reminders[0].id.as_str(), reminders[0].clip_id.as_str()), (reminder.id.as_str(), older.id.as_str()));
+        let filed: Vec<String> = sqlx::query_scalar("SELECT clip_id FROM auto_filings").fetch_all(&db.pool).await.unwrap();
+        assert_eq!(filed, [older.id.clone()]);
+
+        let audit = db.get_audit_log(Some(&newer.id), 10, 0).await.unwrap();
+        let deletion = audit.iter().find(|entry| entry.operation == "delete").expect("the duplicate's deletion is audited");
+        assert_eq!(deletion.details, Some(serde_json::json!({ "merged_into": older.id })));
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tauri::Emitter;
+use crate::DbState;
+
+/// Emitted with `DedupCompleted` after every run, scheduled or manual
+pub const DEDUP_COMPLETED_EVENT: &str = "dedup_completed";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DedupCompleted {
+    pub clips_removed: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DedupJobStatus {
+    pub last_run: Option<DateTime<Utc>>,
+    pub clips_removed_last_run: u64,
+    pub is_running: bool,
+    pub paused: bool,
+}
+
+/// Runs `Database::deduplicate_similar` every `interval`. Clones share
+/// state, so the copy in app state sees what the scheduled loop does.
+#[derive(Clone)]
+pub struct ClipDedupJob {
+    db: DbState,
+    interval: Duration,
+    threshold: f32,
+    last_run: Arc<Mutex<Option<DateTime<Utc>>>>,
+    clips_removed_last_run: Arc<AtomicU64>,
+    running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+}
+
+impl ClipDedupJob {
+    pub fn new(db: DbState, interval: Duration, threshold: f32, paused: bool) -> Self {
+        Self {
+            db,
+            interval,
+            threshold,
+            last_run: Arc::new(Mutex::new(None)),
+            clips_removed_last_run: Arc::new(AtomicU64::new(0)),
+            running: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(paused)),
+        }
+    }
+
+    pub fn status(&self) -> DedupJobStatus {
+        DedupJobStatus {
+            last_run: *self.last_run.lock().unwrap(),
+            clips_removed_last_run: self.clips_removed_last_run.load(Ordering::Relaxed),
+            is_running: self.running.load(Ordering::Relaxed),
+            paused: self.paused.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Stops scheduled runs; `run_now` still works.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// Runs one deduplication pass and emits `dedup_completed`. Fails if a
+    /// pass is already running or the database is open read-only.
+    pub async fn run_now(&self, app: &tauri::AppHandle) -> Result<u64> {
+        if self.running.swap(true, Ordering::Relaxed) {
+            return Err(anyhow!("Deduplication is already running"));
+        }
+
+        let result = async {
+            let db = self.db.lock().await;
+            db.ensure_writable()?;
+            db.deduplicate_similar(self.threshold).await
+        }
+        .await;
+        self.running.store(false, Ordering::Relaxed);
+
+        let clips_removed = result?;
+        *self.last_run.lock().unwrap() = Some(Utc::now());
+        self.clips_removed_last_run.store(clips_removed, Ordering::Relaxed);
+        let _ = app.emit(DEDUP_COMPLETED_EVENT, DedupCompleted { clips_removed });
+        Ok(clips_removed)
+    }
+
+    /// The scheduled loop. Skips a round while paused or while a backup is
+    /// browsed read-only.
+    pub async fn run(self, app: tauri::AppHandle) {
+        loop {
+            tokio::time::sleep(self.interval).await;
+
+            if self.paused.load(Ordering::Relaxed) || self.db.lock().await.is_read_only() {
+                continue;
+            }
+            match self.run_now(&app).await {
+                Ok(removed) => println!("Deduplication merged {} similar clips", removed),
+                Err(e) => eprintln!("Deduplication failed: {}", e),
+            }
+        }
+    }
+}
+
+/// The scheduled loop's task, aborted when the app exits.
+#[derive(Default)]
+pub struct DedupJobHandle(pub Mutex<Option<tauri::async_runtime::JoinHandle<()>>>);
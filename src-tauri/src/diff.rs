@@ -0,0 +1,145 @@
+use std::ops::Range;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, DiffTag, TextDiff};
+
+// Bytes of each side compared by `diff_texts`; anything after is ignored
+const MAX_DIFF_BYTES: usize = 64 * 1024;
+
+// Unchanged lines shown around each hunk of the unified diff
+const UNIFIED_CONTEXT_LINES: usize = 3;
+
+/// Lines present only in the newer text (`added`) or only in the older one
+/// (`removed`), each in the order they appear.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LineDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Line diff of `old` against `new`.
+pub fn line_diff(old: &str, new: &str) -> LineDiff {
+    let mut diff = LineDiff::default();
+    for change in TextDiff::from_lines(old, new).iter_all_changes() {
+        let line = change.value().trim_end_matches(['\r', '\n']).to_string();
+        match change.tag() {
+            ChangeTag::Insert => diff.added.push(line),
+            ChangeTag::Delete => diff.removed.push(line),
+            ChangeTag::Equal => {}
+        }
+    }
+    diff
+}
+
+/// Granularity of `diff_texts`. Ranges in the hunks count units of this size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffMode {
+    Line,
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HunkOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A run of lines (or words) with the same fate. A replacement shows up as
+/// a delete followed by an insert.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffHunk {
+    pub op: HunkOp,
+    pub old_range: Range<usize>,
+    pub new_range: Range<usize>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextDiffResult {
+    pub hunks: Vec<DiffHunk>,
+    /// Line-based unified diff, ready to copy
+    pub unified: String,
+    /// Either side was longer than the compared prefix
+    pub truncated: bool,
+}
+
+/// Diffs the first `MAX_DIFF_BYTES` of each text.
+pub fn diff_texts(old: &str, new: &str, mode: DiffMode, old_label: &str, new_label: &str) -> TextDiffResult {
+    let (old, old_truncated) = truncate_bytes(old, MAX_DIFF_BYTES);
+    let (new, new_truncated) = truncate_bytes(new, MAX_DIFF_BYTES);
+
+    let diff = match mode {
+        DiffMode::Line => TextDiff::from_lines(old, new),
+        DiffMode::Word => TextDiff::from_words(old, new),
+    };
+
+    let mut hunks = Vec::new();
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let old_text = || diff.old_slices()[old_range.clone()].concat();
+        let new_text = || diff.new_slices()[new_range.clone()].concat();
+        let empty_at = |range: &Range<usize>| range.start..range.start;
+
+        match tag {
+            DiffTag::Equal => hunks.push(DiffHunk {
+                op: HunkOp::Equal,
+                old_range: old_range.clone(),
+                new_range: new_range.clone(),
+                text: old_text(),
+            }),
+            DiffTag::Delete => hunks.push(DiffHunk {
+                op: HunkOp::Delete,
+                old_range: old_range.clone(),
+                new_range: new_range.clone(),
+                text: old_text(),
+            }),
+            DiffTag::Insert => hunks.push(DiffHunk {
+                op: HunkOp::Insert,
+                old_range: old_range.clone(),
+                new_range: new_range.clone(),
+                text: new_text(),
+            }),
+            DiffTag::Replace => {
+                hunks.push(DiffHunk {
+                    op: HunkOp::Delete,
+                    old_range: old_range.clone(),
+                    new_range: empty_at(&new_range),
+                    text: old_text(),
+                });
+                hunks.push(DiffHunk {
+                    op: HunkOp::Insert,
+                    old_range: empty_at(&old_range),
+                    new_range: new_range.clone(),
+                    text: new_text(),
+                });
+            }
+        }
+    }
+
+    let unified = TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(UNIFIED_CONTEXT_LINES)
+        .header(old_label, new_label)
+        .to_string();
+
+    TextDiffResult {
+        hunks,
+        unified,
+        truncated: old_truncated || new_truncated,
+    }
+}
+
+// Longest prefix of `text` within `max_bytes` that ends on a char boundary
+fn truncate_bytes(text: &str, max_bytes: usize) -> (&str, bool) {
+    if text.len() <= max_bytes {
+        return (text, false);
+    }
+
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    (&text[..end], true)
+}
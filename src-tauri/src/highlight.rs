@@ -0,0 +1,194 @@
+use std::sync::OnceLock;
+use anyhow::Result;
+use serde::Serialize;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+const THEME: &str = "InspiredGitHub";
+
+// Telltale snippets per language, checked when the first line (shebang,
+// `<?php`, ...) doesn't identify it. Names are syntect's syntax names.
+const LANGUAGE_HINTS: &[(&str, &[&str])] = &[
+    ("Rust", &["fn ", "let mut ", "impl ", "pub fn", "::new(", "&str", "match "]),
+    ("Python", &["def ", "import ", "self.", "elif ", "print(", "__init__"]),
+    ("JavaScript", &["function ", "const ", "=> ", "console.log", "require(", "document."]),
+    ("Go", &["func ", "package ", ":= ", "fmt."]),
+    ("Java", &["public class", "public static", "System.out", "private final"]),
+    ("C++", &["#include <iostream>", "std::", "template<", "cout <<"]),
+    ("C", &["#include <", "printf(", "int main(", "malloc("]),
+    ("SQL", &["SELECT ", "INSERT INTO", "CREATE TABLE", " WHERE ", "JOIN "]),
+    ("HTML", &["<html", "<div", "</", "<body"]),
+    ("CSS", &["{\n", "px;", "color:", "margin:"]),
+    ("Bourne Again Shell (bash)", &["echo ", "$1", "fi\n", "export "]),
+    ("Ruby", &["def ", "end\n", "puts ", ".each do"]),
+];
+
+// Hints a language needs, and must have more of than any other, before
+// `detect_language` names it. Highlighting settles for any hint.
+const MIN_LANGUAGE_HINTS: usize = 2;
+
+/// Stored as a clip's language when `detect_language` can't tell.
+pub const UNKNOWN_LANGUAGE: &str = "unknown";
+
+/// A clip rendered for display. `language` is the detected language for
+/// code clips and `None` for everything else.
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderedClip {
+    pub html: String,
+    pub language: Option<String>,
+}
+
+/// Syntax-highlights `content` as HTML with inline styles, guessing its
+/// language. Falls back to escaped plain text when no language matches.
+pub fn render_code(content: &str) -> Result<RenderedClip> {
+    let syntaxes = syntax_set();
+    let Some(syntax) = guess_syntax(syntaxes, content) else {
+        return Ok(render_plain(content));
+    };
+
+    Ok(RenderedClip {
+        html: highlighted_html_for_string(content, syntaxes, syntax, theme())?,
+        language: Some(syntax.name.clone()),
+    })
+}
+
+/// `content` escaped inside a `<pre>`, unhighlighted.
+pub fn render_plain(content: &str) -> RenderedClip {
+    RenderedClip {
+        html: format!("<pre>{}</pre>", escape_html(content)),
+        language: None,
+    }
+}
+
+/// The language of `content` as a syntect syntax name, when its first line
+/// identifies it or the hints clearly favor one language. `None` for
+/// snippets that could be several languages, or none.
+pub fn detect_language(content: &str) -> Option<String> {
+    if let Some(syntax) = syntax_set().find_syntax_by_first_line(content) {
+        return Some(syntax.name.clone());
+    }
+
+    match hint_scores(content)[..] {
+        [(name, hits), (_, runner_up), ..] if hits >= MIN_LANGUAGE_HINTS && hits > runner_up => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+fn guess_syntax<'a>(syntaxes: &'a SyntaxSet, content: &str) -> Option<&'a SyntaxReference> {
+    if let Some(syntax) = syntaxes.find_syntax_by_first_line(content) {
+        return Some(syntax);
+    }
+
+    match hint_scores(content).first() {
+        Some((name, hits)) if *hits > 0 => syntaxes.find_syntax_by_name(name),
+        _ => None,
+    }
+}
+
+// How many of each language's hints `content` contains, most first. The
+// sort is stable, so ties keep `LANGUAGE_HINTS` order.
+fn hint_scores(content: &str) -> Vec<(&'static str, usize)> {
+    let mut scores: Vec<(&'static str, usize)> = LANGUAGE_HINTS
+        .iter()
+        .map(|(name, hints)| (*name, hints.iter().filter(|hint| content.contains(*hint)).count()))
+        .collect();
+    scores.sort_by(|a, b| b.1.cmp(&a.1));
+    scores
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Loading syntect's bundled definitions takes a while, so it's done once
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+    &THEME_SET.get_or_init(ThemeSet::load_defaults).themes[THEME]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST_SNIPPET: &str = "pub fn parse(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for word in input.split(' ') {
+        words.push(word.to_string());
+    }
+    words
+}
+";
+
+    #[test]
+    fn detects_clear_rust() {
+        assert_eq!(detect_language(RUST_SNIPPET).as_deref(), Some("Rust"));
+    }
+
+    #[test]
+    fn detects_clear_python() {
+        let snippet = "import os
+
+class Greeter:
+    def __init__(self, name):
+        self.name = name
+
+    def greet(self):
+        print(os.getcwd(), self.name)
+";
+        assert_eq!(detect_language(snippet).as_deref(), Some("Python"));
+    }
+
+    #[test]
+    fn detects_language_from_a_shebang() {
+        assert_eq!(detect_language("#!/usr/bin/env python3\nx = 1\n").as_deref(), Some("Python"));
+    }
+
+    #[test]
+    fn ambiguous_snippets_have_no_language() {
+        // As much Ruby as Python
+        assert_eq!(detect_language("def main(): print(x)\nend\n"), None);
+        // A single hint isn't enough
+        assert_eq!(detect_language("def setup"), None);
+        assert_eq!(detect_language("x = 1"), None);
+    }
+
+    #[test]
+    fn rust_snippet_is_highlighted_as_rust() {
+        let rendered = render_code(RUST_SNIPPET).unwrap();
+        assert_eq!(rendered.language.as_deref(), Some("Rust"));
+        assert!(rendered.html.contains("<span style="), "{}", rendered.html);
+        assert!(rendered.html.contains("parse"));
+        assert!(!rendered.html.contains("&str)"), "content should be escaped: {}", rendered.html);
+    }
+
+    #[test]
+    fn prose_is_rendered_plain() {
+        let rendered = render_code("Remember to buy milk & eggs").unwrap();
+        assert_eq!(rendered.language, None);
+        assert_eq!(rendered.html, "<pre>Remember to buy milk &amp; eggs</pre>");
+    }
+
+    #[test]
+    fn plain_rendering_escapes_markup() {
+        let rendered = render_plain("<script>alert('hi')</script>");
+        assert_eq!(rendered.html, "<pre>&lt;script&gt;alert(&#39;hi&#39;)&lt;/script&gt;</pre>");
+        assert_eq!(rendered.language, None);
+    }
+}
@@ -1,119 +1,2390 @@
 use std::sync::Arc;
 use std::time::Duration;
-use tauri::{Manager, State};
+use tauri::{Emitter, Manager, State};
 use tokio::sync::Mutex;
-use arboard::Clipboard;
-use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Datelike, Utc};
 
+mod browser;
+mod capture;
+mod citations;
+mod clipboard;
+mod config;
+mod config_file;
 mod database;
+mod dedup_job;
+mod diff;
+mod highlight;
+mod lock;
+mod metrics;
+mod notify;
 mod ollama;
-use database::{Database, ClipItem};
+mod pending;
+mod readonly;
+mod recovery;
+mod retention;
+mod simhash;
+mod slots;
+mod terms;
+mod urls;
+mod wipe;
+use clipboard::{ClipboardSource, ClipboardText, LastCapture, MonitorPause, MonitoringStatus, Selection, SelfWriteState, SystemClipboard};
+use lock::{AppLock, LockStatus};
+use metrics::{MetricsSnapshot, Operation, SearchTimings, StageLatency};
+use capture::{CapturePipeline, CapturePreview};
+use highlight::RenderedClip;
+use config::{AppCaptureMode, AppConfig};
+use config_file::{ConfigLayers, EffectiveConfig};
+use dedup_job::{ClipDedupJob, DedupJobHandle, DedupJobStatus};
+use diff::{DiffMode, TextDiffResult};
+use ollama::{Entity, OllamaClient, OllamaError};
+use pending::{PendingCapture, PendingNotice, CAPTURE_CANCELED_EVENT, CAPTURE_PENDING_EVENT};
+use readonly::{ReadOnlyMode, ReadOnlyStatus};
+use recovery::RecoveryReport;
+use slots::SlotShortcuts;
+use tauri_plugin_global_shortcut::ShortcutState;
+use retention::{PruneReport, RetentionRule};
+use terms::TermCount;
+use wipe::{WipeReport, WipeToken};
+use database::{Database, DatabaseConfig, EmbeddingCalibration, ClipMetadata, AuditEntry, AutoFiling, CitedAnswer, ClipCursor, ClipItem, ClipPreview, ClipShare, ClipTablePage, ClipTableSort, Collection, CollectionOverlap, CollectionRule, CollectionRuleType, ContentType, DailyCount, ExportFilter, FtsSyncReport, FullImportReport, GroupedClips, ImportReport, IndexReport, IndexRepairReport, MonthlyCount, Page, Reminder, SearchResults, SemanticHit, SentenceSpan, ShareConfig, SlotAssignment, SortField, SourceActivity, SourceStats, SplitMode, SummaryScope, TagHeatmapPoint, TagSuggestion, TaggedClipGroup, TimelineEntry};
 
 type DbState = Arc<Mutex<Database>>;
 
+/// Outcome of the startup recovery, if one was needed.
+#[derive(Default)]
+struct RecoveryState(std::sync::Mutex<Option<RecoveryReport>>);
+
+/// The last home feed and when it was built, keyed by its tag and clip
+/// counts. Reused for `HOME_FEED_TTL`.
+#[derive(Default)]
+struct HomeFeedCache(std::sync::Mutex<Option<(DateTime<Utc>, (u32, u32), Vec<TaggedClipGroup>)>>);
+
+/// Why the database couldn't be opened at all, even after recovery.
+#[derive(Default)]
+struct StartupError(std::sync::Mutex<Option<String>>);
+
+#[derive(serde::Serialize)]
+struct AppStatus {
+    database_ready: bool,
+    /// Set when `database_ready` will never become true this run
+    database_error: Option<String>,
+    recovery: Option<RecoveryReport>,
+    lock: LockStatus,
+    /// Set while a backup is open with `open_database_readonly`
+    read_only: Option<ReadOnlyStatus>,
+}
+
+const SEMANTIC_MIN_SIMILARITY: f32 = 0.5;
+const NEAR_DUPLICATE_THRESHOLD: f32 = 0.95;
+const ACCESS_LOG_KEEP_PER_CLIP: u32 = 20;
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const CHUNK_WORKER_INTERVAL: Duration = Duration::from_secs(30);
+const CHUNK_WORKER_BATCH: i32 = 20;
+const CONFIG_FILE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const COLLECTION_DESCRIBER_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const HOME_FEED_TTL: Duration = Duration::from_secs(30);
+const REMINDER_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+// Emitted with a `ClipPreview` (including `size_bytes`) for captures over
+// the configured warning size
+const LARGE_CLIP_EVENT: &str = "large-clip-captured";
+
+// Emitted with `TagsChanged` after tags are renamed or merged
+const TAGS_CHANGED_EVENT: &str = "tags://changed";
+
+// Emitted with the new `AppConfig` after edits to clipsage.toml are applied
+const CONFIG_RELOADED_EVENT: &str = "config://reloaded";
+
+#[tauri::command]
+fn greet(name: &str) -> String {
+    format!("Hello, {}! Welcome to ClipSage!", name)
+}
+
+#[tauri::command]
+async fn hide_window(window: tauri::Window, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.window_hidden();
+    window.hide().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn show_window(window: tauri::Window) -> Result<(), String> {
+    window.show().map_err(|e| e.to_string())?;
+    window.set_focus().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_app_status(
+    app: tauri::AppHandle,
+    recovery: State<'_, RecoveryState>,
+    startup_error: State<'_, StartupError>,
+    lock: State<'_, AppLock>,
+    read_only: State<'_, ReadOnlyMode>,
+) -> AppStatus {
+    AppStatus {
+        database_ready: app.try_state::<DbState>().is_some(),
+        database_error: startup_error.0.lock().unwrap().clone(),
+        recovery: recovery.0.lock().unwrap().clone(),
+        lock: lock.status(),
+        read_only: read_only.status(),
+    }
+}
+
+#[tauri::command]
+fn monitoring_status(pause: State<'_, MonitorPause>) -> MonitoringStatus {
+    MonitoringStatus { paused: pause.is_paused() }
+}
+
+/// Stops capturing. What's copied while paused is never captured, even
+/// after resuming.
+#[tauri::command]
+fn pause_monitoring(pause: State<'_, MonitorPause>) -> MonitoringStatus {
+    pause.set_paused(true);
+    MonitoringStatus { paused: true }
+}
+
+#[tauri::command]
+fn resume_monitoring(pause: State<'_, MonitorPause>) -> MonitoringStatus {
+    pause.set_paused(false);
+    MonitoringStatus { paused: false }
+}
+
+/// Swaps in the library at `path`, opened read-only, for browsing a backup.
+/// Capture and background work pause, and commands that would write fail
+/// with `read_only` until `close_readonly`. A capture still in its grace
+/// period is saved to the live library first.
+#[tauri::command]
+async fn open_database_readonly(
+    path: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+    read_only: State<'_, ReadOnlyMode>,
+) -> Result<ReadOnlyStatus, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let mut db = db.lock().await;
+    if db.is_read_only() {
+        return Err("A read-only database is already open".to_string());
+    }
+
+    if let Some(clip) = app.state::<PendingCapture>().cancel() {
+        db.insert_clip(&clip).await.map_err(|e| e.to_string())?;
+    }
+
+    let browsed = db
+        .open_read_only(std::path::Path::new(&path), &DatabaseConfig::default())
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = ReadOnlyStatus { path, opened_at: Utc::now() };
+
+    let live = std::mem::replace(&mut *db, browsed);
+    if let Err(live) = read_only.enter(live, status.clone()) {
+        let browsed = std::mem::replace(&mut *db, live);
+        browsed.close().await;
+        return Err("A read-only database is already open".to_string());
+    }
+
+    set_window_titles(&app, &readonly::window_title(Some(&status)));
+    Ok(status)
+}
+
+/// Closes the read-only database and puts the live library back; capture
+/// and background work resume.
+#[tauri::command]
+async fn close_readonly(
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+    read_only: State<'_, ReadOnlyMode>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let mut db = db.lock().await;
+    let live = read_only.exit().ok_or_else(|| "No read-only database is open".to_string())?;
+    let browsed = std::mem::replace(&mut *db, live);
+    browsed.close().await;
+
+    set_window_titles(&app, &readonly::window_title(None));
+    Ok(())
+}
+
+fn set_window_titles(app: &tauri::AppHandle, title: &str) {
+    for window in app.webview_windows().values() {
+        if let Err(e) = window.set_title(title) {
+            eprintln!("Failed to set window title: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+fn lock_app(lock: State<'_, AppLock>) {
+    lock.lock();
+}
+
+#[tauri::command]
+fn unlock_app(pin: String, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.unlock(&pin).map_err(|e| e.to_string())
+}
+
+/// Sets, changes or (with `pin: None`) removes the app lock PIN. Changing an
+/// existing PIN requires the current one.
+#[tauri::command]
+async fn set_app_pin(
+    pin: Option<String>,
+    current_pin: Option<String>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    lock.confirm_pin(current_pin.as_deref()).map_err(|e| e.to_string())?;
+
+    let hash = match pin.as_deref() {
+        Some(pin) if pin.trim().len() < 4 => return Err("PIN must be at least 4 characters".to_string()),
+        Some(pin) => Some(lock::hash_pin(pin).map_err(|e| e.to_string())?),
+        None => None,
+    };
+
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.set_pin_hash(hash.as_deref()).await.map_err(|e| e.to_string())?;
+    lock.set_pin_hash(hash);
+    Ok(())
+}
+
+/// Issues the token `wipe_all_data` requires. It expires after a minute.
+#[tauri::command]
+fn request_wipe_token(lock: State<'_, AppLock>, wipe: State<'_, WipeToken>) -> Result<String, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    Ok(wipe.issue())
+}
+
+/// Erases all history, collections and (unless `keep_settings`) settings,
+/// plus ClipSage's backup files, leaving a freshly initialized app.
+#[tauri::command]
+async fn wipe_all_data(
+    confirm_token: String,
+    keep_settings: Option<bool>,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+    wipe: State<'_, WipeToken>,
+) -> Result<WipeReport, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    wipe.redeem(&confirm_token)?;
+    let keep_settings = keep_settings.unwrap_or(false);
+
+    let mut db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    app.state::<PendingCapture>().cancel();
+    let mut report = db.wipe_all_data(keep_settings).await.map_err(|e| e.to_string())?;
+
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    report.files_deleted = wipe::delete_backup_files(&data_dir);
+
+    if !keep_settings {
+        lock.set_pin_hash(None);
+        lock.set_policy(db.config().lock_idle_timeout(), db.config().lock_on_hide);
+        slots::register_slot_shortcuts(&app, db.config()).map_err(|e| e.to_string())?;
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+async fn search_clips(
+    query: String,
+    include_archive: Option<bool>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<ClipPreview>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    if query.trim().is_empty() {
+        return db.get_recent_clips(50).await.map(to_previews(db.config().preview_chars)).map_err(|e| e.to_string());
+    }
+
+    let search = async {
+        let mut results = db.search_clips(&query, 50).await?;
+        if include_archive.unwrap_or(false) {
+            for clip in db.search_archive(&query, 50).await? {
+                if !results.iter().any(|existing| existing.id == clip.id) {
+                    results.push(clip);
+                }
+            }
+        }
+        Ok::<_, anyhow::Error>(results)
+    };
+
+    db.metrics()
+        .time(Operation::Search, search)
+        .await
+        .map(to_previews(db.config().preview_chars))
+        .map_err(|e| e.to_string())
+}
+
+fn to_previews(preview_chars: usize) -> impl Fn(Vec<ClipItem>) -> Vec<ClipPreview> {
+    move |clips| clips.iter().map(|clip| ClipPreview::new(clip, preview_chars)).collect()
+}
+
+#[tauri::command]
+async fn set_clip_masked(id: String, masked: bool, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.set_clip_masked(&id, masked).await.map_err(|e| e.to_string())
+}
+
+/// Returns a clip's real content, masked or not. Asks for the app-lock PIN
+/// first when `reveal_requires_pin` is on and a PIN is set.
+#[tauri::command]
+async fn reveal_clip(
+    id: String,
+    pin: Option<String>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<String, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    if db.config().reveal_requires_pin {
+        lock.confirm_pin(pin.as_deref()).map_err(|e| e.to_string())?;
+    }
+
+    db.get_clip(&id)
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|clip| clip.content)
+        .ok_or_else(|| format!("Clip not found: {}", id))
+}
+
+/// Discards the capture still waiting out its grace period, if any, and
+/// returns its id.
+#[tauri::command]
+fn cancel_last_capture(app: tauri::AppHandle, pending: State<'_, PendingCapture>) -> Option<String> {
+    let canceled = pending.cancel()?;
+    let _ = app.emit(CAPTURE_CANCELED_EVENT, &canceled.id);
+    Some(canceled.id)
+}
+
+#[tauri::command]
+async fn archive_clips(
+    older_than_days: u64,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<u64, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    let slots_before = db.get_slots().await.map_err(|e| e.to_string())?;
+    let archived = db.archive_clips(older_than_days).await.map_err(|e| e.to_string())?;
+    slots::notify_cleared(&app, &slots_before, &db.get_slots().await.map_err(|e| e.to_string())?);
+    Ok(archived)
+}
+
+#[tauri::command]
+async fn delete_clip(
+    id: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    let cleared = db.delete_clip(&id).await.map_err(|e| e.to_string())?;
+    if !cleared.is_empty() {
+        let _ = app.emit(slots::SLOTS_CLEARED_EVENT, cleared);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn delete_clips(
+    ids: Vec<String>,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<usize, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let ids = db.resolve_clip_ids(&ids).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    let slots_before = db.get_slots().await.map_err(|e| e.to_string())?;
+    let deleted = db.delete_clips(&ids).await.map_err(|e| e.to_string())?;
+    slots::notify_cleared(&app, &slots_before, &db.get_slots().await.map_err(|e| e.to_string())?);
+    Ok(deleted)
+}
+
+/// Raw, sortable listing for the data-table view. Unknown `sort_by` keys
+/// are rejected when the arguments are deserialized.
+#[tauri::command]
+async fn list_clips(
+    sort_by: ClipTableSort,
+    descending: Option<bool>,
+    limit: Option<i32>,
+    offset: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<ClipTablePage, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.list_clips(sort_by, descending.unwrap_or(true), limit.unwrap_or(50), offset.unwrap_or(0))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_audit_log(
+    entity_id: Option<String>,
+    limit: Option<i32>,
+    offset: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<AuditEntry>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_audit_log(entity_id.as_deref(), limit.unwrap_or(100), offset.unwrap_or(0))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn clear_audit_log_older_than(days: u64, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<u64, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.clear_audit_log_older_than(days).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn assign_slot(slot: u8, clip_id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.assign_slot(slot, &clip_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn clear_slot(slot: u8, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.clear_slot(slot).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_slots(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<SlotAssignment>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_slots().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn add_global_pin(clip_id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.add_global_pin(&clip_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn remove_global_pin(clip_id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.remove_global_pin(&clip_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn reorder_global_pins(ordered_ids: Vec<String>, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let ordered_ids = db.resolve_clip_ids(&ordered_ids).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    let ids: Vec<&str> = ordered_ids.iter().map(String::as_str).collect();
+    db.reorder_global_pins(&ids).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_global_pins(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<ClipPreview>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_global_pins().await.map(to_previews(db.config().preview_chars)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn split_clip_into_lines(
+    clip_id: String,
+    min_line_length: usize,
+    archive_original: bool,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<ClipItem>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    let children = db
+        .split_clip_by_lines(&clip_id, min_line_length)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if archive_original {
+        let slots_before = db.get_slots().await.map_err(|e| e.to_string())?;
+        db.archive_clip(&clip_id).await.map_err(|e| e.to_string())?;
+        slots::notify_cleared(&app, &slots_before, &db.get_slots().await.map_err(|e| e.to_string())?);
+    }
+
+    Ok(children)
+}
+
+#[tauri::command]
+async fn split_clip(
+    id: String,
+    delimiter: SplitMode,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<ClipItem>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.split_clip(&id, &delimiter).await.map_err(|e| e.to_string())
+}
+
+/// Combines clips into a new one, in the given order, then deletes the
+/// sources if `delete_sources` (default: the `merge_deletes_sources`
+/// setting) says so.
+#[tauri::command]
+async fn merge_clips_into_new(
+    ids: Vec<String>,
+    separator: String,
+    delete_sources: Option<bool>,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<ClipItem, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let ids = db.resolve_clip_ids(&ids).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    let delete_sources = delete_sources.unwrap_or(db.config().merge_deletes_sources);
+
+    let slots_before = db.get_slots().await.map_err(|e| e.to_string())?;
+    let merged = db
+        .merge_clips_into_new(&ids, &separator, delete_sources)
+        .await
+        .map_err(|e| e.to_string())?;
+    if delete_sources {
+        slots::notify_cleared(&app, &slots_before, &db.get_slots().await.map_err(|e| e.to_string())?);
+    }
+    Ok(merged)
+}
+
+#[tauri::command]
+async fn search_archive(query: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<ClipPreview>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.search_archive(&query, 50).await.map(to_previews(db.config().preview_chars)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn unarchive_clip(id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<ClipItem, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.unarchive_clip(&id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_recent_clips(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<ClipPreview>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_recent_clips(50).await.map(to_previews(db.config().preview_chars)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_recent_clips_grouped(
+    limit: Option<i32>,
+    tz_offset_minutes: i32,
+    cursor: Option<ClipCursor>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<GroupedClips, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_recent_clips_grouped(limit.unwrap_or(50), tz_offset_minutes, cursor.as_ref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Payload of `TAGS_CHANGED_EVENT`: the tags folded into `target` and how
+/// many clips changed.
+#[derive(Clone, serde::Serialize)]
+struct TagsChanged {
+    sources: Vec<String>,
+    target: String,
+    clips_affected: u64,
+}
+
+/// Renames a tag on every clip; `merge_tags` with one source.
+#[tauri::command]
+async fn rename_tag(
+    old: String,
+    new: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<u64, String> {
+    merge_tags(vec![old], new, app, db, lock).await
+}
+
+/// Folds `source_tags` (e.g. spelling variants such as `js` and
+/// `JavaScript`) into `target_tag` on every clip and in tag rules. Returns
+/// how many clips changed.
+#[tauri::command]
+async fn merge_tags(
+    source_tags: Vec<String>,
+    target_tag: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<u64, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    let sources: Vec<&str> = source_tags.iter().map(String::as_str).collect();
+    let clips_affected = db.merge_tags(&sources, &target_tag).await.map_err(|e| e.to_string())?;
+
+    let _ = app.emit(TAGS_CHANGED_EVENT, TagsChanged {
+        sources: source_tags,
+        target: target_tag,
+        clips_affected,
+    });
+    Ok(clips_affected)
+}
+
+/// A clip as display HTML: highlighted with its guessed language for code
+/// clips, escaped plain text otherwise. Masked clips render the placeholder.
+#[tauri::command]
+async fn render_clip_html(id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<RenderedClip, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    let clip = db
+        .get_clip(&id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Clip not found: {}", id))?;
+
+    if clip.masked {
+        return Ok(highlight::render_plain(database::MASKED_PLACEHOLDER));
+    }
+    match clip.content_type {
+        ContentType::Code => highlight::render_code(&clip.content).map_err(|e| e.to_string()),
+        _ => Ok(highlight::render_plain(&clip.content)),
+    }
+}
+
+/// Detects and stores a clip's programming language; `unknown` when the
+/// snippet is ambiguous.
+#[tauri::command]
+async fn detect_language(id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<String, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.detect_code_language(&id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_clips_by_language(
+    language: String,
+    limit: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<ClipItem>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_clips_by_language(&language, limit.unwrap_or(50)).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_clip_reminder(
+    id: String,
+    at: DateTime<Utc>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Reminder, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.set_clip_reminder(&id, at).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_reminders(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<Reminder>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.list_reminders().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cancel_reminder(id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.cancel_reminder(&id).await.map_err(|e| e.to_string())
+}
+
+/// Clips grouped under the most used tags for the home screen. Defaults to
+/// 5 tags of 3 clips; results are cached briefly since the screen polls.
+#[tauri::command]
+async fn get_home_feed(
+    n_tags: Option<u32>,
+    clips_per_tag: Option<u32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+    cache: State<'_, HomeFeedCache>,
+) -> Result<Vec<TaggedClipGroup>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let key = (n_tags.unwrap_or(5), clips_per_tag.unwrap_or(3));
+    if let Some((built_at, cached_key, groups)) = cache.0.lock().unwrap().as_ref() {
+        let fresh = (Utc::now() - *built_at).to_std().is_ok_and(|age| age < HOME_FEED_TTL);
+        if *cached_key == key && fresh {
+            return Ok(groups.clone());
+        }
+    }
+
+    let db = db.lock().await;
+    let groups = db
+        .get_clips_grouped_by_top_tags(key.0, key.1)
+        .await
+        .map_err(|e| e.to_string())?;
+    *cache.0.lock().unwrap() = Some((Utc::now(), key, groups.clone()));
+    Ok(groups)
+}
+
+#[tauri::command]
+async fn get_recently_edited_clips(
+    limit: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<ClipPreview>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_recent_clips_by(limit.unwrap_or(50), SortField::LastModified)
+        .await
+        .map(to_previews(db.config().preview_chars))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn semantic_search_clips(query: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<SemanticHit>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let search = async {
+        let query_embedding = db.embed_text(&query).await?;
+        db.semantic_search_matches(&query_embedding, 50).await
+    };
+
+    db.metrics()
+        .time(Operation::Search, search)
+        .await
+        .map(|hits| hits.into_iter().map(|hit| SemanticHit::new(hit, db.config().preview_chars)).collect())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_clip_sentences(clip_id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<SentenceSpan>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.get_clip_sentences(&clip_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_sentence_embedding(
+    clip_id: String,
+    sentence_index: u32,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<f32>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.get_sentence_embedding(&clip_id, sentence_index).await.map_err(|e| e.to_string())
+}
+
+/// Semantic search with a vector computed elsewhere; Ollama isn't involved.
+#[tauri::command]
+async fn semantic_search_vector(
+    vector: Vec<f32>,
+    limit: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<ClipPreview>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.metrics()
+        .time(Operation::Search, db.semantic_search_vector(&vector, limit.unwrap_or(50)))
+        .await
+        .map(to_previews(db.config().preview_chars))
+        .map_err(|e| e.to_string())
+}
+
+/// Clips related to whatever is on the system clipboard right now, for the
+/// "you might also want" panel. An empty clipboard yields no suggestions.
+#[tauri::command]
+async fn suggest_related(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<ClipPreview>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+
+    let mut clipboard = SystemClipboard::new(Selection::Clipboard).map_err(|e| e.to_string())?;
+    related_to_clipboard(&db, &mut clipboard)
+        .await
+        .map(to_previews(db.config().preview_chars))
+        .map_err(|e| e.to_string())
+}
+
+async fn related_to_clipboard(db: &Database, clipboard: &mut impl ClipboardSource) -> anyhow::Result<Vec<ClipItem>> {
+    let current = match clipboard.read(db.config().non_utf8_clipboard)? {
+        Some(ClipboardText { text, .. }) if !text.trim().is_empty() => text,
+        _ => return Ok(Vec::new()),
+    };
+
+    db.find_related_to_text(&current, 10, SEMANTIC_MIN_SIMILARITY).await
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SearchMode {
+    Text,
+    Semantic,
+}
+
+#[tauri::command]
+async fn search_in_collections(
+    query: String,
+    collection_ids: Vec<String>,
+    limit: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<ClipItem>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let ids: Vec<&str> = collection_ids.iter().map(String::as_str).collect();
+    db.metrics()
+        .time(Operation::Search, db.search_in_collections(&query, &ids, limit.unwrap_or(50)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn semantic_search_in_collections(
+    query: String,
+    collection_ids: Vec<String>,
+    min_similarity: f32,
+    limit: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<ClipItem>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let ids: Vec<&str> = collection_ids.iter().map(String::as_str).collect();
+    let search = async {
+        let query_embedding = db.embed_text(&query).await?;
+        db.semantic_search_in_collections(&query_embedding, &ids, min_similarity, limit.unwrap_or(50))
+            .await
+    };
+
+    db.metrics()
+        .time(Operation::Search, search)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn search_clips_with_total(
+    query: String,
+    mode: SearchMode,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<SearchResults, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let search = async {
+        let started = std::time::Instant::now();
+        let mut timings = SearchTimings::default();
+        let mut results = match mode {
+            SearchMode::Text => {
+                let results = db.text_search_with_total(&query, 50).await?;
+                timings.fts_ms = metrics::elapsed_ms(started);
+                results
+            }
+            SearchMode::Semantic => {
+                let query_embedding = db.embed_text(&query).await?;
+                timings.embedding_ms = metrics::elapsed_ms(started);
+                let stage = std::time::Instant::now();
+                let results = db.semantic_search_with_total(&query_embedding, 50, SEMANTIC_MIN_SIMILARITY).await?;
+                timings.similarity_ms = metrics::elapsed_ms(stage);
+                results
+            }
+        };
+        timings.total_ms = metrics::elapsed_ms(started);
+
+        db.warn_if_slow_search(&query, &timings);
+        if db.config().search_diagnostics {
+            results.diagnostics = Some(timings);
+        }
+        Ok::<_, anyhow::Error>(results)
+    };
+
+    db.metrics()
+        .time(Operation::Search, search)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// One page of hybrid search results, with the total across all pages.
+#[tauri::command]
+async fn search_clips_page(
+    query: String,
+    limit: Option<i32>,
+    offset: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Page<ClipPreview>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let search = db.search_clips_page(&query, limit.unwrap_or(50), offset.unwrap_or(0), SEMANTIC_MIN_SIMILARITY);
+
+    db.metrics()
+        .time(Operation::Search, search)
+        .await
+        .map(|page| page.map(to_previews(db.config().preview_chars)))
+        .map_err(|e| e.to_string())
+}
+
+#[derive(serde::Serialize)]
+struct SearchBenchmark {
+    queries: usize,
+    iterations: u32,
+    stages: Vec<StageLatency>,
+}
+
+/// Runs each of `queries` through the hybrid search `iterations` times
+/// (default 5) and reports latency percentiles per stage.
+#[tauri::command]
+async fn run_search_benchmark(
+    queries: Vec<String>,
+    iterations: Option<u32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<SearchBenchmark, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let iterations = iterations.unwrap_or(5).max(1);
+    let db = db.lock().await;
+
+    let mut runs = Vec::with_capacity(queries.len() * iterations as usize);
+    for _ in 0..iterations {
+        for query in queries.iter().filter(|query| !query.trim().is_empty()) {
+            let (_, timings) = db.search_clips_timed(query, 50).await.map_err(|e| e.to_string())?;
+            runs.push(timings);
+        }
+    }
+
+    Ok(SearchBenchmark {
+        queries: queries.len(),
+        iterations,
+        stages: metrics::stage_latencies(&runs),
+    })
+}
+
+#[tauri::command]
+async fn get_metrics(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<MetricsSnapshot, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    Ok(db.metrics().snapshot())
+}
+
+#[tauri::command]
+async fn reset_metrics(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.metrics().reset();
+    Ok(())
+}
+
+/// How clips containing `phrase` evolved, oldest first, with the lines added
+/// and removed between consecutive versions.
+#[tauri::command]
+async fn get_clip_timeline(phrase: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<TimelineEntry>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_clip_timeline(&phrase).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn diff_clips(
+    id_a: String,
+    id_b: String,
+    mode: Option<DiffMode>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<TextDiffResult, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id_a = db.resolve_clip_id(&id_a).await.map_err(|e| e.to_string())?;
+    let id_b = db.resolve_clip_id(&id_b).await.map_err(|e| e.to_string())?;
+    db.diff_clips(&id_a, &id_b, mode.unwrap_or(DiffMode::Line))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_embed_truncated_clips(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<ClipItem>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_embed_truncated_clips(50).await.map_err(|e| e.to_string())
+}
+
+/// Records the configured model's embedding size, which later embeddings
+/// are checked against. `None` means Ollama is down and calibration was put
+/// off; it's retried at the next startup.
+#[tauri::command]
+async fn calibrate_embeddings(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Option<EmbeddingCalibration>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let mut db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.calibrate_embeddings().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_embedding_calibration(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Option<EmbeddingCalibration>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    Ok(db.embedding_calibration().cloned())
+}
+
+#[tauri::command]
+async fn get_clips_needing_reindex(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<ClipItem>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_clips_needing_reindex(50).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn reembed_clip(id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.reembed_clip(&id).await.map_err(|e| e.to_string())
+}
+
+/// Re-embeds every clip whose embedding came from another model, emitting
+/// "embedding-progress".
+#[tauri::command]
+async fn re_embed_all(app: tauri::AppHandle, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<u64, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.re_embed_all(|progress| {
+        let _ = app.emit("embedding-progress", progress);
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Embeds clips stored without an embedding, emitting "embedding-progress".
+#[tauri::command]
+async fn backfill_embeddings(app: tauri::AppHandle, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<u64, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.backfill_embeddings(|progress| {
+        let _ = app.emit("embedding-progress", progress);
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_config(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<AppConfig, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    Ok(db.config().clone())
+}
+
+/// The config in effect, where each setting came from (default, file, env or
+/// settings) and any error from the last read of clipsage.toml.
+#[tauri::command]
+async fn get_effective_config(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<EffectiveConfig, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.effective_config().map_err(|e| e.to_string())
+}
+
+/// Checks that Ollama answers with the connection settings of `config`
+/// (the current settings when omitted). Returns `None` when it does, or the
+/// problem with a `kind` naming what's wrong: proxy, certificate, token or
+/// reachability.
+#[tauri::command]
+async fn check_ollama_connection(
+    config: Option<AppConfig>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Option<OllamaError>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let config = match config {
+        Some(config) => config,
+        None => db.lock().await.config().clone(),
+    };
+
+    let health = match OllamaClient::from_config(&config) {
+        Ok(client) => client.check_health().await,
+        Err(e) => Err(e),
+    };
+    Ok(health.err())
+}
+
+#[tauri::command]
+async fn update_config(
+    config: AppConfig,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let mut db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    if !config.same_ollama_connection(db.config()) {
+        let client = OllamaClient::from_config(&config).map_err(|e| e.to_string())?;
+        client.check_health().await.map_err(|e| e.to_string())?;
+    }
+    db.set_config(config).await.map_err(|e| e.to_string())?;
+    lock.set_policy(db.config().lock_idle_timeout(), db.config().lock_on_hide);
+    slots::register_slot_shortcuts(&app, db.config()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn summarize_text(text: String, max_tokens: Option<u32>, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<String, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let max_tokens = max_tokens.unwrap_or(db.config().summary_max_tokens);
+    config::validate_summary_max_tokens(max_tokens)?;
+
+    db.ollama()
+        .generate_summary(&text, max_tokens)
+        .await
+        .map(|summary| summary.trim().to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Answers a question from the clip history, citing the clips used.
+#[tauri::command]
+async fn ask_clips(question: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<CitedAnswer, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ask_clips(&question, SEMANTIC_MIN_SIMILARITY).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn suggest_tags(
+    id: String,
+    limit: Option<usize>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<TagSuggestion>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.suggest_tags(&id, limit.unwrap_or(5)).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn suggest_search_terms(
+    query: String,
+    context_clip_ids: Vec<String>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<String>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let context_clip_ids = db.resolve_clip_ids(&context_clip_ids).await.map_err(|e| e.to_string())?;
+    let clips = db.get_clips_by_ids(&context_clip_ids).await.map_err(|e| e.to_string())?;
+    let summaries: Vec<&str> = clips.iter().map(|clip| clip.summary.as_str()).collect();
+
+    db.ollama()
+        .suggest_related_queries(&query, &summaries)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn find_near_duplicates(
+    threshold: Option<f32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<(String, String, f32)>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.find_near_duplicates(threshold.unwrap_or(NEAR_DUPLICATE_THRESHOLD))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_clip_similarity_matrix(clip_ids: Vec<String>, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<Vec<f32>>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_ids = db.resolve_clip_ids(&clip_ids).await.map_err(|e| e.to_string())?;
+    let ids: Vec<&str> = clip_ids.iter().map(String::as_str).collect();
+    db.get_embedding_similarity_matrix(&ids).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_collection_similarity_matrix(collection_id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<Vec<f32>>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_ids = db.get_collection_clip_ids(&collection_id).await.map_err(|e| e.to_string())?;
+    let ids: Vec<&str> = clip_ids.iter().map(String::as_str).collect();
+    db.get_embedding_similarity_matrix(&ids).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_collection(name: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Collection, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.create_collection(&name).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_collection_overlap(
+    id_a: String,
+    id_b: String,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<CollectionOverlap, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.collection_overlap(&id_a, &id_b).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn merge_collections(
+    id_a: String,
+    id_b: String,
+    new_name: String,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Collection, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.merge_collections(&id_a, &id_b, &new_name).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn auto_create_collections_from_tags(
+    min_clips: u32,
+    dry_run: Option<bool>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<Collection>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.auto_create_collections_from_tags(min_clips, dry_run.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_collection_auto_file(
+    collection_id: String,
+    enabled: bool,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.set_collection_auto_file(&collection_id, enabled).await.map_err(|e| e.to_string())
+}
+
+/// Has the model (re)write the collection's description and returns it.
+#[tauri::command]
+async fn generate_collection_description(
+    collection_id: String,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<String, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.describe_collection(&collection_id).await.map_err(|e| e.to_string())
+}
+
+/// Review queue of recent auto-filings, newest first.
+#[tauri::command]
+async fn get_auto_filings(
+    limit: Option<u32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<AutoFiling>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_auto_filings(limit.unwrap_or(50)).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn undo_auto_file(
+    clip_id: String,
+    correct_collection_id: Option<String>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.undo_auto_file(&clip_id, correct_collection_id.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_collection_rule(
+    collection_id: String,
+    rule_type: String,
+    rule_value: String,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let rule_type = CollectionRuleType::parse(&rule_type).map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.create_collection_rule(&collection_id, rule_type, &rule_value)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_collection_rule(rule_id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.delete_collection_rule(&rule_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_retention_rule(
+    tag: String,
+    max_age_days: Option<u32>,
+    max_count: Option<u32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<RetentionRule, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.create_retention_rule(&tag, max_age_days, max_count)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_retention_rule(
+    rule_id: String,
+    max_age_days: Option<u32>,
+    max_count: Option<u32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.update_retention_rule(&rule_id, max_age_days, max_count)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_retention_rule(rule_id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.delete_retention_rule(&rule_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_retention_rules(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<RetentionRule>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.list_retention_rules().await.map_err(|e| e.to_string())
+}
+
+/// What the retention rules would delete now, without deleting anything.
+#[tauri::command]
+async fn preview_prune(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<PruneReport, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.preview_prune().await.map_err(|e| e.to_string())
+}
+
+/// Runs the retention rules now instead of waiting for maintenance.
+#[tauri::command]
+async fn apply_retention_rules(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<PruneReport, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.apply_retention_rules().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_collection_rules(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<CollectionRule>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.list_collection_rules().await.map_err(|e| e.to_string())
+}
+
+/// Dry run: would a clip with `sample_content` (tagged as if just captured)
+/// satisfy this rule?
+#[tauri::command]
+fn test_collection_rule(rule_type: String, rule_value: String, sample_content: String) -> Result<bool, String> {
+    let rule_type = CollectionRuleType::parse(&rule_type).map_err(|e| e.to_string())?;
+    let urls = urls::extract_urls(&sample_content);
+    let tags = database::detect_tags(&sample_content, &urls);
+    let now = Utc::now();
+    let sample = ClipItem {
+        id: String::new(),
+        summary: String::new(),
+        content_type: ContentType::detect(&sample_content, &tags),
+        content: sample_content,
+        tags,
+        created_at: now,
+        last_modified: now,
+        source: Some("clipboard".to_string()),
+        embedding: None,
+        urls,
+        embed_truncated: false,
+        source_url: None,
+        rewrite_history: Vec::new(),
+        masked: false,
+        keywords: Vec::new(),
+        metadata: Default::default(),
+        seq: None,
+        code_language: None,
+    };
+
+    rule_type.matches(&rule_value, &sample).map_err(|e| e.to_string())
+}
+
+/// What the monitor would store for `content` (summary, tags, type, URLs)
+/// under the current settings. Nothing is saved or copied.
+#[tauri::command]
+async fn preview_capture(content: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<CapturePreview, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    Ok(capture::preview(&content, db.config()))
+}
+
+#[tauri::command]
+async fn list_collections(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<Collection>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.list_collections().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn add_clips_to_collection(
+    collection_id: String,
+    clip_ids: Vec<String>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_ids = db.resolve_clip_ids(&clip_ids).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.add_clips_to_collection(&collection_id, &clip_ids).await.map_err(|e| e.to_string())
+}
+
+/// Moves clips into `collection_id`, or with `keep_existing` just adds them
+/// and leaves their other memberships alone.
+#[tauri::command]
+async fn move_clips_to_collection(
+    ids: Vec<String>,
+    collection_id: String,
+    keep_existing: Option<bool>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let ids = db.resolve_clip_ids(&ids).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.move_clips_to_collection(&ids, &collection_id, keep_existing.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn remove_clip_from_collection(
+    collection_id: String,
+    clip_id: String,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.remove_clip_from_collection(&collection_id, &clip_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn export_clips_json(path: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<usize, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.export_clips_json(std::path::Path::new(&path)).await.map_err(|e| e.to_string())
+}
+
+#[derive(serde::Serialize)]
+struct ExportInfo {
+    n_clips: usize,
+    embedding_dim: usize,
+    array_path: String,
+    ids_path: String,
+}
+
+/// Exports embeddings as a `.npy` array plus a JSON list of clip ids, for
+/// clustering or plotting in Python.
+#[tauri::command]
+async fn export_embeddings(path: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<ExportInfo, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let (n_clips, embedding_dim, ids_path) = db
+        .export_embeddings_to_npy(std::path::Path::new(&path))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(ExportInfo {
+        n_clips,
+        embedding_dim,
+        array_path: path,
+        ids_path: ids_path.display().to_string(),
+    })
+}
+
+#[tauri::command]
+async fn full_export(
+    path: String,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<usize, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.full_export_to(std::path::Path::new(&path), |progress| {
+        let _ = app.emit("transfer-progress", progress);
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Imports Alfred's clipboard history from its `clipboard.alfdb`.
+#[cfg(target_os = "macos")]
+#[tauri::command]
+async fn import_alfred_clipboard(path: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<ImportReport, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.import_from_alfred(std::path::Path::new(&path)).await.map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+async fn import_alfred_clipboard(path: String) -> Result<ImportReport, String> {
+    Err(format!("Alfred is only available on macOS, can't import {}", path))
+}
+
+#[tauri::command]
+async fn full_import(
+    path: String,
+    import_settings: Option<bool>,
+    dedupe_after: Option<bool>,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<FullImportReport, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let mut db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    let report = db
+        .full_import(
+            std::path::Path::new(&path),
+            import_settings.unwrap_or(false),
+            dedupe_after.unwrap_or(false),
+            |progress| {
+                let _ = app.emit("transfer-progress", progress);
+            },
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if report.settings_imported {
+        lock.set_policy(db.config().lock_idle_timeout(), db.config().lock_on_hide);
+        slots::register_slot_shortcuts(&app, db.config()).map_err(|e| e.to_string())?;
+    }
+    Ok(report)
+}
+
+/// Collapses clips with identical content; returns how many were removed.
+#[tauri::command]
+async fn merge_duplicates(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<u64, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.merge_duplicates().await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! Welcome to ClipSage!", name)
+async fn export_filtered(filter: ExportFilter, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<String, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.export_filtered(&filter).await.map_err(|e| e.to_string())
 }
 
+/// Compares text, semantic and hybrid results for `query`. Only built
+/// into debug builds.
+#[cfg(debug_assertions)]
 #[tauri::command]
-async fn hide_window(window: tauri::Window) -> Result<(), String> {
-    window.hide().map_err(|e| e.to_string())
+async fn compare_search_methods(
+    query: String,
+    limit: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<database::SearchComparison, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.compare_search_methods(&query, limit.unwrap_or(20)).await.map_err(|e| e.to_string())
 }
 
+/// Most frequent words across the history, for the insights panel.
 #[tauri::command]
-async fn show_window(window: tauri::Window) -> Result<(), String> {
-    window.show().map_err(|e| e.to_string())?;
-    window.set_focus().map_err(|e| e.to_string())
+async fn top_terms(limit: Option<usize>, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<TermCount>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.top_terms(limit.unwrap_or(50)).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn search_clips(query: String, db: State<'_, DbState>) -> Result<Vec<ClipItem>, String> {
+async fn get_source_stats(
+    days: u32,
+    tz_offset_minutes: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<SourceStats>, String> {
+    lock.check().map_err(|e| e.to_string())?;
     let db = db.lock().await;
-    if query.trim().is_empty() {
-        db.get_recent_clips(50).await.map_err(|e| e.to_string())
+    db.get_source_stats(days, tz_offset_minutes.unwrap_or(0))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Where most copying happens: clips per source over the last `days` days,
+/// with day boundaries in the caller's time zone.
+#[tauri::command]
+async fn source_activity(
+    days: u32,
+    tz_offset_minutes: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<SourceActivity>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_source_activity(days, tz_offset_minutes.unwrap_or(0))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_tag_usage_heatmap(tag: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<TagHeatmapPoint>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_tag_time_heatmap(&tag).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_all_clips_heatmap(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<TagHeatmapPoint>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_all_clips_heatmap().await.map_err(|e| e.to_string())
+}
+
+/// Per-month clip counts for `year` (the current year by default), for the
+/// contribution graph.
+#[tauri::command]
+async fn get_annual_clip_counts(year: Option<i32>, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<MonthlyCount>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let year = year.unwrap_or_else(|| Utc::now().year());
+    db.get_monthly_clip_counts(year).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_daily_clip_counts(
+    year: i32,
+    month: u32,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<DailyCount>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_daily_clip_counts(year, month).await.map_err(|e| e.to_string())
+}
+
+/// Rebuilds summaries of existing clips after the summary strategy changes,
+/// emitting `summary-progress` events. Returns how many summaries changed.
+#[tauri::command]
+async fn regenerate_summaries(
+    scope: SummaryScope,
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<u64, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.regenerate_summaries(&scope, |progress| {
+        let _ = app.emit("summary-progress", progress);
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn check_index(app: tauri::AppHandle, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<IndexReport, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.check_index(|progress| {
+        let _ = app.emit("index-progress", progress);
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn verify_fts_sync(db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<FtsSyncReport, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.verify_fts_sync().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn repair_index(app: tauri::AppHandle, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<IndexRepairReport, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.repair_index(|progress| {
+        let _ = app.emit("index-progress", progress);
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn copy_clip(
+    id: String,
+    clean_url: Option<bool>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+    self_write: State<'_, SelfWriteState>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    let clip = db
+        .get_clip(&id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Clip not found: {}", id))?;
+
+    // Only the clipboard copy is cleaned; the stored clip keeps its URL
+    let content = if clean_url.unwrap_or(false) && clip.content_type == ContentType::Url {
+        urls::strip_tracking_params(&clip.content, &db.config().url_tracking_params)
     } else {
-        db.search_clips(&query, 50).await.map_err(|e| e.to_string())
+        clip.content
+    };
+
+    self_write.write_text(&content).map_err(|e| e.to_string())?;
+    if db.is_read_only() {
+        return Ok(());
     }
+    db.record_access(&id).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn get_recent_clips(db: State<'_, DbState>) -> Result<Vec<ClipItem>, String> {
+async fn rewrite_clip(
+    id: String,
+    instruction: String,
+    create_new_version: bool,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<ClipItem, String> {
+    lock.check().map_err(|e| e.to_string())?;
     let db = db.lock().await;
-    db.get_recent_clips(50).await.map_err(|e| e.to_string())
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.rewrite_clip(&id, &instruction, create_new_version)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn semantic_search_clips(query: String, db: State<'_, DbState>) -> Result<Vec<ClipItem>, String> {
+async fn extract_clip_entities(clip_id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<Entity>, String> {
+    lock.check().map_err(|e| e.to_string())?;
     let db = db.lock().await;
-    // For now, we'll use a simple embedding of the query text
-    // In a production system, you'd want to use a proper embedding model
-    let query_embedding: Vec<f32> = query
-        .chars()
-        .map(|c| c as u32 as f32 / 255.0)
-        .collect();
-    
-    db.semantic_search(&query_embedding, 50).await.map_err(|e| e.to_string())
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.extract_entities_for_clip(&clip_id).await.map_err(|e| e.to_string())
 }
 
-async fn start_clipboard_monitor(db: DbState) {
-    let mut clipboard = match Clipboard::new() {
-        Ok(cb) => cb,
-        Err(e) => {
-            eprintln!("Failed to initialize clipboard: {}", e);
-            return;
+#[tauri::command]
+async fn generate_clip_keywords(id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<String>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.generate_clip_keywords(&id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn search_by_keywords(
+    query: String,
+    limit: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<ClipItem>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.search_by_keywords(&query, limit.unwrap_or(50)).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn search_by_entity(
+    entity_type: String,
+    entity_value: String,
+    limit: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<ClipItem>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.find_clips_by_entity(&entity_type, &entity_value, limit.unwrap_or(50))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_clip_metadata(id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<ClipMetadata, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.get_clip(&id)
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|clip| clip.metadata)
+        .ok_or_else(|| format!("Clip not found: {}", id))
+}
+
+/// Merges `patch` into a clip's metadata; `null` values remove keys.
+#[tauri::command]
+async fn update_clip_metadata(
+    id: String,
+    patch: serde_json::Map<String, serde_json::Value>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<ClipMetadata, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.update_metadata(&id, patch).await.map_err(|e| e.to_string())
+}
+
+/// Links a clip to the page it was copied from (called by the browser
+/// extension, which knows the page when the copy happens).
+#[tauri::command]
+async fn set_clip_source_url(
+    clip_id: String,
+    url: String,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.set_clip_source_url(&clip_id, &url).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_clips_by_source_url(
+    url: String,
+    limit: Option<i32>,
+    db: State<'_, DbState>,
+    lock: State<'_, AppLock>,
+) -> Result<Vec<ClipItem>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.get_clips_by_source_url(&url, limit.unwrap_or(50)).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn generate_share_link(clip_id: String, config: ShareConfig, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<String, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.create_share(&clip_id, config)
+        .await
+        .map(|share| share.share_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn revoke_share(share_id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.revoke_share(&share_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_shares_for_clip(clip_id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<Vec<ClipShare>, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let clip_id = db.resolve_clip_id(&clip_id).await.map_err(|e| e.to_string())?;
+    db.list_shares_for_clip(&clip_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn record_clip_access(id: String, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<(), String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    let id = db.resolve_clip_id(&id).await.map_err(|e| e.to_string())?;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.record_access(&id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn prune_access_log(keep_per_clip: u32, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<u64, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.prune_access_log(keep_per_clip).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn prune_old_accesses(older_than_days: u64, db: State<'_, DbState>, lock: State<'_, AppLock>) -> Result<u64, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    let db = db.lock().await;
+    db.ensure_writable().map_err(|e| e.to_string())?;
+    db.prune_old_accesses(older_than_days).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_dedup_job_status(job: State<'_, ClipDedupJob>) -> Result<DedupJobStatus, String> {
+    Ok(job.status())
+}
+
+/// Runs a similarity deduplication pass now; returns how many clips were
+/// merged away.
+#[tauri::command]
+async fn trigger_dedup_now(
+    app: tauri::AppHandle,
+    job: State<'_, ClipDedupJob>,
+    lock: State<'_, AppLock>,
+) -> Result<u64, String> {
+    lock.check().map_err(|e| e.to_string())?;
+    job.run_now(&app).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn pause_dedup_job(job: State<'_, ClipDedupJob>) {
+    job.pause();
+}
+
+#[tauri::command]
+fn resume_dedup_job(job: State<'_, ClipDedupJob>) {
+    job.resume();
+}
+
+// Fires reminders as OS notifications, whether or not the window is shown.
+// The first pass runs at launch, so reminders that came due while the app
+// was closed fire right away, marked overdue.
+async fn start_reminder_scheduler(app: tauri::AppHandle, db: DbState) {
+    let started_at = Utc::now();
+    loop {
+        let due = {
+            let db = db.lock().await;
+            if db.is_read_only() {
+                Ok(Vec::new())
+            } else {
+                db.take_due_reminders(Utc::now()).await
+            }
+        };
+        match due {
+            Ok(due) => {
+                for (reminder, clip) in &due {
+                    notify::reminder_due(&app, clip, reminder.remind_at < started_at);
+                }
+            }
+            Err(e) => eprintln!("Failed to check reminders: {}", e),
         }
-    };
 
-    let mut last_content = String::new();
+        tokio::time::sleep(REMINDER_POLL_INTERVAL).await;
+    }
+}
 
+async fn start_maintenance_task(db: DbState) {
     loop {
-        tokio::time::sleep(Duration::from_millis(500)).await;
+        {
+            let db = db.lock().await;
+            if !db.is_read_only() {
+                match db.prune_access_log(ACCESS_LOG_KEEP_PER_CLIP).await {
+                    Ok(removed) => println!("Pruned {} access log entries", removed),
+                    Err(e) => eprintln!("Failed to prune access log: {}", e),
+                }
 
-        if let Ok(content) = clipboard.get_text() {
-            if content != last_content && !content.trim().is_empty() && content.len() > 3 {
-                last_content = content.clone();
-                
-                // Generate a simple summary (first 50 chars or first line)
-                let summary = if content.len() > 50 {
-                    format!("{}...", &content[..47])
-                } else {
-                    content.lines().next().unwrap_or(&content).to_string()
-                };
+                match db.apply_retention_rules().await {
+                    Ok(report) => {
+                        for rule in &report.by_rule {
+                            println!("Retention rule `{}` deleted {} clips", rule.tag, rule.clips.len());
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to apply retention rules: {}", e),
+                }
 
-                // Simple tag generation based on content
-                let mut tags = Vec::new();
-                if content.contains("http") || content.contains("www") {
-                    tags.push("url".to_string());
+                let retention_hours = db.config().primary_selection_retention_hours;
+                if retention_hours > 0 {
+                    match db.prune_clips_from_source(capture::PRIMARY_SELECTION_SOURCE, retention_hours as u64).await {
+                        Ok(removed) => println!("Pruned {} primary-selection clips", removed),
+                        Err(e) => eprintln!("Failed to prune primary-selection clips: {}", e),
+                    }
                 }
-                if content.contains("function") || content.contains("const") || content.contains("let") {
-                    tags.push("code".to_string());
+            }
+        }
+
+        tokio::time::sleep(MAINTENANCE_INTERVAL).await;
+    }
+}
+
+// Saves a captured clip. Capture latency covers preparing the clip plus the
+// insert, not any grace period in between.
+async fn commit_capture(app: &tauri::AppHandle, db: &DbState, clip: &ClipItem, prepared_in: Duration) {
+    let guard = db.lock().await;
+    match guard.find_fuzzy_duplicate(&clip.content).await {
+        Ok(Some(existing)) => {
+            println!("Skipping capture: near-duplicate of clip {}", existing);
+            return;
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Fuzzy duplicate check failed: {}", e),
+    }
+    let insert_started = std::time::Instant::now();
+    let inserted = guard.insert_clip(clip).await;
+    guard.metrics().record(Operation::Capture, prepared_in + insert_started.elapsed(), inserted.is_ok());
+    if let Err(e) = inserted {
+        eprintln!("Failed to insert clip: {}", e);
+        notify::capture_failed(app, &guard.config().notification_rules, &e.to_string());
+        return;
+    }
+    notify::clip_captured(app, &guard.config().notification_rules, clip);
+
+    if capture::needs_model_summary(&clip.content, guard.config()) {
+        let db = db.clone();
+        let clip_id = clip.id.clone();
+        tauri::async_runtime::spawn(async move {
+            let db = db.lock().await;
+            if db.is_read_only() {
+                return;
+            }
+            if let Err(e) = db.summarize_with_model(&clip_id).await {
+                eprintln!("Failed to summarize clip {}: {}", clip_id, e);
+            }
+        });
+    }
+
+    // Classifying waits on the model, so don't hold up the capture loop
+    if guard.config().auto_file_enabled {
+        let db = db.clone();
+        let clip_id = clip.id.clone();
+        tauri::async_runtime::spawn(async move {
+            let db = db.lock().await;
+            if db.is_read_only() {
+                return;
+            }
+            if let Err(e) = db.auto_file_clip(&clip_id).await {
+                eprintln!("Failed to auto-file clip {}: {}", clip_id, e);
+            }
+        });
+    }
+}
+
+// Polls clipsage.toml for edits and applies them. An invalid file is
+// logged and the previous config stays in effect.
+async fn start_config_file_watcher(app: tauri::AppHandle, db: DbState, path: std::path::PathBuf) {
+    let modified_at = |path: &std::path::Path| std::fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    let mut last_modified = modified_at(path.as_path());
+
+    loop {
+        tokio::time::sleep(CONFIG_FILE_POLL_INTERVAL).await;
+
+        let modified = modified_at(path.as_path());
+        if modified == last_modified {
+            continue;
+        }
+
+        // Picked up once the live database is back
+        let mut db = db.lock().await;
+        if db.is_read_only() {
+            continue;
+        }
+        last_modified = modified;
+
+        match db.reload_config_file().await {
+            Ok(true) => {
+                println!("Reloaded {}", path.display());
+                let lock = app.state::<AppLock>();
+                lock.set_policy(db.config().lock_idle_timeout(), db.config().lock_on_hide);
+                if let Err(e) = slots::register_slot_shortcuts(&app, db.config()) {
+                    eprintln!("Failed to register slot shortcuts: {}", e);
                 }
-                if content.contains("@") && content.contains(".") {
-                    tags.push("email".to_string());
+                let _ = app.emit(CONFIG_RELOADED_EVENT, db.config());
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("Keeping previous config: {}", e),
+        }
+    }
+}
+
+// Embeds chunks for long clips in the background. The database lock is
+// taken per clip so a slow embedding call doesn't stall the UI for a batch.
+async fn start_chunk_worker(db: DbState) {
+    loop {
+        tokio::time::sleep(CHUNK_WORKER_INTERVAL).await;
+
+        if db.lock().await.is_read_only() {
+            continue;
+        }
+        let pending = match db.lock().await.clips_needing_chunks(CHUNK_WORKER_BATCH).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                eprintln!("Failed to find clips to chunk: {}", e);
+                continue;
+            }
+        };
+
+        for id in pending {
+            let db = db.lock().await;
+            if db.is_read_only() {
+                break;
+            }
+            if let Err(e) = db.refresh_chunks(&id).await {
+                // Most likely Ollama is unavailable; retry on the next round
+                eprintln!("Failed to embed chunks for {}: {}", id, e);
+                break;
+            }
+        }
+    }
+}
+
+// Rewrites collection descriptions that have gone stale, one collection
+// per lock like the chunk worker. Collections never described are left alone.
+async fn start_collection_describer(db: DbState) {
+    loop {
+        tokio::time::sleep(COLLECTION_DESCRIBER_INTERVAL).await;
+
+        if db.lock().await.is_read_only() {
+            continue;
+        }
+        let stale = match db.lock().await.collections_needing_description().await {
+            Ok(ids) => ids,
+            Err(e) => {
+                eprintln!("Failed to find stale collection descriptions: {}", e);
+                continue;
+            }
+        };
+
+        for id in stale {
+            let db = db.lock().await;
+            if db.is_read_only() {
+                break;
+            }
+            if let Err(e) = db.describe_collection(&id).await {
+                eprintln!("Failed to describe collection {}: {}", id, e);
+                break;
+            }
+        }
+    }
+}
+
+// Marks the clipboard's current text as captured when it is what the last
+// session stored most recently, so a restart doesn't store it twice.
+async fn skip_if_already_captured(
+    db: &Database,
+    clipboard: &mut impl ClipboardSource,
+    pipeline: &mut CapturePipeline,
+) -> anyhow::Result<()> {
+    if let Some(ClipboardText { text, .. }) = clipboard.read(db.config().non_utf8_clipboard)? {
+        if db.is_latest_clip(&text).await? {
+            pipeline.mark_captured(text);
+        }
+    }
+    Ok(())
+}
+
+// Watches one selection. The primary-selection monitor idles while its
+// setting is off, and only captures text that stayed selected for a whole
+// poll so a selection being dragged out isn't stored piece by piece.
+async fn start_clipboard_monitor(
+    app: tauri::AppHandle,
+    db: DbState,
+    self_write: SelfWriteState,
+    last_capture: LastCapture,
+    mut clipboard: impl ClipboardSource,
+) {
+    let primary = clipboard.selection() == Selection::Primary;
+    let source = if primary { capture::PRIMARY_SELECTION_SOURCE } else { capture::CLIPBOARD_SOURCE };
+
+    let mut pipeline = CapturePipeline::new(clipboard.selection(), self_write, last_capture);
+    let mut reported_unreadable = false;
+
+    // Whatever was on the clipboard at startup is captured by the first poll
+    // unless the previous session already stored it
+    if !primary {
+        if let Err(e) = skip_if_already_captured(&db.lock().await, &mut clipboard, &mut pipeline).await {
+            eprintln!("Failed to check clipboard against the last clip: {}", e);
+        }
+    }
+
+    let mut poll_interval = db.lock().await.config().clipboard_poll_interval();
+
+    loop {
+        tokio::time::sleep(poll_interval).await;
+
+        let (policy, interval, read_only, enabled, min_bytes) = {
+            let db = db.lock().await;
+            let config = db.config();
+            (
+                config.non_utf8_clipboard,
+                config.clipboard_poll_interval(),
+                db.is_read_only(),
+                !primary || config.primary_selection_enabled,
+                if primary { config.primary_selection_min_bytes } else { 0 },
+            )
+        };
+        poll_interval = interval;
+        if !enabled {
+            continue;
+        }
+        let read = match clipboard.read(policy) {
+            Ok(read) => {
+                reported_unreadable = false;
+                read
+            }
+            Err(e) => {
+                // Report once per unreadable copy rather than every poll
+                if !reported_unreadable {
+                    eprintln!("Skipping clipboard content: {}", e);
+                    reported_unreadable = true;
                 }
-                if content.len() > 200 {
-                    tags.push("long-text".to_string());
+                None
+            }
+        };
+
+        if let Some(ClipboardText { text: content, lossy }) = read {
+            // Paused by the user or while a backup is browsed read-only.
+            // Copies made meanwhile count as seen, so they (or text copied out
+            // of the backup) aren't captured on resume.
+            if read_only || app.state::<MonitorPause>().is_paused() {
+                pipeline.mark_seen(content);
+                continue;
+            }
+
+            if pipeline.admit(&content, min_bytes) {
+                let capture_started = std::time::Instant::now();
+                let config = db.lock().await.config().clone();
+
+                // Only look up the source app when some app has a policy
+                if !config.app_capture_modes.is_empty() {
+                    let app_name = browser::frontmost_app(Duration::from_millis(config.browser_url_timeout_ms)).await;
+                    match app_name.as_deref().map(|name| config.capture_mode_for(name)) {
+                        Some(AppCaptureMode::Exclude) => continue,
+                        Some(AppCaptureMode::HashOnly) => {
+                            match db.lock().await.insert_hash_only_clip(&content, Some(source)).await {
+                                Ok(_) => notify::clip_redacted(&app, &config.notification_rules, app_name.as_deref().unwrap_or(source)),
+                                Err(e) => {
+                                    eprintln!("Failed to record hash-only clip: {}", e);
+                                    notify::capture_failed(&app, &config.notification_rules, &e.to_string());
+                                }
+                            }
+                            continue;
+                        }
+                        Some(AppCaptureMode::Capture) | None => {}
+                    }
                 }
 
-                let clip_item = ClipItem {
-                    id: Uuid::new_v4().to_string(),
-                    content: content.clone(),
-                    summary,
-                    tags,
-                    timestamp: Utc::now(),
-                    source: Some("clipboard".to_string()),
-                    embedding: Some(content
-                        .chars()
-                        .map(|c| c as u32 as f32 / 255.0)
-                        .collect()),
+                // Opt-in: ask the frontmost browser which page this came from
+                let source_url = if config.capture_browser_urls {
+                    browser::active_tab_url(
+                        &config.browser_url_browsers,
+                        Duration::from_millis(config.browser_url_timeout_ms),
+                    )
+                    .await
+                } else {
+                    None
                 };
 
-                let db = db.lock().await;
-                if let Err(e) = db.insert_clip(&clip_item).await {
-                    eprintln!("Failed to insert clip: {}", e);
+                let clip_item = capture::build_clip(&content, lossy, source, source_url, &config);
+
+                if let Some(notice) = capture::large_clip_notice(&clip_item, &config) {
+                    let _ = app.emit(LARGE_CLIP_EVENT, notice);
                 }
+
+                let Some(grace) = config.capture_grace() else {
+                    commit_capture(&app, &db, &clip_item, capture_started.elapsed()).await;
+                    continue;
+                };
+
+                // Hold the clip so it can still be canceled; a newer copy
+                // replaces it and this timer then finds nothing to save
+                let prepared_in = capture_started.elapsed();
+                let _ = app.emit(CAPTURE_PENDING_EVENT, PendingNotice {
+                    clip: ClipPreview::new(&clip_item, config.preview_chars),
+                    commit_in_ms: config.capture_grace_ms,
+                });
+                let pending = app.state::<PendingCapture>().inner().clone();
+                let generation = pending.hold(clip_item);
+                let db = db.clone();
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Some(clip) = pending.take_after(generation, grace).await {
+                        commit_capture(&app, &db, &clip, prepared_in).await;
+                    }
+                });
             }
         }
     }
@@ -123,9 +2394,34 @@ async fn start_clipboard_monitor(db: DbState) {
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(|app, shortcut, event| {
+                    if event.state() != ShortcutState::Pressed {
+                        return;
+                    }
+                    if let Some(slot) = app.state::<SlotShortcuts>().slot_for(shortcut) {
+                        tauri::async_runtime::spawn(slots::copy_slot(app.clone(), slot));
+                    }
+                })
+                .build(),
+        )
         .setup(|app| {
             let app_handle = app.handle().clone();
-            
+            let self_write = SelfWriteState::default();
+            app.manage(self_write.clone());
+            app.manage(RecoveryState::default());
+            app.manage(StartupError::default());
+            app.manage(AppLock::default());
+            app.manage(SlotShortcuts::default());
+            app.manage(PendingCapture::default());
+            app.manage(WipeToken::default());
+            app.manage(ReadOnlyMode::default());
+            app.manage(DedupJobHandle::default());
+            app.manage(MonitorPause::default());
+            app.manage(HomeFeedCache::default());
+
             tauri::async_runtime::spawn(async move {
                 // Initialize database with proper SQLite file URL
                 let data_dir = app_handle.path().app_data_dir().unwrap();
@@ -133,14 +2429,38 @@ pub fn run() {
                 let db_path = data_dir.join("clipsage.db");
                 println!("Attempting to create database at: {}", db_path.display());
                 
-                let database = match Database::new(&format!("sqlite://{}?mode=rwc", db_path.display())).await {
-                    Ok(db) => {
+                let database = match recovery::open_database(&db_path, &DatabaseConfig::default()).await {
+                    Ok((mut db, report)) => {
                         println!("Database initialized successfully!");
+                        db.set_archive_path(data_dir.join("clipsage-archive.db"));
+                        if let Ok(config_dir) = app_handle.path().app_config_dir() {
+                            if let Err(e) = db.set_config_layers(ConfigLayers::load(&config_dir)).await {
+                                eprintln!("Ignoring config file and environment: {}", e);
+                            }
+                        }
+
+                        let lock = app_handle.state::<AppLock>();
+                        lock.set_policy(db.config().lock_idle_timeout(), db.config().lock_on_hide);
+                        match db.get_pin_hash().await {
+                            Ok(hash) => lock.configure(hash),
+                            Err(e) => eprintln!("Failed to load app lock PIN: {}", e),
+                        }
+
+                        if let Err(e) = slots::register_slot_shortcuts(&app_handle, db.config()) {
+                            eprintln!("Failed to register slot shortcuts: {}", e);
+                        }
+                        if let Some(report) = report {
+                            let _ = app_handle.emit("app://recovery", &report);
+                            *app_handle.state::<RecoveryState>().0.lock().unwrap() = Some(report);
+                        }
                         Arc::new(Mutex::new(db))
                     },
                     Err(e) => {
                         eprintln!("Failed to initialize database: {}", e);
                         eprintln!("Current directory: {:?}", std::env::current_dir());
+                        let message = format!("Couldn't open or recreate {}: {}", db_path.display(), e);
+                        let _ = app_handle.emit("app://database-error", &message);
+                        *app_handle.state::<StartupError>().0.lock().unwrap() = Some(message);
                         return;
                     }
                 };
@@ -148,21 +2468,299 @@ pub fn run() {
                 // Store database in app state
                 app_handle.manage(database.clone());
 
+                tauri::async_runtime::spawn(start_maintenance_task(database.clone()));
+                tauri::async_runtime::spawn(start_chunk_worker(database.clone()));
+                tauri::async_runtime::spawn(start_collection_describer(database.clone()));
+                tauri::async_runtime::spawn(start_reminder_scheduler(app_handle.clone(), database.clone()));
+
+                // Logs a warning if search has drifted from the clips table
+                let verify_db = database.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = verify_db.lock().await.verify_fts_sync().await {
+                        eprintln!("Failed to verify the search index: {}", e);
+                    }
+                });
+
+                // First run, or the model changed since the last calibration
+                let calibrate_db = database.clone();
+                tauri::async_runtime::spawn(async move {
+                    let mut db = calibrate_db.lock().await;
+                    if db.embedding_calibration().is_none() {
+                        if let Err(e) = db.calibrate_embeddings().await {
+                            eprintln!("Failed to calibrate embeddings: {}", e);
+                        }
+                    }
+                });
+
+                let config = database.lock().await.config().clone();
+                app_handle.state::<MonitorPause>().apply_autostart(&config);
+                let dedup_job = ClipDedupJob::new(
+                    database.clone(),
+                    config.dedup_interval(),
+                    config.dedup_similarity_threshold,
+                    !config.dedup_job_enabled,
+                );
+                app_handle.manage(dedup_job.clone());
+                let dedup_task = tauri::async_runtime::spawn(dedup_job.run(app_handle.clone()));
+                *app_handle.state::<DedupJobHandle>().0.lock().unwrap() = Some(dedup_task);
+                let config_file = database.lock().await.config_file_path().map(ToOwned::to_owned);
+                if let Some(path) = config_file {
+                    tauri::async_runtime::spawn(start_config_file_watcher(app_handle.clone(), database.clone(), path));
+                }
+
+                let last_capture = LastCapture::default();
+                if cfg!(target_os = "linux") {
+                    match SystemClipboard::new(Selection::Primary) {
+                        Ok(primary) => {
+                            tauri::async_runtime::spawn(start_clipboard_monitor(
+                                app_handle.clone(),
+                                database.clone(),
+                                self_write.clone(),
+                                last_capture.clone(),
+                                primary,
+                            ));
+                        }
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
+
                 println!("Starting clipboard monitoring...");
                 // Start clipboard monitoring
-                start_clipboard_monitor(database).await;
+                match SystemClipboard::new(Selection::Clipboard) {
+                    Ok(clipboard) => {
+                        start_clipboard_monitor(app_handle, database, self_write, last_capture, clipboard).await;
+                    }
+                    Err(e) => eprintln!("{}", e),
+                }
             });
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             greet, 
+            get_app_status,
+            monitoring_status,
+            pause_monitoring,
+            resume_monitoring,
+            open_database_readonly,
+            close_readonly,
+            lock_app,
+            unlock_app,
+            set_app_pin,
+            request_wipe_token,
+            wipe_all_data,
             hide_window, 
             show_window, 
             search_clips, 
+            set_clip_masked,
+            reveal_clip,
+            cancel_last_capture,
+            archive_clips,
+            search_archive,
+            unarchive_clip,
+            delete_clip,
+            delete_clips,
+            list_clips,
+            get_audit_log,
+            clear_audit_log_older_than,
+            assign_slot,
+            clear_slot,
+            get_slots,
+            add_global_pin,
+            remove_global_pin,
+            reorder_global_pins,
+            get_global_pins,
+            split_clip_into_lines,
+            split_clip,
+            merge_clips_into_new,
             get_recent_clips,
-            semantic_search_clips
+            get_recent_clips_grouped,
+            render_clip_html,
+            detect_language,
+            get_clips_by_language,
+            rename_tag,
+            merge_tags,
+            set_clip_reminder,
+            list_reminders,
+            cancel_reminder,
+            get_home_feed,
+            get_recently_edited_clips,
+            semantic_search_clips,
+            semantic_search_vector,
+            get_clip_sentences,
+            get_sentence_embedding,
+            suggest_related,
+            search_clips_with_total,
+            search_clips_page,
+            run_search_benchmark,
+            search_in_collections,
+            semantic_search_in_collections,
+            get_clip_timeline,
+            diff_clips,
+            summarize_text,
+            ask_clips,
+            suggest_search_terms,
+            suggest_tags,
+            get_embed_truncated_clips,
+            get_clips_needing_reindex,
+            calibrate_embeddings,
+            get_embedding_calibration,
+            reembed_clip,
+            re_embed_all,
+            backfill_embeddings,
+            get_metrics,
+            reset_metrics,
+            find_near_duplicates,
+            get_clip_similarity_matrix,
+            get_collection_similarity_matrix,
+            create_collection,
+            get_collection_overlap,
+            merge_collections,
+            auto_create_collections_from_tags,
+            create_collection_rule,
+            delete_collection_rule,
+            list_collection_rules,
+            create_retention_rule,
+            update_retention_rule,
+            delete_retention_rule,
+            list_retention_rules,
+            preview_prune,
+            apply_retention_rules,
+            test_collection_rule,
+            preview_capture,
+            set_collection_auto_file,
+            generate_collection_description,
+            get_auto_filings,
+            undo_auto_file,
+            list_collections,
+            add_clips_to_collection,
+            move_clips_to_collection,
+            remove_clip_from_collection,
+            get_config,
+            get_effective_config,
+            check_ollama_connection,
+            update_config,
+            export_clips_json,
+            export_embeddings,
+            export_filtered,
+            full_export,
+            full_import,
+            import_alfred_clipboard,
+            merge_duplicates,
+            get_source_stats,
+            source_activity,
+            top_terms,
+            #[cfg(debug_assertions)]
+            compare_search_methods,
+            get_tag_usage_heatmap,
+            get_all_clips_heatmap,
+            get_annual_clip_counts,
+            get_daily_clip_counts,
+            check_index,
+            verify_fts_sync,
+            repair_index,
+            regenerate_summaries,
+            copy_clip,
+            rewrite_clip,
+            extract_clip_entities,
+            search_by_entity,
+            set_clip_source_url,
+            get_clip_metadata,
+            update_clip_metadata,
+            get_clips_by_source_url,
+            generate_clip_keywords,
+            search_by_keywords,
+            generate_share_link,
+            revoke_share,
+            list_shares_for_clip,
+            record_clip_access,
+            prune_access_log,
+            prune_old_accesses,
+            get_dedup_job_status,
+            trigger_dedup_now,
+            pause_dedup_job,
+            resume_dedup_job
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                if let Some(handle) = app.state::<DedupJobHandle>().0.lock().unwrap().take() {
+                    handle.abort();
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::tests::FakeClipboard;
+    use crate::database::tests::{test_clip, test_db};
+
+    #[tokio::test]
+    async fn clipboard_suggestions_are_similar_clips_other_than_the_copy() {
+        let (db, _dir) = test_db().await;
+        let copied = test_clip("rust borrow checker");
+        let related = test_clip("rust borrow checker errors");
+        let unrelated = test_clip("zzz jjj qqq yyy");
+        for clip in [&copied, &related, &unrelated] {
+            db.insert_clip(clip).await.unwrap();
+        }
+
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["rust borrow checker"]);
+        let suggestions = related_to_clipboard(&db, &mut clipboard).await.unwrap();
+
+        let ids: Vec<&str> = suggestions.iter().map(|clip| clip.id.as_str()).collect();
+        assert_eq!(ids, [related.id.as_str()]);
+    }
+
+    #[tokio::test]
+    async fn empty_clipboard_has_no_suggestions() {
+        let (db, _dir) = test_db().await;
+        db.insert_clip(&test_clip("anything at all")).await.unwrap();
+
+        let mut empty = FakeClipboard::new(Selection::Clipboard, &[]);
+        assert!(related_to_clipboard(&db, &mut empty).await.unwrap().is_empty());
+        let mut blank = FakeClipboard::new(Selection::Clipboard, &["  \n "]);
+        assert!(related_to_clipboard(&db, &mut blank).await.unwrap().is_empty());
+    }
+
+    // Starts a fresh monitor pipeline against `clipboard` as the app does
+    // on launch, then reports whether its first poll would capture
+    async fn first_poll_after_restart(db: &Database, clipboard: &mut FakeClipboard) -> bool {
+        let mut pipeline = CapturePipeline::new(Selection::Clipboard, SelfWriteState::default(), LastCapture::default());
+        skip_if_already_captured(db, clipboard, &mut pipeline).await.unwrap();
+
+        let read = clipboard.read(db.config().non_utf8_clipboard).unwrap().unwrap();
+        pipeline.admit(&read.text, 0)
+    }
+
+    #[tokio::test]
+    async fn restart_with_unchanged_clipboard_captures_nothing() {
+        let (db, _dir) = test_db().await;
+        db.insert_clip(&test_clip("older copy")).await.unwrap();
+        db.insert_clip(&test_clip("still on the clipboard")).await.unwrap();
+
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["still on the clipboard"]);
+        assert!(!first_poll_after_restart(&db, &mut clipboard).await);
+
+        db.insert_hash_only_clip("hash-only secret", None).await.unwrap();
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["hash-only secret"]);
+        assert!(!first_poll_after_restart(&db, &mut clipboard).await);
+    }
+
+    #[tokio::test]
+    async fn restart_with_changed_clipboard_captures_it() {
+        let (db, _dir) = test_db().await;
+        db.insert_clip(&test_clip("stored last session")).await.unwrap();
+
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["copied while closed"]);
+        assert!(first_poll_after_restart(&db, &mut clipboard).await);
+
+        // Matching an older clip isn't enough; only the latest counts
+        db.insert_clip(&test_clip("newest clip")).await.unwrap();
+        let mut clipboard = FakeClipboard::new(Selection::Clipboard, &["stored last session"]);
+        assert!(first_poll_after_restart(&db, &mut clipboard).await);
+    }
 }
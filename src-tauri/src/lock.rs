@@ -0,0 +1,221 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::SaltString;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use serde::Serialize;
+
+// Failed unlocks wait 1s, 2s, 4s, ... up to this long before the next try.
+const MAX_UNLOCK_DELAY: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockError {
+    /// Returned by data commands while the history is locked
+    Locked,
+    WrongPin,
+    /// Too many failed attempts; try again after this long
+    RateLimited(Duration),
+    NotConfigured,
+}
+
+impl fmt::Display for LockError {
+    // `locked` is matched by the frontend to show the unlock screen
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::Locked => write!(f, "locked"),
+            LockError::WrongPin => write!(f, "Incorrect PIN"),
+            LockError::RateLimited(wait) => {
+                write!(f, "Too many attempts, try again in {}s", wait.as_secs().max(1))
+            }
+            LockError::NotConfigured => write!(f, "App lock is not enabled"),
+        }
+    }
+}
+
+impl std::error::Error for LockError {}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockStatus {
+    pub enabled: bool,
+    pub locked: bool,
+    pub failed_attempts: u32,
+    /// Seconds until another unlock attempt is accepted
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Debug)]
+struct LockInner {
+    pin_hash: Option<String>,
+    locked: bool,
+    idle_timeout: Option<Duration>,
+    lock_on_hide: bool,
+    last_activity: Instant,
+    failed_attempts: u32,
+    retry_at: Option<Instant>,
+}
+
+impl LockInner {
+    // Checks `pin` under the failed-attempt backoff
+    fn attempt(&mut self, pin: &str) -> Result<(), LockError> {
+        let hash = self.pin_hash.as_deref().ok_or(LockError::NotConfigured)?;
+
+        if let Some(retry_at) = self.retry_at {
+            let now = Instant::now();
+            if retry_at > now {
+                return Err(LockError::RateLimited(retry_at - now));
+            }
+        }
+
+        if verify_pin(pin, hash) {
+            self.failed_attempts = 0;
+            self.retry_at = None;
+            Ok(())
+        } else {
+            self.failed_attempts += 1;
+            let delay = Duration::from_secs(1u64 << (self.failed_attempts - 1).min(16)).min(MAX_UNLOCK_DELAY);
+            self.retry_at = Some(Instant::now() + delay);
+            Err(LockError::WrongPin)
+        }
+    }
+
+    fn lock_if_idle(&mut self) {
+        if let (Some(timeout), true) = (self.idle_timeout, self.pin_hash.is_some()) {
+            if self.last_activity.elapsed() >= timeout {
+                self.locked = true;
+            }
+        }
+    }
+}
+
+/// Optional PIN lock over the history window. Only the UI is gated; the
+/// clipboard monitor keeps capturing while locked.
+#[derive(Debug)]
+pub struct AppLock(Mutex<LockInner>);
+
+impl Default for AppLock {
+    fn default() -> Self {
+        AppLock(Mutex::new(LockInner {
+            pin_hash: None,
+            locked: false,
+            idle_timeout: None,
+            lock_on_hide: true,
+            last_activity: Instant::now(),
+            failed_attempts: 0,
+            retry_at: None,
+        }))
+    }
+}
+
+impl AppLock {
+    /// Installs the stored PIN hash. An enabled lock starts out locked.
+    pub fn configure(&self, pin_hash: Option<String>) {
+        let mut inner = self.0.lock().unwrap();
+        inner.locked = pin_hash.is_some();
+        inner.pin_hash = pin_hash;
+        inner.last_activity = Instant::now();
+    }
+
+    pub fn set_policy(&self, idle_timeout: Option<Duration>, lock_on_hide: bool) {
+        let mut inner = self.0.lock().unwrap();
+        inner.idle_timeout = idle_timeout;
+        inner.lock_on_hide = lock_on_hide;
+    }
+
+    /// Replaces (or with `None`, removes) the PIN hash without locking.
+    pub fn set_pin_hash(&self, pin_hash: Option<String>) {
+        let mut inner = self.0.lock().unwrap();
+        inner.pin_hash = pin_hash;
+        inner.locked = false;
+        inner.failed_attempts = 0;
+        inner.retry_at = None;
+    }
+
+    /// Gate for data-returning commands. Locks first if the idle period has
+    /// passed, otherwise counts the call as activity.
+    pub fn check(&self) -> Result<(), LockError> {
+        let mut inner = self.0.lock().unwrap();
+        if inner.pin_hash.is_none() {
+            return Ok(());
+        }
+
+        inner.lock_if_idle();
+        if inner.locked {
+            return Err(LockError::Locked);
+        }
+
+        inner.last_activity = Instant::now();
+        Ok(())
+    }
+
+    pub fn lock(&self) {
+        let mut inner = self.0.lock().unwrap();
+        if inner.pin_hash.is_some() {
+            inner.locked = true;
+        }
+    }
+
+    /// Called when the history window is hidden.
+    pub fn window_hidden(&self) {
+        let mut inner = self.0.lock().unwrap();
+        if inner.pin_hash.is_some() && inner.lock_on_hide {
+            inner.locked = true;
+        }
+    }
+
+    pub fn unlock(&self, pin: &str) -> Result<(), LockError> {
+        let mut inner = self.0.lock().unwrap();
+        if inner.pin_hash.is_none() {
+            return Err(LockError::NotConfigured);
+        }
+
+        inner.attempt(pin)?;
+        inner.locked = false;
+        inner.last_activity = Instant::now();
+        Ok(())
+    }
+
+    /// Re-confirms the PIN for sensitive actions (changing it, revealing a
+    /// masked clip). Shares the unlock backoff; always passes when no PIN
+    /// is set.
+    pub fn confirm_pin(&self, pin: Option<&str>) -> Result<(), LockError> {
+        let mut inner = self.0.lock().unwrap();
+        if inner.pin_hash.is_none() {
+            return Ok(());
+        }
+
+        inner.attempt(pin.unwrap_or_default())
+    }
+
+    pub fn status(&self) -> LockStatus {
+        let mut inner = self.0.lock().unwrap();
+        inner.lock_if_idle();
+
+        let now = Instant::now();
+        LockStatus {
+            enabled: inner.pin_hash.is_some(),
+            locked: inner.locked,
+            failed_attempts: inner.failed_attempts,
+            retry_after_secs: inner
+                .retry_at
+                .filter(|retry_at| *retry_at > now)
+                .map(|retry_at| (retry_at - now).as_secs().max(1)),
+        }
+    }
+}
+
+pub fn hash_pin(pin: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow!("Failed to hash PIN: {}", e))
+}
+
+fn verify_pin(pin: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(parsed) => Argon2::default().verify_password(pin.as_bytes(), &parsed).is_ok(),
+        Err(_) => false,
+    }
+}
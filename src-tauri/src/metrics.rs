@@ -0,0 +1,254 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+// Latency samples kept per operation; percentiles cover the most recent ones.
+const MAX_SAMPLES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Capture,
+    Insert,
+    Embedding,
+    Search,
+}
+
+#[derive(Debug, Default)]
+struct OperationStats {
+    count: u64,
+    errors: u64,
+    samples: VecDeque<Duration>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationMetrics {
+    pub operation: Operation,
+    pub count: u64,
+    pub errors: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSnapshot {
+    pub enabled: bool,
+    pub operations: Vec<OperationMetrics>,
+}
+
+/// In-memory counters and latency samples. When disabled, recording is a
+/// single atomic load.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    enabled: AtomicBool,
+    operations: Mutex<HashMap<Operation, OperationStats>>,
+}
+
+impl Metrics {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn record(&self, operation: Operation, elapsed: Duration, success: bool) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut operations = self.operations.lock().unwrap();
+        let stats = operations.entry(operation).or_default();
+        stats.count += 1;
+        if !success {
+            stats.errors += 1;
+        }
+        if stats.samples.len() == MAX_SAMPLES {
+            stats.samples.pop_front();
+        }
+        stats.samples.push_back(elapsed);
+    }
+
+    /// Awaits `future`, recording its latency and whether it returned `Ok`.
+    pub async fn time<T, E>(&self, operation: Operation, future: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return future.await;
+        }
+
+        let started = Instant::now();
+        let result = future.await;
+        self.record(operation, started.elapsed(), result.is_ok());
+        result
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let operations = self.operations.lock().unwrap();
+        let mut summaries: Vec<OperationMetrics> = operations
+            .iter()
+            .map(|(operation, stats)| {
+                let mut sorted: Vec<Duration> = stats.samples.iter().copied().collect();
+                sorted.sort();
+
+                OperationMetrics {
+                    operation: *operation,
+                    count: stats.count,
+                    errors: stats.errors,
+                    p50_ms: percentile_ms(&sorted, 0.50),
+                    p95_ms: percentile_ms(&sorted, 0.95),
+                    p99_ms: percentile_ms(&sorted, 0.99),
+                    max_ms: sorted.last().map_or(0.0, |max| max.as_secs_f64() * 1000.0),
+                }
+            })
+            .collect();
+        summaries.sort_by_key(|summary| summary.operation as u8);
+
+        MetricsSnapshot {
+            enabled: self.enabled.load(Ordering::Relaxed),
+            operations: summaries,
+        }
+    }
+
+    pub fn reset(&self) {
+        self.operations.lock().unwrap().clear();
+    }
+}
+
+/// Per-stage durations of one search. Stages a search mode doesn't run
+/// stay at zero.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SearchTimings {
+    pub fts_ms: f64,
+    pub embedding_ms: f64,
+    /// Scoring stored embeddings against the query
+    pub similarity_ms: f64,
+    /// Merging and deduplicating text and semantic results
+    pub fusion_ms: f64,
+    pub total_ms: f64,
+}
+
+impl SearchTimings {
+    fn stages(&self) -> [(&'static str, f64); 5] {
+        [
+            ("fts", self.fts_ms),
+            ("embedding", self.embedding_ms),
+            ("similarity", self.similarity_ms),
+            ("fusion", self.fusion_ms),
+            ("total", self.total_ms),
+        ]
+    }
+}
+
+impl std::fmt::Display for SearchTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = self
+            .stages()
+            .iter()
+            .map(|(stage, ms)| format!("{} {:.1} ms", stage, ms))
+            .collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageLatency {
+    pub stage: &'static str,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Latency percentiles per search stage over every run of a benchmark.
+pub fn stage_latencies(runs: &[SearchTimings]) -> Vec<StageLatency> {
+    let stage_names = SearchTimings::default().stages().map(|(stage, _)| stage);
+    stage_names
+        .iter()
+        .enumerate()
+        .map(|(index, stage)| {
+            let mut sorted: Vec<Duration> = runs
+                .iter()
+                .map(|run| Duration::from_secs_f64(run.stages()[index].1 / 1000.0))
+                .collect();
+            sorted.sort();
+
+            StageLatency {
+                stage: *stage,
+                p50_ms: percentile_ms(&sorted, 0.50),
+                p95_ms: percentile_ms(&sorted, 0.95),
+                p99_ms: percentile_ms(&sorted, 0.99),
+                max_ms: sorted.last().map_or(0.0, |max| max.as_secs_f64() * 1000.0),
+            }
+        })
+        .collect()
+}
+
+/// Milliseconds elapsed since `started`, for `SearchTimings` fields.
+pub fn elapsed_ms(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
+
+// Nearest-rank percentile over already sorted samples
+fn percentile_ms(sorted: &[Duration], quantile: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((quantile * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1].as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(metrics: &Metrics, operation: Operation) -> Option<(u64, u64)> {
+        metrics
+            .snapshot()
+            .operations
+            .iter()
+            .find(|summary| summary.operation == operation)
+            .map(|summary| (summary.count, summary.errors))
+    }
+
+    #[tokio::test]
+    async fn counters_increment_across_operations() {
+        let metrics = Metrics::default();
+        metrics.set_enabled(true);
+
+        for _ in 0..3 {
+            let ok: Result<(), ()> = metrics.time(Operation::Search, async { Ok(()) }).await;
+            assert!(ok.is_ok());
+        }
+        let failed: Result<(), &str> = metrics.time(Operation::Search, async { Err("boom") }).await;
+        assert!(failed.is_err());
+        metrics.record(Operation::Insert, Duration::from_millis(5), true);
+        metrics.record(Operation::Insert, Duration::from_millis(15), true);
+
+        assert_eq!(counts(&metrics, Operation::Search), Some((4, 1)));
+        assert_eq!(counts(&metrics, Operation::Insert), Some((2, 0)));
+        assert_eq!(counts(&metrics, Operation::Embedding), None);
+
+        let insert = metrics
+            .snapshot()
+            .operations
+            .into_iter()
+            .find(|summary| summary.operation == Operation::Insert)
+            .unwrap();
+        assert_eq!(insert.max_ms, 15.0);
+    }
+
+    #[test]
+    fn nothing_is_counted_while_disabled() {
+        let metrics = Metrics::default();
+        metrics.record(Operation::Capture, Duration::from_millis(1), true);
+        assert!(metrics.snapshot().operations.is_empty());
+
+        metrics.set_enabled(true);
+        metrics.record(Operation::Capture, Duration::from_millis(1), true);
+        assert_eq!(counts(&metrics, Operation::Capture), Some((1, 0)));
+
+        metrics.reset();
+        assert!(metrics.snapshot().operations.is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use tauri_plugin_notification::NotificationExt;
+use crate::config::NotificationRules;
+use crate::database::ClipItem;
+
+/// Notifies if `clip` carries one of the tags in `rules.on_tags`. Masked
+/// clips never put their summary in the notification.
+pub fn clip_captured(app: &tauri::AppHandle, rules: &NotificationRules, clip: &ClipItem) {
+    let Some(tag) = clip
+        .tags
+        .iter()
+        .find(|tag| rules.on_tags.iter().any(|wanted| wanted.eq_ignore_ascii_case(tag)))
+    else {
+        return;
+    };
+
+    let body = if clip.masked { "Masked clip" } else { clip.summary.as_str() };
+    show(app, &format!("Captured a `{}` clip", tag), body);
+}
+
+/// Notifies that a copy was stored as a hash only. The content itself is
+/// never shown.
+pub fn clip_redacted(app: &tauri::AppHandle, rules: &NotificationRules, source_app: &str) {
+    if rules.on_redaction {
+        show(app, "Copy redacted", &format!("Only a hash of a copy from {} was stored", source_app));
+    }
+}
+
+/// Always shown: the user asked for it. `overdue` marks reminders that came
+/// due while the app wasn't running.
+pub fn reminder_due(app: &tauri::AppHandle, clip: &ClipItem, overdue: bool) {
+    let title = if overdue { "Reminder (overdue)" } else { "Reminder" };
+    let body = if clip.masked { "Masked clip" } else { clip.summary.as_str() };
+    show(app, title, body);
+}
+
+pub fn capture_failed(app: &tauri::AppHandle, rules: &NotificationRules, error: &str) {
+    if rules.on_capture_failure {
+        show(app, "Capture failed", error);
+    }
+}
+
+// Notifications are best effort: do-not-disturb or missing permission
+// shouldn't affect capture, so failures are only logged
+fn show(app: &tauri::AppHandle, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        eprintln!("Failed to show notification: {}", e);
+    }
+}
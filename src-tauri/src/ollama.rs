@@ -1,8 +1,31 @@
-use anyhow::Result;
+use std::fmt;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use reqwest::Client;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{Certificate, Client, Proxy, StatusCode};
+use crate::config::AppConfig;
 
-const OLLAMA_API_URL: &str = "http://localhost:11434";
+/// Where Ollama listens unless `ollama_url` says otherwise
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+/// Model used for embeddings and generation unless `ollama_model` says otherwise
+pub const DEFAULT_OLLAMA_MODEL: &str = "nomic-embed-text";
+
+// How long the connection check waits for Ollama to answer
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Upper bound on the prompt sent when suggesting related searches
+const SUGGESTION_PROMPT_LIMIT: usize = 1500;
+
+// Longest query quoted in that prompt, leaving the rest for clip context
+const SUGGESTION_QUERY_LIMIT: usize = 300;
+
+// How many clip summaries, and how much of each, describe a collection
+const COLLECTION_SUMMARY_CLIPS: usize = 20;
+const COLLECTION_SUMMARY_CHARS: usize = 100;
 
 #[derive(Debug, Serialize)]
 struct EmbeddingRequest {
@@ -15,19 +38,160 @@ struct EmbeddingResponse {
     embedding: Vec<f32>,
 }
 
+#[derive(Debug, Serialize)]
+struct BatchEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [&'a str],
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchEmbeddingResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entity {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    #[serde(rename = "value")]
+    pub entity_value: String,
+}
+
+/// The model's pick for where a clip belongs; `collection` is `None` when
+/// nothing fits.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CollectionGuess {
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub confidence: f32,
+}
+
+/// Why the Ollama connection settings don't work. Serialized with a `kind`
+/// so the settings screen can point at the field at fault.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum OllamaError {
+    InvalidProxy(String),
+    CaCertUnreadable(String),
+    CaCertInvalid(String),
+    /// The bearer token can't be sent as a header
+    InvalidToken,
+    /// A proxy is configured and the connection failed
+    ProxyUnreachable(String),
+    Unreachable(String),
+    /// Ollama (or the proxy in front of it) refused the bearer token
+    Unauthorized,
+    Unexpected(String),
+}
+
+impl fmt::Display for OllamaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OllamaError::InvalidProxy(e) => write!(f, "Invalid proxy: {}", e),
+            OllamaError::CaCertUnreadable(e) => write!(f, "Can't read CA certificate: {}", e),
+            OllamaError::CaCertInvalid(e) => write!(f, "Invalid CA certificate: {}", e),
+            OllamaError::InvalidToken => write!(f, "Bearer token contains characters not allowed in a header"),
+            OllamaError::ProxyUnreachable(e) => write!(f, "Can't reach Ollama through the proxy: {}", e),
+            OllamaError::Unreachable(e) => write!(f, "Can't reach Ollama: {}", e),
+            OllamaError::Unauthorized => write!(f, "Ollama rejected the bearer token"),
+            OllamaError::Unexpected(e) => write!(f, "Unexpected response from Ollama: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for OllamaError {}
+
+#[derive(Debug, Clone)]
 pub struct OllamaClient {
     client: Client,
+    base_url: String,
     model: String,
+    // Set when requests go through a proxy, to tell proxy failures apart
+    proxied: bool,
 }
 
 impl OllamaClient {
-    pub fn new(model: &str) -> Self {
+    pub fn new(base_url: &str, model: &str) -> Self {
         Self {
             client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
             model: model.to_string(),
+            proxied: env_proxy_configured(),
+        }
+    }
+
+    /// A client for the configured server, proxy, CA certificate and bearer
+    /// token. Without `ollama_proxy`, the usual `HTTP(S)_PROXY` environment
+    /// variables apply.
+    pub fn from_config(config: &AppConfig) -> Result<Self, OllamaError> {
+        let mut builder = Client::builder();
+
+        let proxied = match config.ollama_proxy.as_deref().filter(|proxy| !proxy.is_empty()) {
+            Some(proxy) => {
+                builder = builder.proxy(Proxy::all(proxy).map_err(|e| OllamaError::InvalidProxy(e.to_string()))?);
+                true
+            }
+            None => env_proxy_configured(),
+        };
+
+        if let Some(path) = config.ollama_ca_cert_path.as_deref().filter(|path| !path.is_empty()) {
+            let bytes = std::fs::read(path).map_err(|e| OllamaError::CaCertUnreadable(format!("{}: {}", path, e)))?;
+            let certificate = Certificate::from_pem(&bytes)
+                .or_else(|_| Certificate::from_der(&bytes))
+                .map_err(|e| OllamaError::CaCertInvalid(format!("{}: {}", path, e)))?;
+            builder = builder.add_root_certificate(certificate);
+        }
+
+        if let Some(token) = config.ollama_bearer_token.as_deref().filter(|token| !token.is_empty()) {
+            let mut value =
+                HeaderValue::from_str(&format!("Bearer {}", token)).map_err(|_| OllamaError::InvalidToken)?;
+            value.set_sensitive(true);
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, value);
+            builder = builder.default_headers(headers);
+        }
+
+        Ok(Self {
+            client: builder.build().map_err(|e| OllamaError::Unexpected(e.to_string()))?,
+            base_url: config.ollama_url.trim_end_matches('/').to_string(),
+            model: config.ollama_model.clone(),
+            proxied,
+        })
+    }
+
+    /// Asks Ollama for its version to confirm the connection settings work.
+    pub async fn check_health(&self) -> Result<(), OllamaError> {
+        let response = self
+            .client
+            .get(format!("{}/api/version", self.base_url))
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| {
+                if self.proxied && (e.is_connect() || e.is_timeout()) {
+                    OllamaError::ProxyUnreachable(e.to_string())
+                } else if e.is_connect() || e.is_timeout() {
+                    OllamaError::Unreachable(e.to_string())
+                } else {
+                    OllamaError::Unexpected(e.to_string())
+                }
+            })?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(OllamaError::Unauthorized),
+            StatusCode::PROXY_AUTHENTICATION_REQUIRED => {
+                Err(OllamaError::ProxyUnreachable("proxy requires authentication".to_string()))
+            }
+            status => Err(OllamaError::Unexpected(status.to_string())),
         }
     }
 
+    /// Name of the embedding model this client asks Ollama for.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
     pub async fn get_embedding(&self, text: &str) -> Result<Vec<f32>> {
         let request = EmbeddingRequest {
             model: self.model.clone(),
@@ -35,7 +199,7 @@ impl OllamaClient {
         };
 
         let response = self.client
-            .post(format!("{}/api/embeddings", OLLAMA_API_URL))
+            .post(format!("{}/api/embeddings", self.base_url))
             .json(&request)
             .send()
             .await?
@@ -45,20 +209,203 @@ impl OllamaClient {
         Ok(response.embedding)
     }
 
-    pub async fn generate_summary(&self, text: &str) -> Result<String> {
+    /// Embeds all of `texts` in one request, returning vectors in the same
+    /// order. Ollama versions without `/api/embed` answer 404; for those
+    /// each text is embedded on its own through `get_embedding`.
+    pub async fn batch_embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let request = BatchEmbeddingRequest {
+            model: &self.model,
+            input: texts,
+        };
+
+        let response = self.client
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            let mut embeddings = Vec::with_capacity(texts.len());
+            for text in texts {
+                embeddings.push(self.get_embedding(text).await?);
+            }
+            return Ok(embeddings);
+        }
+        let response = response
+            .error_for_status()?
+            .json::<BatchEmbeddingResponse>()
+            .await?;
+
+        if response.embeddings.len() != texts.len() {
+            return Err(anyhow!(
+                "Expected {} embeddings, got {}",
+                texts.len(),
+                response.embeddings.len()
+            ));
+        }
+
+        Ok(response.embeddings)
+    }
+
+    /// Summarizes `text`, capping the output at `max_tokens` via `num_predict`.
+    pub async fn generate_summary(&self, text: &str, max_tokens: u32) -> Result<String> {
         let prompt = format!(
             "Summarize the following text in one short sentence:\n\n{}",
             text
         );
 
-        let request = serde_json::json!({
+        self.generate_with_limit(&prompt, Some(max_tokens)).await
+    }
+
+    pub async fn rewrite(&self, text: &str, instruction: &str) -> Result<String> {
+        let prompt = format!(
+            "Rewrite the following text according to this instruction: {}\n\n{}",
+            instruction, text
+        );
+
+        Ok(self.generate(&prompt).await?.trim().to_string())
+    }
+
+    /// Runs named entity recognition over `text` (people, organizations,
+    /// places, dates, products, ...).
+    pub async fn extract_entities(&self, text: &str) -> Result<Vec<Entity>> {
+        let prompt = format!(
+            "Extract the named entities (person, organization, location, date, product) from the text below. \
+             Respond only with a JSON array of objects with \"type\" and \"value\" fields.\n\n{}",
+            text
+        );
+
+        let response = self.generate(&prompt).await?;
+        let entities: Vec<Entity> = parse_json_array(&response)?;
+
+        Ok(entities
+            .into_iter()
+            .map(|entity| Entity {
+                entity_type: entity.entity_type.trim().to_lowercase(),
+                entity_value: entity.entity_value.trim().to_string(),
+            })
+            .filter(|entity| !entity.entity_type.is_empty() && !entity.entity_value.is_empty())
+            .collect())
+    }
+
+    /// Keyphrases for `text`, more specific than tags and meant for search.
+    pub async fn generate_keywords(&self, text: &str, max_keywords: u8) -> Result<Vec<String>> {
+        let prompt = format!(
+            "List up to {} specific keyphrases from this text as a JSON array of strings:\n\n{}",
+            max_keywords, text
+        );
+
+        let response = self.generate(&prompt).await?;
+        let mut keywords: Vec<String> = Vec::new();
+        for keyword in parse_string_array(&response)? {
+            if !keywords.iter().any(|existing| existing.eq_ignore_ascii_case(&keyword)) {
+                keywords.push(keyword);
+            }
+        }
+        keywords.truncate(max_keywords as usize);
+
+        Ok(keywords)
+    }
+
+    /// Answers `question` from the numbered `sources` only, asking the model
+    /// to cite them as `[1]`, `[2]`, ... after each claim.
+    pub async fn answer_with_citations(&self, question: &str, sources: &[&str]) -> Result<String> {
+        let mut prompt = String::from(
+            "Answer the question using only the numbered sources below. After each claim, cite the \
+             source it came from as [1], [2], etc. If the sources don't contain the answer, say so.\n\n",
+        );
+        for (index, source) in sources.iter().enumerate() {
+            prompt.push_str(&format!("[{}]\n{}\n\n", index + 1, source));
+        }
+        prompt.push_str(&format!("Question: {}\nAnswer:", question));
+
+        Ok(self.generate(&prompt).await?.trim().to_string())
+    }
+
+    /// Picks which of `collections` (by name) a clip with `summary` belongs
+    /// in, if any.
+    pub async fn classify_collection(&self, summary: &str, collections: &[&str]) -> Result<CollectionGuess> {
+        let mut prompt = String::from(
+            "Which of these collections does the clip below belong in? Respond only with a JSON object \
+             like {\"collection\": \"name\", \"confidence\": 0.9}, using null for the collection if none fits.\n\n\
+             Collections:\n",
+        );
+        for name in collections {
+            prompt.push_str(&format!("- {}\n", name));
+        }
+        prompt.push_str(&format!("\nClip: {}", summary));
+
+        let response = self.generate(&prompt).await?;
+        let start = response.find('{').ok_or_else(|| anyhow!("no JSON object in model response"))?;
+        let end = response.rfind('}').ok_or_else(|| anyhow!("no JSON object in model response"))?;
+        if end < start {
+            return Err(anyhow!("no JSON object in model response"));
+        }
+
+        let mut guess: CollectionGuess = serde_json::from_str(&response[start..=end])?;
+        guess.confidence = guess.confidence.clamp(0.0, 1.0);
+        Ok(guess)
+    }
+
+    /// Suggests 3-5 alternative search terms for `query`, using a few clip
+    /// summaries as context. Context is truncated so the prompt stays small.
+    pub async fn suggest_related_queries(&self, query: &str, context_clips: &[&str]) -> Result<Vec<String>> {
+        let mut prompt = format!(
+            "A user searched their clipboard history for \"{}\" and found few results. \
+             Suggest 3 to 5 related search terms. Respond only with a JSON array of strings.\n\n\
+             Related clips:\n",
+            truncate_bytes(query, SUGGESTION_QUERY_LIMIT)
+        );
+
+        for clip in context_clips {
+            let remaining = SUGGESTION_PROMPT_LIMIT.saturating_sub(prompt.len() + 3);
+            if remaining == 0 {
+                break;
+            }
+            prompt.push_str("- ");
+            prompt.push_str(truncate_bytes(clip, remaining));
+            prompt.push('\n');
+        }
+
+        let response = self.generate(&prompt).await?;
+        Ok(parse_string_array(&response)?.into_iter().take(5).collect())
+    }
+
+    /// A 2-3 sentence description of what a collection is about, from the
+    /// summaries of (up to 20 of) its clips.
+    pub async fn summarize_collection(&self, clip_summaries: &[&str]) -> Result<String> {
+        let mut prompt = String::from(
+            "The following are summaries of clips a user saved together in one collection. \
+             Describe the collection's overall theme in 2 to 3 sentences.\n\n",
+        );
+        for summary in clip_summaries.iter().take(COLLECTION_SUMMARY_CLIPS) {
+            prompt.push_str("- ");
+            prompt.extend(summary.chars().take(COLLECTION_SUMMARY_CHARS));
+            prompt.push('\n');
+        }
+
+        Ok(self.generate(&prompt).await?.trim().to_string())
+    }
+
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        self.generate_with_limit(prompt, None).await
+    }
+
+    async fn generate_with_limit(&self, prompt: &str, num_predict: Option<u32>) -> Result<String> {
+        let mut request = serde_json::json!({
             "model": self.model,
             "prompt": prompt,
             "stream": false
         });
+        if let Some(num_predict) = num_predict {
+            request["options"] = serde_json::json!({ "num_predict": num_predict });
+        }
 
         let response = self.client
-            .post(format!("{}/api/generate", OLLAMA_API_URL))
+            .post(format!("{}/api/generate", self.base_url))
             .json(&request)
             .send()
             .await?
@@ -67,4 +414,150 @@ impl OllamaClient {
 
         Ok(response["response"].as_str().unwrap_or("").to_string())
     }
-} 
\ No newline at end of file
+}
+
+/// Turns text into embedding vectors. `OllamaClient` is the real one;
+/// `Database` only needs this, so tests can supply fixed vectors instead.
+pub trait EmbeddingProvider: fmt::Debug + Send + Sync {
+    fn get_embedding<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Vec<f32>>>;
+
+    /// Vectors for `texts`, in the same order.
+    fn batch_embed<'a>(&'a self, texts: &'a [&'a str]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>>;
+}
+
+impl EmbeddingProvider for OllamaClient {
+    fn get_embedding<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Vec<f32>>> {
+        Box::pin(OllamaClient::get_embedding(self, text))
+    }
+
+    fn batch_embed<'a>(&'a self, texts: &'a [&'a str]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(OllamaClient::batch_embed(self, texts))
+    }
+}
+
+fn env_proxy_configured() -> bool {
+    ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]
+        .iter()
+        .any(|var| std::env::var(var).is_ok_and(|value| !value.is_empty()))
+}
+
+// Models often wrap JSON in prose or code fences, so parse the outermost array
+fn parse_json_array<T: DeserializeOwned>(response: &str) -> Result<Vec<T>> {
+    let start = response.find('[').ok_or_else(|| anyhow!("no JSON array in model response"))?;
+    let end = response.rfind(']').ok_or_else(|| anyhow!("no JSON array in model response"))?;
+    if end < start {
+        return Err(anyhow!("no JSON array in model response"));
+    }
+
+    Ok(serde_json::from_str(&response[start..=end])?)
+}
+
+fn parse_string_array(response: &str) -> Result<Vec<String>> {
+    let items: Vec<String> = parse_json_array(response)?;
+    Ok(items
+        .into_iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect())
+}
+
+// At most `max_bytes` of `text`, cut at a char boundary
+fn truncate_bytes(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const MODEL: &str = "nomic-embed-text";
+
+    #[tokio::test]
+    async fn batch_embed_sends_all_texts_in_one_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/embed"))
+            .and(body_json(json!({ "model": MODEL, "input": ["first", "second", "third"] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "embeddings": [[0.1, 0.2], [0.3, 0.4], [0.5, 0.6]]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = OllamaClient::new(&server.uri(), MODEL);
+        let embeddings = client.batch_embed(&["first", "second", "third"]).await.unwrap();
+
+        assert_eq!(embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4], vec![0.5, 0.6]]);
+    }
+
+    #[tokio::test]
+    async fn batch_embed_rejects_a_short_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/embed"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "embeddings": [[0.1, 0.2]] })))
+            .mount(&server)
+            .await;
+
+        let client = OllamaClient::new(&server.uri(), MODEL);
+        assert!(client.batch_embed(&["first", "second"]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn batch_embed_falls_back_to_single_requests_without_the_batch_endpoint() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/embed"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/embeddings"))
+            .and(body_json(json!({ "model": MODEL, "prompt": "first" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "embedding": [1.0, 0.0] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/embeddings"))
+            .and(body_json(json!({ "model": MODEL, "prompt": "second" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "embedding": [0.0, 1.0] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = OllamaClient::new(&server.uri(), MODEL);
+        let embeddings = client.batch_embed(&["first", "second"]).await.unwrap();
+
+        assert_eq!(embeddings, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+    }
+
+    #[tokio::test]
+    async fn generate_summary_sends_the_token_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .and(body_partial_json(json!({ "model": MODEL, "stream": false, "options": { "num_predict": 42 } })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "response": "A short summary." })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = OllamaClient::new(&server.uri(), MODEL);
+        let summary = client.generate_summary("Some long text to summarize", 42).await.unwrap();
+
+        assert_eq!(summary, "A short summary.");
+    }
+}
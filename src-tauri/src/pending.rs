@@ -0,0 +1,106 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::Serialize;
+use crate::database::{ClipItem, ClipPreview};
+
+/// Emitted with a `PendingNotice` when a capture starts its grace period
+pub const CAPTURE_PENDING_EVENT: &str = "capture://pending";
+
+/// Emitted with the clip id when a pending capture is canceled
+pub const CAPTURE_CANCELED_EVENT: &str = "capture://canceled";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingNotice {
+    pub clip: ClipPreview,
+    pub commit_in_ms: u64,
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    generation: u64,
+    clip: Option<ClipItem>,
+}
+
+/// The most recent capture still inside its grace period. Holding a new
+/// clip replaces (and so discards) any clip already waiting.
+#[derive(Debug, Clone, Default)]
+pub struct PendingCapture(Arc<Mutex<Pending>>);
+
+impl PendingCapture {
+    /// Holds `clip` and returns the generation to pass to `take`.
+    pub fn hold(&self, clip: ClipItem) -> u64 {
+        let mut pending = self.0.lock().unwrap();
+        pending.generation += 1;
+        pending.clip = Some(clip);
+        pending.generation
+    }
+
+    /// Takes the held clip if it is still the one from `generation`, i.e. it
+    /// was neither canceled nor superseded.
+    pub fn take(&self, generation: u64) -> Option<ClipItem> {
+        let mut pending = self.0.lock().unwrap();
+        if pending.generation == generation {
+            pending.clip.take()
+        } else {
+            None
+        }
+    }
+
+    /// Waits out `grace`, then takes the clip from `generation` if nothing
+    /// canceled or superseded it in the meantime.
+    pub async fn take_after(&self, generation: u64, grace: Duration) -> Option<ClipItem> {
+        tokio::time::sleep(grace).await;
+        self.take(generation)
+    }
+
+    /// Drops the held clip so it is never saved.
+    pub fn cancel(&self) -> Option<ClipItem> {
+        self.0.lock().unwrap().clip.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::tests::test_clip;
+
+    const GRACE: Duration = Duration::from_secs(5);
+
+    #[tokio::test(start_paused = true)]
+    async fn held_clip_is_committed_after_the_grace_period() {
+        let pending = PendingCapture::default();
+        let clip = test_clip("copied on purpose");
+        let generation = pending.hold(clip.clone());
+
+        let committed = pending.take_after(generation, GRACE).await;
+        assert_eq!(committed.map(|clip| clip.id), Some(clip.id));
+        assert!(pending.cancel().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn canceling_within_the_grace_period_drops_the_clip() {
+        let pending = PendingCapture::default();
+        let clip = test_clip("copied by accident");
+        let generation = pending.hold(clip.clone());
+
+        let waiting = tokio::spawn({
+            let pending = pending.clone();
+            async move { pending.take_after(generation, GRACE).await }
+        });
+        tokio::time::sleep(GRACE / 2).await;
+        assert_eq!(pending.cancel().map(|clip| clip.id), Some(clip.id));
+
+        assert!(waiting.await.unwrap().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_newer_copy_supersedes_the_held_one() {
+        let pending = PendingCapture::default();
+        let first = pending.hold(test_clip("first"));
+        let second_clip = test_clip("second");
+        let second = pending.hold(second_clip.clone());
+
+        assert!(pending.take_after(first, GRACE).await.is_none());
+        assert_eq!(pending.take_after(second, GRACE).await.map(|clip| clip.id), Some(second_clip.id));
+    }
+}
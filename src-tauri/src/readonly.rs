@@ -0,0 +1,61 @@
+use std::fmt;
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use crate::database::Database;
+
+// Window title outside read-only mode
+const APP_TITLE: &str = "ClipSage";
+
+/// Returned by commands that would write while a database is open read-only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOnlyError;
+
+impl fmt::Display for ReadOnlyError {
+    // `read_only` is matched by the frontend, like `locked`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "read_only")
+    }
+}
+
+impl std::error::Error for ReadOnlyError {}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadOnlyStatus {
+    pub path: String,
+    pub opened_at: DateTime<Utc>,
+}
+
+/// The live database, set aside while another file is browsed read-only.
+#[derive(Default)]
+pub struct ReadOnlyMode(Mutex<Option<(Database, ReadOnlyStatus)>>);
+
+impl ReadOnlyMode {
+    pub fn status(&self) -> Option<ReadOnlyStatus> {
+        self.0.lock().unwrap().as_ref().map(|(_, status)| status.clone())
+    }
+
+    /// Keeps `live` until `exit`. Fails if a read-only session is already
+    /// open, handing `live` back.
+    pub fn enter(&self, live: Database, status: ReadOnlyStatus) -> Result<(), Database> {
+        let mut session = self.0.lock().unwrap();
+        if session.is_some() {
+            return Err(live);
+        }
+        *session = Some((live, status));
+        Ok(())
+    }
+
+    /// The live database to put back, if a read-only session is open.
+    pub fn exit(&self) -> Option<Database> {
+        self.0.lock().unwrap().take().map(|(live, _)| live)
+    }
+}
+
+/// Window title for the given read-only session, or the plain app name.
+pub fn window_title(status: Option<&ReadOnlyStatus>) -> String {
+    match status {
+        Some(status) => format!("{} (read-only: {})", APP_TITLE, status.path),
+        None => APP_TITLE.to_string(),
+    }
+}
@@ -0,0 +1,170 @@
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::sqlite::SqlitePoolOptions;
+use crate::database::{Database, DatabaseConfig};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryReport {
+    /// Where the damaged database was moved for manual inspection
+    pub backup_path: String,
+    pub integrity_errors: Vec<String>,
+    pub recovered_clips: u64,
+    pub unreadable_rows: u64,
+    /// True when nothing could be salvaged and the app started empty
+    pub fresh_start: bool,
+}
+
+/// Opens the database at `db_path`, running `PRAGMA quick_check` first. A
+/// damaged file is moved aside, readable clips are copied into a fresh
+/// database, and the FTS index is rebuilt. The app always gets a working
+/// database unless even a fresh one can't be created.
+pub async fn open_database(db_path: &Path, db_config: &DatabaseConfig) -> Result<(Database, Option<RecoveryReport>)> {
+    let integrity_errors = if db_path.exists() {
+        quick_check(db_path).await
+    } else {
+        Vec::new()
+    };
+
+    if integrity_errors.is_empty() {
+        match Database::new(&database_url(db_path), db_config).await {
+            Ok(db) => return Ok((db, None)),
+            Err(e) if db_path.exists() => return recover(db_path, db_config, vec![e.to_string()]).await,
+            Err(e) => return Err(e),
+        }
+    }
+
+    recover(db_path, db_config, integrity_errors).await
+}
+
+async fn recover(
+    db_path: &Path,
+    db_config: &DatabaseConfig,
+    integrity_errors: Vec<String>,
+) -> Result<(Database, Option<RecoveryReport>)> {
+    eprintln!("Database at {} is damaged: {:?}", db_path.display(), integrity_errors);
+
+    let backup_path = backup_damaged(db_path)?;
+    println!("Moved damaged database to {}", backup_path.display());
+
+    let db = Database::new(&database_url(db_path), db_config).await?;
+
+    let mut report = RecoveryReport {
+        backup_path: backup_path.display().to_string(),
+        integrity_errors,
+        recovered_clips: 0,
+        unreadable_rows: 0,
+        fresh_start: true,
+    };
+
+    match db.salvage_clips_from(&backup_path).await {
+        Ok(salvage) => {
+            report.recovered_clips = salvage.recovered_clips;
+            report.unreadable_rows = salvage.unreadable_rows;
+            report.fresh_start = salvage.recovered_clips == 0;
+        }
+        Err(e) => eprintln!("Failed to salvage clips: {}", e),
+    }
+
+    if let Err(e) = db.repair_index(|_| {}).await {
+        eprintln!("Failed to rebuild search index after recovery: {}", e);
+    }
+
+    println!(
+        "Recovered {} clips ({} unreadable rows)",
+        report.recovered_clips, report.unreadable_rows
+    );
+
+    Ok((db, Some(report)))
+}
+
+// Returns the problems reported by `PRAGMA quick_check`, or the error that
+// stopped it from running at all. Empty means the file is healthy.
+async fn quick_check(db_path: &Path) -> Vec<String> {
+    let pool = match SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&format!("sqlite://{}?mode=ro", db_path.display()))
+        .await
+    {
+        Ok(pool) => pool,
+        Err(e) => return vec![e.to_string()],
+    };
+
+    let result = sqlx::query_scalar::<_, String>("PRAGMA quick_check")
+        .fetch_all(&pool)
+        .await;
+    pool.close().await;
+
+    match result {
+        Ok(lines) if lines.len() == 1 && lines[0] == "ok" => Vec::new(),
+        Ok(lines) => lines,
+        Err(e) => vec![e.to_string()],
+    }
+}
+
+// Moves the damaged file (and its WAL/SHM siblings) next to the original
+// with a timestamped name. Nothing is deleted.
+fn backup_damaged(db_path: &Path) -> Result<PathBuf> {
+    let stamp = Utc::now().format("%Y%m%d-%H%M%S");
+    let file_name = db_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "clipsage.db".to_string());
+    let backup_path = db_path.with_file_name(format!("{}.corrupt-{}", file_name, stamp));
+
+    std::fs::rename(db_path, &backup_path)?;
+    for suffix in ["-wal", "-shm"] {
+        let sibling = db_path.with_file_name(format!("{}{}", file_name, suffix));
+        if sibling.exists() {
+            let sibling_backup = backup_path.with_file_name(format!("{}.corrupt-{}{}", file_name, stamp, suffix));
+            if let Err(e) = std::fs::rename(&sibling, &sibling_backup) {
+                eprintln!("Failed to move {} aside: {}", sibling.display(), e);
+            }
+        }
+    }
+
+    Ok(backup_path)
+}
+
+fn database_url(db_path: &Path) -> String {
+    format!("sqlite://{}?mode=rwc", db_path.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn healthy_database_opens_without_recovery() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("clipsage.db");
+        let (db, _) = open_database(&db_path, &DatabaseConfig::default()).await.unwrap();
+        drop(db);
+
+        let (_, report) = open_database(&db_path, &DatabaseConfig::default()).await.unwrap();
+        assert!(report.is_none());
+    }
+
+    #[tokio::test]
+    async fn corrupt_database_is_moved_aside_and_replaced() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("clipsage.db");
+        let garbage: Vec<u8> = (0..4096u32).map(|i| (i * 31 % 251) as u8).collect();
+        std::fs::write(&db_path, &garbage).unwrap();
+
+        let (db, report) = open_database(&db_path, &DatabaseConfig::default()).await.unwrap();
+        let report = report.expect("a damaged database should be reported");
+
+        assert!(!report.integrity_errors.is_empty());
+        assert!(report.fresh_start);
+        assert_eq!(report.recovered_clips, 0);
+        let backup_path = Path::new(&report.backup_path);
+        assert_ne!(backup_path, db_path);
+        assert_eq!(std::fs::read(backup_path).unwrap(), garbage);
+
+        assert!(db.get_recent_clips(10).await.unwrap().is_empty());
+        assert!(db_path.exists());
+    }
+}
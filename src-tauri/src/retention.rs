@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use crate::database::ClipPreview;
+
+/// Tag of the rule for clips that no other rule covers
+pub const DEFAULT_RULE_TAG: &str = "default";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionRule {
+    pub id: String,
+    /// Tag the rule covers, or `default`
+    pub tag: String,
+    /// Delete clips older than this many days; `None` ignores age
+    pub max_age_days: Option<u32>,
+    /// Keep only this many of the newest clips; `None` keeps any number
+    pub max_count: Option<u32>,
+}
+
+impl RetentionRule {
+    pub fn is_default(&self) -> bool {
+        self.tag.eq_ignore_ascii_case(DEFAULT_RULE_TAG)
+    }
+
+    // Orders rules by how long they keep clips: no limit beats any limit,
+    // age counts before count
+    fn leniency(&self) -> (u32, u32) {
+        (self.max_age_days.unwrap_or(u32::MAX), self.max_count.unwrap_or(u32::MAX))
+    }
+}
+
+/// Clips one rule deleted (or would delete).
+#[derive(Debug, Clone, Serialize)]
+pub struct RulePrune {
+    pub rule_id: String,
+    pub tag: String,
+    pub clips: Vec<ClipPreview>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PruneReport {
+    pub deleted: u64,
+    pub by_rule: Vec<RulePrune>,
+}
+
+/// What the planner needs to know about a clip.
+#[derive(Debug, Clone)]
+pub struct RetentionCandidate {
+    pub id: String,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Picks the clips to delete from `clips` (newest first, pinned clips
+/// already left out). A clip is governed by the rules for its tags, or by
+/// the default rule if none of its tags has one, and survives if any of
+/// them keeps it. Each deleted clip is returned with the index of the most
+/// lenient rule that applied, which is the one reported as deleting it.
+pub fn plan(rules: &[RetentionRule], clips: &[RetentionCandidate], now: DateTime<Utc>) -> Vec<(String, usize)> {
+    let mut seen = vec![0u32; rules.len()];
+    let mut doomed = Vec::new();
+
+    for clip in clips {
+        let mut applicable: Vec<usize> = (0..rules.len())
+            .filter(|&i| !rules[i].is_default() && clip.tags.iter().any(|tag| tag.eq_ignore_ascii_case(&rules[i].tag)))
+            .collect();
+        if applicable.is_empty() {
+            applicable = (0..rules.len()).filter(|&i| rules[i].is_default()).collect();
+        }
+        if applicable.is_empty() {
+            continue;
+        }
+
+        let age_days = (now - clip.created_at).num_days();
+        let mut kept = false;
+        for &i in &applicable {
+            seen[i] += 1;
+            let young_enough = rules[i].max_age_days.map_or(true, |max| age_days < max as i64);
+            let within_count = rules[i].max_count.map_or(true, |max| seen[i] <= max);
+            kept |= young_enough && within_count;
+        }
+
+        if !kept {
+            let decider = applicable
+                .into_iter()
+                .max_by_key(|&i| rules[i].leniency())
+                .expect("applicable rules are not empty");
+            doomed.push((clip.id.clone(), decider));
+        }
+    }
+
+    doomed
+}
@@ -0,0 +1,75 @@
+// FNV-1a, chosen because fingerprints are stored and must not change
+// between builds
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit SimHash of `content`'s words. Case, whitespace and punctuation
+/// around words don't affect it, so copies that differ only in formatting
+/// get the same fingerprint and similar texts get nearby ones. `None` when
+/// there are no words to hash.
+pub fn fingerprint(content: &str) -> Option<u64> {
+    let mut weights = [0i32; 64];
+    let words: Vec<String> = content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    for word in &words {
+        let hash = fnv1a(word.as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .filter(|(_, weight)| **weight > 0)
+            .fold(0, |fingerprint, (bit, _)| fingerprint | (1 << bit)),
+    )
+}
+
+/// Number of bits two fingerprints differ in, 0 to 64.
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(FNV_OFFSET, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting_only_variants_share_a_fingerprint() {
+        let original = fingerprint("Meeting moved to Thursday, 3pm.").unwrap();
+        for variant in ["meeting   moved to thursday 3pm", "MEETING MOVED TO THURSDAY -- 3PM!", "\tMeeting moved\nto Thursday, 3pm  "] {
+            assert_eq!(distance(original, fingerprint(variant).unwrap()), 0, "{:?}", variant);
+        }
+    }
+
+    #[test]
+    fn different_content_does_not_collide() {
+        let meeting = fingerprint("Meeting moved to Thursday, 3pm.").unwrap();
+        let invoice = fingerprint("Invoice 4471 is overdue, please pay by Friday").unwrap();
+        assert!(distance(meeting, invoice) > 3);
+    }
+
+    #[test]
+    fn text_without_words_has_no_fingerprint() {
+        assert_eq!(fingerprint("  -- !! \n"), None);
+    }
+}
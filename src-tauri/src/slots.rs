@@ -0,0 +1,99 @@
+use std::str::FromStr;
+use std::sync::Mutex;
+use anyhow::{anyhow, Result};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+use crate::clipboard::SelfWriteState;
+use crate::config::AppConfig;
+use crate::database::{SlotAssignment, SLOT_COUNT};
+use crate::lock::AppLock;
+use crate::DbState;
+
+/// Emitted with the slot numbers that lost their clip
+pub const SLOTS_CLEARED_EVENT: &str = "slots://cleared";
+
+/// Global shortcuts currently registered for slots, with their slot number.
+#[derive(Debug, Default)]
+pub struct SlotShortcuts(Mutex<Vec<(Shortcut, u8)>>);
+
+impl SlotShortcuts {
+    pub fn slot_for(&self, shortcut: &Shortcut) -> Option<u8> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(registered, _)| registered == shortcut)
+            .map(|(_, slot)| *slot)
+    }
+}
+
+/// (Re-)registers `{slot_hotkey_modifiers}+1` .. `+9`, dropping any previous
+/// slot shortcuts first. Slots whose shortcut is taken by another app are
+/// skipped with a warning.
+pub fn register_slot_shortcuts(app: &AppHandle, config: &AppConfig) -> Result<()> {
+    let state = app.state::<SlotShortcuts>();
+    let mut registered = state.0.lock().unwrap();
+
+    for (shortcut, _) in registered.drain(..) {
+        let _ = app.global_shortcut().unregister(shortcut);
+    }
+
+    if !config.slot_hotkeys_enabled {
+        return Ok(());
+    }
+
+    for slot in 1..=SLOT_COUNT {
+        let accelerator = format!("{}+{}", config.slot_hotkey_modifiers, slot);
+        let shortcut = Shortcut::from_str(&accelerator)
+            .map_err(|e| anyhow!("Invalid slot shortcut {}: {}", accelerator, e))?;
+
+        match app.global_shortcut().register(shortcut) {
+            Ok(()) => registered.push((shortcut, slot)),
+            Err(e) => eprintln!("Failed to register slot shortcut {}: {}", accelerator, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the clip in `slot` to the clipboard without opening the window.
+/// Does nothing while the app is locked or the slot is empty.
+pub async fn copy_slot(app: AppHandle, slot: u8) {
+    if app.state::<AppLock>().check().is_err() {
+        return;
+    }
+    let Some(db) = app.try_state::<DbState>() else {
+        return;
+    };
+
+    let db = db.lock().await;
+    let clip = match db.get_slot_clip(slot).await {
+        Ok(Some(clip)) => clip,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("Failed to load slot {}: {}", slot, e);
+            return;
+        }
+    };
+
+    if let Err(e) = app.state::<SelfWriteState>().write_text(&clip.content) {
+        eprintln!("Failed to copy slot {}: {}", slot, e);
+        return;
+    }
+    if let Err(e) = db.record_access(&clip.id).await {
+        eprintln!("Failed to record access: {}", e);
+    }
+}
+
+/// Tells the UI which slots emptied because their clips were removed.
+pub fn notify_cleared(app: &AppHandle, before: &[SlotAssignment], after: &[SlotAssignment]) {
+    let cleared: Vec<u8> = before
+        .iter()
+        .filter(|old| !after.iter().any(|new| new.slot == old.slot))
+        .map(|old| old.slot)
+        .collect();
+
+    if !cleared.is_empty() {
+        let _ = app.emit(SLOTS_CLEARED_EVENT, cleared);
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::{HashMap, HashSet};
+use serde::Serialize;
+
+// Words shorter than this are never counted
+const MIN_TERM_CHARS: usize = 3;
+
+/// Common English words left out of `top_terms` unless the stopword
+/// setting says otherwise.
+pub const DEFAULT_STOPWORDS: &[&str] = &[
+    "the", "and", "for", "are", "but", "not", "you", "all", "any", "can", "had", "her", "was",
+    "one", "our", "out", "has", "him", "his", "how", "its", "may", "new", "now", "old", "see",
+    "two", "who", "did", "get", "she", "too", "use", "that", "this", "with", "have", "from",
+    "they", "will", "would", "there", "their", "what", "about", "which", "when", "were", "been",
+    "than", "then", "them", "these", "those", "into", "your", "just", "also", "more", "some",
+    "only", "other", "could", "should", "very", "here", "where", "while", "each", "such",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TermCount {
+    pub term: String,
+    pub count: u64,
+}
+
+/// Tallies lowercased words (runs of letters, digits, `_` and `'`) across
+/// `texts`, skipping `stopwords` and very short words.
+#[derive(Debug, Default)]
+pub struct TermCounter {
+    counts: HashMap<String, u64>,
+    stopwords: HashSet<String>,
+}
+
+impl TermCounter {
+    pub fn new(stopwords: &[String]) -> Self {
+        Self {
+            counts: HashMap::new(),
+            stopwords: stopwords.iter().map(|word| word.to_lowercase()).collect(),
+        }
+    }
+
+    pub fn add(&mut self, text: &str) {
+        for word in text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\'')) {
+            let word = word.trim_matches('\'').to_lowercase();
+            if word.chars().count() < MIN_TERM_CHARS
+                || word.chars().all(|c| c.is_ascii_digit())
+                || self.stopwords.contains(&word)
+            {
+                continue;
+            }
+            *self.counts.entry(word).or_default() += 1;
+        }
+    }
+
+    /// The `limit` most frequent terms, most frequent first; ties are
+    /// alphabetical.
+    pub fn top(self, limit: usize) -> Vec<TermCount> {
+        let mut terms: Vec<TermCount> = self
+            .counts
+            .into_iter()
+            .map(|(term, count)| TermCount { term, count })
+            .collect();
+        terms.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.term.cmp(&b.term)));
+        terms.truncate(limit);
+        terms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORPUS: &[&str] = &[
+        "The cargo build failed",
+        "cargo test and cargo build",
+        "the build is green now",
+        "Deploy the build, then rerun",
+    ];
+
+    fn top_terms(stopwords: &[String], limit: usize) -> Vec<(String, u64)> {
+        let mut counter = TermCounter::new(stopwords);
+        for text in CORPUS {
+            counter.add(text);
+        }
+        counter.top(limit).into_iter().map(|term| (term.term, term.count)).collect()
+    }
+
+    #[test]
+    fn most_frequent_terms_come_first_without_stopwords() {
+        let stopwords: Vec<String> = DEFAULT_STOPWORDS.iter().map(|word| word.to_string()).collect();
+        let top = top_terms(&stopwords, 3);
+        assert_eq!(top, [("build".to_string(), 4), ("cargo".to_string(), 3), ("deploy".to_string(), 1)]);
+
+        let all = top_terms(&stopwords, 100);
+        for stopword in ["the", "and", "now", "then"] {
+            assert!(all.iter().all(|(term, _)| term != stopword), "{} was counted", stopword);
+        }
+    }
+
+    #[test]
+    fn stopwords_are_configurable() {
+        let top = top_terms(&["BUILD".to_string()], 2);
+        assert_eq!(top, [("cargo".to_string(), 3), ("the".to_string(), 3)]);
+    }
+}
@@ -0,0 +1,196 @@
+use url::Url;
+
+// Top-level domains accepted for bare hosts like `example.com`. Scheme-prefixed
+// and `www.` URLs are accepted regardless of TLD; bare hosts need a known one so
+// that file names (`main.rs`) and abbreviations (`e.g.`) aren't picked up.
+const BARE_DOMAIN_TLDS: &[&str] = &[
+    "com", "org", "net", "io", "dev", "app", "ai", "co", "edu", "gov", "me", "info", "biz",
+    "xyz", "tech", "sh", "so", "gg", "tv", "us", "uk", "de", "fr", "nl", "ca", "au", "jp",
+    "ch", "se", "no", "es", "it", "eu", "in", "ly", "fm",
+];
+
+const LEADING_PUNCTUATION: &[char] = &['(', '[', '{', '<', '"', '\'', '`'];
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', '"', '\'', '`', '>', ']', '}'];
+
+/// Extracts every URL mentioned in `text`, returned in canonical form
+/// (lowercased host, explicit scheme, default port dropped) without duplicates.
+pub fn extract_urls(text: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+
+    for token in text.split_whitespace() {
+        let candidate = trim_token(token);
+        if let Some(url) = canonicalize(candidate) {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+
+    urls
+}
+
+/// Parses a single URL-like string into its canonical form.
+pub fn canonicalize(candidate: &str) -> Option<String> {
+    let lower = candidate.to_ascii_lowercase();
+
+    let parsed = if lower.starts_with("http://") || lower.starts_with("https://") {
+        Url::parse(candidate).ok()?
+    } else if lower.starts_with("www.") {
+        Url::parse(&format!("https://{}", candidate)).ok()?
+    } else if looks_like_bare_domain(candidate) {
+        Url::parse(&format!("https://{}", candidate)).ok()?
+    } else {
+        return None;
+    };
+
+    let host = parsed.host_str()?;
+    if !host.contains('.') && host != "localhost" {
+        return None;
+    }
+
+    Some(parsed.to_string())
+}
+
+/// Removes query parameters matching `denylist` from `url`. Entries ending
+/// in `*` match by prefix; names compare case-insensitively. Text that isn't
+/// an absolute URL is returned unchanged.
+pub fn strip_tracking_params(url: &str, denylist: &[String]) -> String {
+    let Ok(mut parsed) = Url::parse(url.trim()) else {
+        return url.to_string();
+    };
+    if parsed.query().is_none() {
+        return url.to_string();
+    }
+
+    let kept: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(name, _)| !is_tracking_param(name, denylist))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+
+    if kept.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    parsed.to_string()
+}
+
+fn is_tracking_param(name: &str, denylist: &[String]) -> bool {
+    let name = name.to_ascii_lowercase();
+    denylist.iter().any(|pattern| {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        }
+    })
+}
+
+fn trim_token(token: &str) -> &str {
+    let mut trimmed = token.trim_start_matches(LEADING_PUNCTUATION);
+
+    loop {
+        let before = trimmed.len();
+        trimmed = trimmed.trim_end_matches(TRAILING_PUNCTUATION);
+
+        // Only strip a closing paren when it isn't balanced inside the URL,
+        // so `https://en.wikipedia.org/wiki/Rust_(programming_language)` survives.
+        if trimmed.ends_with(')') && trimmed.matches(')').count() > trimmed.matches('(').count() {
+            trimmed = &trimmed[..trimmed.len() - 1];
+        }
+
+        if trimmed.len() == before {
+            return trimmed;
+        }
+    }
+}
+
+fn looks_like_bare_domain(candidate: &str) -> bool {
+    // Emails and user@host forms are handled by the email tagger
+    if candidate.contains('@') {
+        return false;
+    }
+
+    let host = candidate
+        .split(|c| c == '/' || c == '?' || c == '#')
+        .next()
+        .unwrap_or("");
+    let host = host.split(':').next().unwrap_or("");
+
+    let labels: Vec<&str> = host.split('.').collect();
+    if labels.len() < 2 {
+        return false;
+    }
+
+    let valid_labels = labels.iter().all(|label| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+
+    let tld = labels[labels.len() - 1].to_ascii_lowercase();
+    valid_labels && BARE_DOMAIN_TLDS.contains(&tld.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_punctuation_is_not_part_of_the_url() {
+        assert_eq!(extract_urls("See http://a.com."), ["http://a.com/"]);
+        assert_eq!(extract_urls("Docs: <https://docs.rs/url>!"), ["https://docs.rs/url"]);
+    }
+
+    #[test]
+    fn bare_and_www_domains_are_found() {
+        assert_eq!(extract_urls("visit example.com"), ["https://example.com/"]);
+        assert_eq!(extract_urls("try www.Example.com/Path"), ["https://www.example.com/Path"]);
+    }
+
+    #[test]
+    fn urls_are_canonical_and_deduplicated() {
+        let text = "https://a.com/x, HTTPS://A.COM:443/x and (http://b.org:8080/y)";
+        assert_eq!(extract_urls(text), ["https://a.com/x", "http://b.org:8080/y"]);
+    }
+
+    #[test]
+    fn balanced_parentheses_stay_in_the_url() {
+        assert_eq!(
+            extract_urls("(see https://en.wikipedia.org/wiki/Rust_(programming_language))"),
+            ["https://en.wikipedia.org/wiki/Rust_(programming_language)"]
+        );
+    }
+
+    #[test]
+    fn prose_file_names_and_emails_are_not_urls() {
+        for text in ["the http protocol", "edit main.rs first", "e.g. this one", "mail user@example.com"] {
+            assert!(extract_urls(text).is_empty(), "{}", text);
+        }
+    }
+
+    fn denylist() -> Vec<String> {
+        vec!["utm_*".to_string(), "fbclid".to_string()]
+    }
+
+    #[test]
+    fn tracking_params_are_stripped() {
+        assert_eq!(
+            strip_tracking_params("https://example.com/page?utm_source=news&id=5&fbclid=abc", &denylist()),
+            "https://example.com/page?id=5"
+        );
+        assert_eq!(
+            strip_tracking_params("https://example.com/page?UTM_Campaign=spring&fbclid=abc", &denylist()),
+            "https://example.com/page"
+        );
+    }
+
+    #[test]
+    fn urls_without_tracking_params_are_unchanged() {
+        for url in ["https://example.com/page", "https://example.com/search?q=rust&page=2", "not a url at all"] {
+            assert_eq!(strip_tracking_params(url, &denylist()), url);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// How long a token from `request_wipe_token` stays valid
+pub const WIPE_TOKEN_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WipeReport {
+    /// Rows deleted per table
+    pub tables: std::collections::BTreeMap<String, u64>,
+    pub settings_cleared: bool,
+    /// Backup and archive files removed from the data directory
+    pub files_deleted: Vec<String>,
+}
+
+/// The single outstanding wipe confirmation token, if any.
+#[derive(Debug, Default)]
+pub struct WipeToken(Mutex<Option<(String, Instant)>>);
+
+impl WipeToken {
+    /// Issues a fresh token, invalidating any earlier one.
+    pub fn issue(&self) -> String {
+        let token = Uuid::new_v4().to_string();
+        *self.0.lock().unwrap() = Some((token.clone(), Instant::now()));
+        token
+    }
+
+    /// Consumes the outstanding token if it matches `token` and hasn't
+    /// expired. A token can only be redeemed once; a wrong guess leaves it
+    /// in place.
+    pub fn redeem(&self, token: &str) -> Result<(), String> {
+        let mut outstanding = self.0.lock().unwrap();
+        match outstanding.as_ref() {
+            Some((_, at)) if at.elapsed() > WIPE_TOKEN_TTL => {
+                outstanding.take();
+                Err("Wipe confirmation token is invalid or has expired".to_string())
+            }
+            Some((issued, _)) if issued == token => {
+                outstanding.take();
+                Ok(())
+            }
+            Some(_) => Err("Wipe confirmation token is invalid or has expired".to_string()),
+            None => Err("Request a wipe confirmation token first".to_string()),
+        }
+    }
+}
+
+/// Deletes files ClipSage created next to its database: copies set aside by
+/// startup recovery (`clipsage.db.corrupt-*`) and the archive database.
+/// Returns the names removed; files that can't be removed are reported and
+/// skipped.
+pub fn delete_backup_files(data_dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(data_dir) else {
+        return Vec::new();
+    };
+
+    let mut deleted = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !(name.starts_with("clipsage.db.corrupt-") || name.starts_with("clipsage-archive.db")) {
+            continue;
+        }
+        match std::fs::remove_file(entry.path()) {
+            Ok(()) => deleted.push(name),
+            Err(e) => eprintln!("Failed to delete {}: {}", name, e),
+        }
+    }
+
+    deleted.sort();
+    deleted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_wrong_token_leaves_the_real_one_redeemable() {
+        let tokens = WipeToken::default();
+        let token = tokens.issue();
+
+        assert!(tokens.redeem("not-the-token").is_err());
+        assert_eq!(tokens.redeem(&token), Ok(()));
+        assert!(tokens.redeem(&token).is_err());
+    }
+}
@@ -1,33 +1,52 @@
 import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { Search, Clock, Tag, FileText, Copy, X, Filter } from "lucide-react";
+import { Search, Clock, Tag, FileText, Copy, X, Filter, Lock, Pin } from "lucide-react";
 import "./App.css";
 
 interface ClipItem {
   id: string;
-  content: string;
+  preview: string;
   summary: string;
   tags: string[];
-  timestamp: string;
+  created_at: string;
+  last_modified: string;
   source?: string;
+  masked: boolean;
 }
 
 function App() {
   const [query, setQuery] = useState("");
   const [results, setResults] = useState<ClipItem[]>([]);
+  const [pins, setPins] = useState<ClipItem[]>([]);
   const [selectedIndex, setSelectedIndex] = useState(0);
   const [loading, setLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
   const [activeTags, setActiveTags] = useState<string[]>([]);
   const [showFilters, setShowFilters] = useState(false);
+  const [suggestions, setSuggestions] = useState<string[]>([]);
+  const [locked, setLocked] = useState(false);
+  const [pin, setPin] = useState("");
   const inputRef = useRef<HTMLInputElement>(null);
 
-  // Load initial clips
+  // Load initial clips; pins load separately so a slow list never hides them
   useEffect(() => {
     inputRef.current?.focus();
+    loadPins();
     loadRecentClips();
   }, []);
 
+  const loadPins = async () => {
+    try {
+      setPins(await invoke<ClipItem[]>("get_global_pins"));
+    } catch (err) {
+      if (String(err) === "locked") {
+        setLocked(true);
+        return;
+      }
+      console.error("Failed to load pinned clips:", err);
+    }
+  };
+
   const loadRecentClips = async () => {
     try {
       setLoading(true);
@@ -36,6 +55,10 @@ function App() {
       setResults(clips);
       setSelectedIndex(0);
     } catch (err) {
+      if (String(err) === "locked") {
+        setLocked(true);
+        return;
+      }
       setError("Failed to load clips: " + String(err));
       console.error("Failed to load clips:", err);
     } finally {
@@ -50,7 +73,15 @@ function App() {
       const clips = await invoke<ClipItem[]>("search_clips", { query: searchQuery });
       setResults(clips);
       setSelectedIndex(0);
+      setSuggestions([]);
+      if (clips.length === 0) {
+        loadSuggestions(searchQuery, results.slice(0, 5).map(clip => clip.id));
+      }
     } catch (err) {
+      if (String(err) === "locked") {
+        setLocked(true);
+        return;
+      }
       setError("Failed to search clips: " + String(err));
       console.error("Failed to search clips:", err);
     } finally {
@@ -58,6 +89,18 @@ function App() {
     }
   };
 
+  const loadSuggestions = async (searchQuery: string, contextClipIds: string[]) => {
+    try {
+      const terms = await invoke<string[]>("suggest_search_terms", {
+        query: searchQuery,
+        contextClipIds,
+      });
+      setSuggestions(terms);
+    } catch (err) {
+      console.error("Failed to load search suggestions:", err);
+    }
+  };
+
   useEffect(() => {
     const timeoutId = setTimeout(() => {
       if (query.trim()) {
@@ -90,13 +133,27 @@ function App() {
 
   const handleSelectItem = async (item: ClipItem) => {
     try {
-      await navigator.clipboard.writeText(item.content);
+      await invoke("copy_clip", { id: item.id });
       await invoke("hide_window");
     } catch (error) {
       console.error("Failed to copy to clipboard:", error);
     }
   };
 
+  const handleUnlock = async (e: React.FormEvent) => {
+    e.preventDefault();
+    try {
+      setError(null);
+      await invoke("unlock_app", { pin });
+      setPin("");
+      setLocked(false);
+      loadPins();
+      loadRecentClips();
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
   const formatTimestamp = (timestamp: string) => {
     const date = new Date(timestamp);
     const now = new Date();
@@ -126,6 +183,32 @@ function App() {
 
   const allTags = Array.from(new Set(results.flatMap(item => item.tags)));
 
+  if (locked) {
+    return (
+      <div className="w-full h-screen flex items-start justify-center pt-20 px-4">
+        <form onSubmit={handleUnlock} className="search-container w-full max-w-sm">
+          <div className="flex items-center px-2">
+            <Lock className="w-5 h-5 text-gray-400 ml-4" />
+            <input
+              autoFocus
+              type="password"
+              value={pin}
+              onChange={(e) => setPin(e.target.value)}
+              onKeyDown={(e) => e.key === "Escape" && invoke("hide_window")}
+              placeholder="Enter PIN to unlock"
+              className="search-input"
+            />
+          </div>
+          {error && (
+            <div className="px-6 py-4 text-red-600 text-sm">
+              {error}
+            </div>
+          )}
+        </form>
+      </div>
+    );
+  }
+
   return (
     <div className="w-full h-screen flex items-start justify-center pt-20 px-4">
       <div className="search-container w-full max-w-2xl">
@@ -158,6 +241,22 @@ function App() {
           </button>
         </div>
 
+        {pins.length > 0 && (
+          <div className="px-6 py-2 border-t border-gray-100/50 flex items-center gap-2 overflow-x-auto">
+            <Pin className="w-3 h-3 text-gray-400 flex-shrink-0" />
+            {pins.map(item => (
+              <button
+                key={item.id}
+                onClick={() => handleSelectItem(item)}
+                className="tag max-w-[10rem] truncate"
+                title={item.preview}
+              >
+                {item.summary}
+              </button>
+            ))}
+          </div>
+        )}
+
         {showFilters && (
           <div className="px-6 py-3 border-t border-gray-100/50">
             <div className="flex flex-wrap gap-2">
@@ -189,6 +288,16 @@ function App() {
         {!loading && !error && results.length === 0 && (
           <div className="px-6 py-8 text-center text-gray-500">
             {query.trim() ? "No clips found matching your search." : "No clips yet. Start copying text to build your clipboard history!"}
+            {query.trim() && suggestions.length > 0 && (
+              <div className="mt-4 flex flex-wrap items-center justify-center gap-2">
+                <span className="text-xs">Did you mean…?</span>
+                {suggestions.map(term => (
+                  <button key={term} onClick={() => setQuery(term)} className="tag">
+                    {term}
+                  </button>
+                ))}
+              </div>
+            )}
           </div>
         )}
 
@@ -209,15 +318,12 @@ function App() {
                       </p>
                     </div>
                     <p className="text-sm text-gray-600 line-clamp-2 mb-2">
-                      {item.content.length > 120 
-                        ? `${item.content.substring(0, 120)}...` 
-                        : item.content
-                      }
+                      {item.preview}
                     </p>
                     <div className="flex items-center gap-3 text-xs text-gray-500">
                       <div className="flex items-center gap-1">
                         <Clock className="w-3 h-3" />
-                        {formatTimestamp(item.timestamp)}
+                        {formatTimestamp(item.created_at)}
                       </div>
                       {item.source && (
                         <span>from {item.source}</span>